@@ -0,0 +1,104 @@
+// シミュレーション結果の集計
+// スレッドやWeb Worker、別のセッションで回した結果をそのまま足し合わせられる形で持つ
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{AddAssign, MulAssign};
+
+// 足し合わせられない結果
+#[derive(Debug, PartialEq)]
+pub enum MergeError {
+    Season { expected: String, found: String },
+    // 形が合わないフィールド
+    Shape(String),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::Season { expected, found } => write!(
+                f,
+                "cannot merge a result of season {} into season {}",
+                found, expected
+            ),
+            MergeError::Shape(field) => write!(f, "cannot merge results: {} differs", field),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+// ある順位になった回数と、そのときのポイントの合計・最高・最低
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PointRange {
+    pub time: usize,
+    pub sum_point: usize,
+    // 一度もその順位にならなかった場合はNone
+    pub highest_point: Option<usize>,
+    pub lowest_point: Option<usize>,
+}
+
+impl PointRange {
+    pub fn add(&mut self, point: usize) {
+        self.time += 1;
+        self.sum_point += point;
+        self.update_range(point);
+    }
+
+    // 回数は数えずに最高・最低だけ更新する
+    pub fn update_range(&mut self, point: usize) {
+        self.highest_point = Some(self.highest_point.map_or(point, |p| p.max(point)));
+        self.lowest_point = Some(self.lowest_point.map_or(point, |p| p.min(point)));
+    }
+
+    pub fn merge(&mut self, other: &PointRange) {
+        self.time += other.time;
+        self.sum_point += other.sum_point;
+        self.highest_point = match (self.highest_point, other.highest_point) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.lowest_point = match (self.lowest_point, other.lowest_point) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    // 最高・最低は回数によらない
+    pub fn scale(&mut self, factor: usize) {
+        self.time *= factor;
+        self.sum_point *= factor;
+        if factor == 0 {
+            self.highest_point = None;
+            self.lowest_point = None;
+        }
+    }
+}
+
+// 形は check_shape で先に確かめておく
+pub fn merge_counts<T: Copy + AddAssign>(counts: &mut [Vec<T>], other: &[Vec<T>]) {
+    for (counts, other) in counts.iter_mut().zip(other.iter()) {
+        for (count, other) in counts.iter_mut().zip(other.iter()) {
+            *count += *other;
+        }
+    }
+}
+
+pub fn check_shape<T, U>(
+    field: &str,
+    values: &[Vec<T>],
+    other: &[Vec<U>],
+) -> Result<(), MergeError> {
+    if values.len() == other.len() && values.iter().zip(other).all(|(a, b)| a.len() == b.len()) {
+        Ok(())
+    } else {
+        Err(MergeError::Shape(field.to_string()))
+    }
+}
+
+pub fn scale_counts<T: Copy + MulAssign>(counts: &mut [Vec<T>], factor: T) {
+    for count in counts.iter_mut().flatten() {
+        *count *= factor;
+    }
+}
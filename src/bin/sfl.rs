@@ -0,0 +1,414 @@
+use serde_json::{json, Value};
+use sfl_lib::results::{parse_csv, parse_json};
+use sfl_lib::season::Season;
+use sfl_lib::{SflSimulation, DEFAULT_SEED};
+use std::fs;
+use std::io::Write;
+use std::process::exit;
+
+const USAGE: &str = "usage: sfl <command> [options]
+
+commands:
+  simulate   division place, playoff and champion probabilities
+  standings  current points and battle differential
+  ratings    team ratings from the results
+  predict    score distribution of each remaining match
+
+options:
+  --season <file>   season JSON (default: bundled jp2024)
+  --results <file>  results CSV (.csv) or JSON, see docs/results.md
+  --count <n>       number of simulations (default: 10000)
+  --seed <n>        random seed
+  --json            print JSON instead of a table";
+
+struct Options {
+    command: String,
+    season: Option<String>,
+    results: Option<String>,
+    count: usize,
+    seed: u64,
+    json: bool,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        // パイプの先が閉じていても失敗扱いにしない
+        Ok(output) => {
+            let _ = writeln!(std::io::stdout(), "{}", output);
+        }
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            exit(2);
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+    let options = parse_args(args)?;
+    if options.command == "help" {
+        return Ok(USAGE.to_string());
+    }
+    let mut simulation = load_simulation(&options)?;
+    let (header, rows, value) = match options.command.as_str() {
+        "simulate" => simulate(&mut simulation, &options),
+        "standings" => standings(&simulation),
+        "ratings" => ratings(&simulation),
+        "predict" => predict(&simulation),
+        command => return Err(format!("unknown command: {}", command)),
+    };
+    if options.json {
+        serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+    } else {
+        Ok(format_table(&header, &rows))
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        command: String::new(),
+        season: None,
+        results: None,
+        count: 10000,
+        seed: DEFAULT_SEED,
+        json: false,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or(format!("{} needs a value", name))
+        };
+        match arg.as_str() {
+            "--season" => options.season = Some(value(arg)?),
+            "--results" => options.results = Some(value(arg)?),
+            "--count" => {
+                options.count = value(arg)?
+                    .parse()
+                    .map_err(|_| "--count must be a number".to_string())?
+            }
+            "--seed" => {
+                options.seed = value(arg)?
+                    .parse()
+                    .map_err(|_| "--seed must be a number".to_string())?
+            }
+            "--json" => options.json = true,
+            "-h" | "--help" => options.command = "help".to_string(),
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+            _ if options.command.is_empty() => options.command = arg.to_owned(),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    if options.command.is_empty() {
+        return Err("no command".to_string());
+    }
+    Ok(options)
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn load_simulation(options: &Options) -> Result<SflSimulation, String> {
+    let season = match options.season.as_ref() {
+        Some(path) => Season::from_json(&read_file(path)?).map_err(|e| e.to_string())?,
+        None => Season::jp2024(),
+    };
+    let mut simulation = SflSimulation::from_season(season);
+    simulation.count = options.count;
+    simulation.seed = options.seed;
+    let Some(path) = options.results.as_ref() else {
+        return Ok(simulation);
+    };
+    let text = read_file(path)?;
+    let results = if path.ends_with(".csv") {
+        parse_csv(&text)
+    } else {
+        parse_json(&text)
+    }
+    .map_err(|e| format!("{}: {}", path, e))?;
+    simulation
+        .import_results(&results)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    Ok(simulation)
+}
+
+type Output = (Vec<&'static str>, Vec<Vec<String>>, Value);
+
+fn percent(probability: f64) -> String {
+    format!("{:.1}%", probability * 100_f64)
+}
+
+// スコアが1通りに決まっていれば終わった試合
+fn is_finished(simulation: &SflSimulation, match_index: usize) -> bool {
+    simulation.get_match_score_distribution(match_index).len() == 1
+}
+
+struct Team {
+    division_index: usize,
+    division: String,
+    team_index: usize,
+    code: String,
+}
+
+// ディビジョンの順、ディビジョン内はシーズンに書かれた順
+fn get_teams(simulation: &SflSimulation) -> Vec<Team> {
+    let season = simulation.get_season();
+    season
+        .divisions
+        .iter()
+        .enumerate()
+        .flat_map(|(division_index, division)| {
+            division.teams.iter().map(move |team| Team {
+                division_index,
+                division: division.name.to_owned(),
+                team_index: team.get_index(),
+                code: season.get_team_code(team).to_string(),
+            })
+        })
+        .collect()
+}
+
+fn simulate(simulation: &mut SflSimulation, options: &Options) -> Output {
+    simulation.simulate();
+    let result = simulation.get_result();
+    let count = result.count.max(1) as f64;
+    let expected =
+        |team: &Team, n: usize| result.division_points_battles[team.team_index][n] as f64 / count;
+    let mut teams = get_teams(simulation);
+    teams.sort_by(|a, b| {
+        a.division_index
+            .cmp(&b.division_index)
+            .then(expected(b, 0).total_cmp(&expected(a, 0)))
+    });
+    let mut rows = vec![];
+    let mut values = vec![];
+    for team in teams.iter() {
+        let place_probability: Vec<f64> = result.division_place_count[team.team_index]
+            .iter()
+            .map(|c| *c as f64 / count)
+            .collect();
+        let playoff_probability: f64 = place_probability
+            .iter()
+            .take(simulation.get_season().playoff_team_count)
+            .sum();
+        let champion_probability = result.playoff_place_count[team.team_index][0] as f64 / count;
+        let (point, battle) = (expected(team, 0), expected(team, 1));
+        rows.push(vec![
+            team.division.to_owned(),
+            team.code.to_owned(),
+            format!("{:.1}", point),
+            format!("{:.1}", battle),
+            percent(place_probability[0]),
+            percent(playoff_probability),
+            percent(champion_probability),
+        ]);
+        values.push(json!({
+            "team": team.code,
+            "division": team.division,
+            "expected_point": point,
+            "expected_battle": battle,
+            "place_probability": place_probability,
+            "playoff_probability": playoff_probability,
+            "champion_probability": champion_probability,
+        }));
+    }
+    let value = json!({
+        "season": simulation.get_season().id,
+        "count": result.count,
+        "seed": options.seed,
+        "exact": simulation.is_exact(),
+        "teams": values,
+    });
+    (
+        vec![
+            "division", "team", "point", "battle", "first", "playoff", "champion",
+        ],
+        rows,
+        value,
+    )
+}
+
+fn standings(simulation: &SflSimulation) -> Output {
+    let stats = simulation.sfl_stats();
+    let (points, battles) = (stats.get_points(), stats.get_battles());
+    let matches = simulation.get_matches();
+    let played = |team: &Team| {
+        matches
+            .iter()
+            .enumerate()
+            .filter(|(match_index, m)| {
+                (m.team() == team.team_index || m.opponent_team() == team.team_index)
+                    && is_finished(simulation, *match_index)
+            })
+            .count()
+    };
+    let mut teams = get_teams(simulation);
+    teams.sort_by(|a, b| {
+        a.division_index
+            .cmp(&b.division_index)
+            .then(points[b.team_index].cmp(&points[a.team_index]))
+            .then(battles[b.team_index].cmp(&battles[a.team_index]))
+    });
+    let mut rows = vec![];
+    let mut values = vec![];
+    for team in teams.iter() {
+        let (played, point, battle) = (
+            played(team),
+            points[team.team_index],
+            battles[team.team_index],
+        );
+        rows.push(vec![
+            team.division.to_owned(),
+            team.code.to_owned(),
+            played.to_string(),
+            point.to_string(),
+            battle.to_string(),
+        ]);
+        values.push(json!({
+            "team": team.code,
+            "division": team.division,
+            "played": played,
+            "point": point,
+            "battle": battle,
+        }));
+    }
+    (
+        vec!["division", "team", "played", "point", "battle"],
+        rows,
+        json!({ "teams": values }),
+    )
+}
+
+fn ratings(simulation: &SflSimulation) -> Output {
+    let mut rows = vec![];
+    let mut values = vec![];
+    for team in get_teams(simulation) {
+        // AWAY, AWAY大将, HOME, HOME大将
+        let ratings: Vec<f64> = [(false, false), (false, true), (true, false), (true, true)]
+            .iter()
+            .map(|(is_home, is_leader)| {
+                simulation.get_rating(team.team_index, *is_home, *is_leader)
+            })
+            .collect();
+        let mut row = vec![team.division.to_owned(), team.code.to_owned()];
+        row.extend(ratings.iter().map(|rating| format!("{:.1}", rating)));
+        rows.push(row);
+        values.push(json!({
+            "team": team.code,
+            "division": team.division,
+            "away": ratings[0],
+            "away_leader": ratings[1],
+            "home": ratings[2],
+            "home_leader": ratings[3],
+        }));
+    }
+    (
+        vec![
+            "division",
+            "team",
+            "away",
+            "away_leader",
+            "home",
+            "home_leader",
+        ],
+        rows,
+        json!({ "teams": values }),
+    )
+}
+
+fn predict(simulation: &SflSimulation) -> Output {
+    let season = simulation.get_season();
+    let mut rows = vec![];
+    let mut values = vec![];
+    for (match_index, m) in simulation.get_matches().iter().enumerate() {
+        if is_finished(simulation, match_index) {
+            continue;
+        }
+        let scores = simulation.get_match_score_distribution(match_index);
+        let away_win: f64 = scores
+            .iter()
+            .filter(|score| score.team_point > score.opponent_team_point)
+            .map(|score| score.probability)
+            .sum();
+        let expected = |point: fn(&(u32, u32)) -> u32| -> f64 {
+            scores
+                .iter()
+                .map(|score| {
+                    point(&(score.team_point, score.opponent_team_point)) as f64 * score.probability
+                })
+                .sum()
+        };
+        let (away_point, home_point) = (expected(|p| p.0), expected(|p| p.1));
+        let likely = scores
+            .iter()
+            .max_by(|a, b| a.probability.total_cmp(&b.probability));
+        let (away, home) = (
+            season.get_team_code(&m.team).to_string(),
+            season.get_team_code(&m.opponent_team).to_string(),
+        );
+        rows.push(vec![
+            m.section.to_string(),
+            m.date_expression(),
+            away.to_owned(),
+            home.to_owned(),
+            percent(away_win),
+            format!("{:.1}-{:.1}", away_point, home_point),
+            likely.map_or("".to_string(), |score| {
+                format!(
+                    "{}-{} ({})",
+                    score.team_point,
+                    score.opponent_team_point,
+                    percent(score.probability)
+                )
+            }),
+        ]);
+        values.push(json!({
+            "section": m.section,
+            "date": m.date_expression(),
+            "away": away,
+            "home": home,
+            "away_win_probability": away_win,
+            "expected_away_point": away_point,
+            "expected_home_point": home_point,
+            "scores": scores
+                .iter()
+                .map(|score| json!({
+                    "away_point": score.team_point,
+                    "home_point": score.opponent_team_point,
+                    "probability": score.probability,
+                }))
+                .collect::<Vec<Value>>(),
+        }));
+    }
+    (
+        vec![
+            "section", "date", "away", "home", "away_win", "expected", "likely",
+        ],
+        rows,
+        json!({ "matches": values }),
+    )
+}
+
+fn format_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: Vec<String>| -> String {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let mut lines = vec![format_row(header.iter().map(|h| h.to_string()).collect())];
+    lines.extend(rows.iter().map(|row| format_row(row.to_owned())));
+    lines.join("\n")
+}
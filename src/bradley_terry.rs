@@ -0,0 +1,306 @@
+use crate::model::WinProbabilityModel;
+use crate::season::Season;
+use crate::sfl::{SflRecord, SflStage, SflTeam};
+
+const MAX_ITERATION: usize = 100;
+const EPSILON: f64 = 0.00000001_f64;
+
+// Bradley–Terry（ロジスティック）モデルを実績のセット全体から最尤推定する
+// チーム側の勝率 = σ(強さ - 相手の強さ + 大将戦補正の差 + HOME補正)
+// 強さは自然対数のオッズの尺度。400 / ln10 倍するとイロレーティングの差に相当する
+#[derive(Clone, Debug)]
+pub struct BradleyTerry {
+    // HOME側に共通の補正を入れる
+    use_home: bool,
+    // チームごとに大将戦以降の補正を入れる
+    use_leader: bool,
+    // 正則化の事前分布（平均0の正規分布）の分散
+    // Noneなら最初のチームの強さと大将戦補正を0に固定した最尤推定
+    prior_variance: Option<f64>,
+    // 以下はSflTeamの番号順。ステージに含まれないチームは0
+    strengths: Vec<f64>,
+    strength_errors: Vec<f64>,
+    leader_offsets: Vec<f64>,
+    leader_offset_errors: Vec<f64>,
+    home_advantage: f64,
+    home_advantage_error: f64,
+    // 推定に使ったセット数
+    set_count: usize,
+}
+
+impl BradleyTerry {
+    pub fn new(use_home: bool, use_leader: bool, prior_variance: Option<f64>) -> BradleyTerry {
+        BradleyTerry {
+            use_home,
+            use_leader,
+            prior_variance,
+            strengths: vec![],
+            strength_errors: vec![],
+            leader_offsets: vec![],
+            leader_offset_errors: vec![],
+            home_advantage: 0_f64,
+            home_advantage_error: 0_f64,
+            set_count: 0,
+        }
+    }
+    pub fn get_strength(&self, team_index: usize) -> f64 {
+        self.strengths.get(team_index).copied().unwrap_or(0_f64)
+    }
+    pub fn get_strength_error(&self, team_index: usize) -> f64 {
+        self.strength_errors
+            .get(team_index)
+            .copied()
+            .unwrap_or(f64::NAN)
+    }
+    pub fn get_leader_offset(&self, team_index: usize) -> f64 {
+        self.leader_offsets
+            .get(team_index)
+            .copied()
+            .unwrap_or(0_f64)
+    }
+    pub fn get_leader_offset_error(&self, team_index: usize) -> f64 {
+        self.leader_offset_errors
+            .get(team_index)
+            .copied()
+            .unwrap_or(f64::NAN)
+    }
+    pub fn get_home_advantage(&self) -> f64 {
+        self.home_advantage
+    }
+    pub fn get_home_advantage_error(&self) -> f64 {
+        self.home_advantage_error
+    }
+    pub fn get_set_count(&self) -> usize {
+        self.set_count
+    }
+}
+
+impl BradleyTerry {
+    // パラメータの並びは 強さ(チーム数), 大将戦補正(チーム数), HOME補正(1)
+    fn get_features(&self, teams: &[SflTeam], record: &SflRecord) -> Vec<(usize, f64)> {
+        let team_count = teams.len();
+        let team = teams.iter().position(|t| *t == record.sfl_match.team);
+        let opponent_team = teams
+            .iter()
+            .position(|t| *t == record.sfl_match.opponent_team);
+        let (Some(team), Some(opponent_team)) = (team, opponent_team) else {
+            return vec![];
+        };
+        let mut features = vec![(team, 1_f64), (opponent_team, -1_f64)];
+        if self.use_leader && record.game_type.is_leader() {
+            features.push((team_count + team, 1_f64));
+            features.push((team_count + opponent_team, -1_f64));
+        }
+        if self.use_home {
+            let home = if record.sfl_match.is_home {
+                1_f64
+            } else {
+                -1_f64
+            };
+            features.push((team_count * 2, home));
+        }
+        features
+    }
+
+    pub fn fit_records(&mut self, teams: &[SflTeam], records: &[&SflRecord]) {
+        let team_count = teams.len();
+        let parameter_count = team_count * 2 + 1;
+        // 使わないパラメータは0に固定する
+        let mut free: Vec<bool> = (0..parameter_count)
+            .map(|n| {
+                if n < team_count {
+                    true
+                } else if n < team_count * 2 {
+                    self.use_leader
+                } else {
+                    self.use_home
+                }
+            })
+            .collect();
+        if self.prior_variance.is_none() && team_count > 0 {
+            free[0] = false;
+            free[team_count] = false;
+        }
+        let free_indexes: Vec<usize> = (0..parameter_count).filter(|n| free[*n]).collect();
+        let samples: Vec<(Vec<(usize, f64)>, f64)> = records
+            .iter()
+            .map(|record| {
+                let y = if record.win_flag { 1_f64 } else { 0_f64 };
+                (self.get_features(teams, record), y)
+            })
+            .filter(|(features, _)| !features.is_empty())
+            .collect();
+
+        let get_free_information = |information: Vec<Vec<f64>>| -> Vec<Vec<f64>> {
+            free_indexes
+                .iter()
+                .map(|i| free_indexes.iter().map(|j| information[*i][*j]).collect())
+                .collect()
+        };
+
+        // ニュートン法
+        let mut theta = vec![0_f64; parameter_count];
+        for _ in 0..MAX_ITERATION {
+            let (gradient, information) = self.get_gradient_and_information(&theta, &samples);
+            let gradient: Vec<f64> = free_indexes.iter().map(|i| gradient[*i]).collect();
+            let Some(inverse) = invert(&get_free_information(information)) else {
+                break;
+            };
+            let mut step_size = 0_f64;
+            for (n, i) in free_indexes.iter().enumerate() {
+                let step: f64 = (0..free_indexes.len())
+                    .map(|m| inverse[n][m] * gradient[m])
+                    .sum();
+                theta[*i] += step;
+                step_size = step_size.max(step.abs());
+            }
+            if step_size < EPSILON {
+                break;
+            }
+        }
+        // 標準誤差は推定値での観測情報行列の逆行列から
+        let (_, information) = self.get_gradient_and_information(&theta, &samples);
+        let covariance = invert(&get_free_information(information));
+        let mut errors = vec![0_f64; parameter_count];
+        for (n, i) in free_indexes.iter().enumerate() {
+            errors[*i] = match covariance.as_ref() {
+                Some(covariance) => covariance[n][n].sqrt(),
+                None => f64::NAN,
+            };
+        }
+
+        let size = teams
+            .iter()
+            .map(|team| team.get_index() + 1)
+            .max()
+            .unwrap_or(0);
+        self.strengths = vec![0_f64; size];
+        self.strength_errors = vec![f64::NAN; size];
+        self.leader_offsets = vec![0_f64; size];
+        self.leader_offset_errors = vec![f64::NAN; size];
+        for (k, team) in teams.iter().enumerate() {
+            let index = team.get_index();
+            self.strengths[index] = theta[k];
+            self.strength_errors[index] = errors[k];
+            self.leader_offsets[index] = theta[team_count + k];
+            self.leader_offset_errors[index] = errors[team_count + k];
+        }
+        self.home_advantage = theta[team_count * 2];
+        self.home_advantage_error = errors[team_count * 2];
+        self.set_count = samples.len();
+    }
+
+    // 事前分布込みの対数尤度の勾配と、符号を反転したヘッセ行列
+    fn get_gradient_and_information(
+        &self,
+        theta: &[f64],
+        samples: &[(Vec<(usize, f64)>, f64)],
+    ) -> (Vec<f64>, Vec<Vec<f64>>) {
+        let size = theta.len();
+        let mut gradient = vec![0_f64; size];
+        let mut information = vec![vec![0_f64; size]; size];
+        for (features, y) in samples.iter() {
+            let z: f64 = features.iter().map(|(i, x)| theta[*i] * x).sum();
+            let p = sigmoid(z);
+            for (i, x_i) in features.iter() {
+                gradient[*i] += (y - p) * x_i;
+                for (j, x_j) in features.iter() {
+                    information[*i][*j] += p * (1_f64 - p) * x_i * x_j;
+                }
+            }
+        }
+        if let Some(prior_variance) = self.prior_variance {
+            for i in 0..size {
+                gradient[i] -= theta[i] / prior_variance;
+                information[i][i] += 1_f64 / prior_variance;
+            }
+        }
+        (gradient, information)
+    }
+
+    pub fn get_team_win_percentage(&self, record: &SflRecord) -> Option<f64> {
+        if self.set_count == 0 {
+            return None;
+        }
+        let sfl_match = &record.sfl_match;
+        let team = sfl_match.team.get_index();
+        let opponent_team = sfl_match.opponent_team.get_index();
+        let mut z = self.strengths.get(team)? - self.strengths.get(opponent_team)?;
+        if self.use_leader && record.game_type.is_leader() {
+            z += self.leader_offsets.get(team)? - self.leader_offsets.get(opponent_team)?;
+        }
+        if self.use_home {
+            z += if sfl_match.is_home {
+                self.home_advantage
+            } else {
+                -self.home_advantage
+            };
+        }
+        Some(sigmoid(z))
+    }
+}
+
+impl WinProbabilityModel for BradleyTerry {
+    fn fit(&mut self, season: &Season, sfl_stage: &SflStage, sfl_records: &[Vec<SflRecord>]) {
+        let records: Vec<&SflRecord> = sfl_records
+            .iter()
+            .flatten()
+            .filter(|record| record.is_valid && !record.is_prediction)
+            .collect();
+        self.fit_records(&season.get_teams(sfl_stage), &records);
+    }
+    fn get_team_win_percentage(&self, record: &SflRecord) -> Option<f64> {
+        BradleyTerry::get_team_win_percentage(self, record)
+    }
+    fn get_model_key(&self) -> Option<String> {
+        Some(format!(
+            "BradleyTerry/{}/{}/{:?}",
+            self.use_home, self.use_leader, self.prior_variance
+        ))
+    }
+}
+
+fn sigmoid(z: f64) -> f64 {
+    1_f64 / (1_f64 + (-z).exp())
+}
+
+// ガウス・ジョルダン法。特異なら None
+fn invert(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let size = matrix.len();
+    let mut left: Vec<Vec<f64>> = matrix.to_vec();
+    let mut right: Vec<Vec<f64>> = (0..size)
+        .map(|i| {
+            (0..size)
+                .map(|j| if i == j { 1_f64 } else { 0_f64 })
+                .collect()
+        })
+        .collect();
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|a, b| left[*a][column].abs().total_cmp(&left[*b][column].abs()))?;
+        if left[pivot][column].is_nan() || left[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        left.swap(column, pivot);
+        right.swap(column, pivot);
+        let value = left[column][column];
+        for j in 0..size {
+            left[column][j] /= value;
+            right[column][j] /= value;
+        }
+        for row in 0..size {
+            if row == column {
+                continue;
+            }
+            let factor = left[row][column];
+            if factor == 0_f64 {
+                continue;
+            }
+            for j in 0..size {
+                left[row][j] -= factor * left[column][j];
+                right[row][j] -= factor * right[column][j];
+            }
+        }
+    }
+    Some(right)
+}
@@ -0,0 +1,264 @@
+use crate::exact::EXACT_STATE_LIMIT;
+use crate::season::Season;
+use crate::sfl::SflStageKind::AllDivision;
+use crate::sfl::{SflRecord, SflStage};
+
+// 残りの試合の結果によらず決まっていること
+// 同じポイントならバトル得失で決まるが、バトル得失までは追わないので同点は決まっていない扱い
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClinchStatus {
+    pub team_index: usize,
+    // 残りの試合をすべて落としても入るポイント
+    pub guaranteed_point: u32,
+    // 残りの試合をすべて最大で取った場合のポイント
+    pub max_point: u32,
+    pub playoff_clinched: bool,
+    pub first_clinched: bool,
+    pub playoff_eliminated: bool,
+    pub first_eliminated: bool,
+    // 他の試合の結果によらずプレイオフ進出、1位を決めるのにあと必要なポイント
+    // 残りの試合で取れるポイントより大きければ自力では決められない
+    pub playoff_magic_number: u32,
+    pub first_magic_number: u32,
+    // ディビジョンの残りの試合のポイントを全列挙して求めたか
+    // false ならチームごとのポイントの上下限だけから求めた保守的な値で、
+    // 直接対決で両方が最大を取れないことを考えないので、決まっていても決まっていない扱いになることがある
+    pub is_exact: bool,
+}
+
+// ディビジョンの試合のうち、ありうる (チーム側, 相手側) のポイントの組
+struct MatchPoints {
+    team: usize,
+    opponent_team: usize,
+    points: Vec<(u32, u32)>,
+}
+
+// 順位の条件ごとの集計。place_count 位以内について
+#[derive(Clone, Copy)]
+struct PlaceCheck {
+    place_count: usize,
+    clinched: bool,
+    eliminated: bool,
+    magic_number: u32,
+}
+
+impl PlaceCheck {
+    fn new(place_count: usize) -> PlaceCheck {
+        PlaceCheck {
+            place_count,
+            clinched: true,
+            eliminated: true,
+            magic_number: 0,
+        }
+    }
+
+    // 1つの組み合わせでのポイント。others は他のチームのポイント
+    fn add(&mut self, point: u32, others: &[u32], guaranteed_point: u32) {
+        let at_least = others.iter().filter(|other| **other >= point).count();
+        let above = others.iter().filter(|other| **other > point).count();
+        if at_least >= self.place_count {
+            // このポイントでは足りないので、これより多く取る必要がある
+            self.clinched = false;
+            self.magic_number = self.magic_number.max(point + 1 - guaranteed_point);
+        }
+        if above < self.place_count {
+            self.eliminated = false;
+        }
+    }
+}
+
+// 実績のレコードから、ディビジョンごとに各チームの状況を求める
+// AllDivision なら全ディビジョンのチームを並べる
+// ディビジョンの残りの組み合わせが EXACT_STATE_LIMIT 以下なら全列挙し、多ければポイントの上下限から求める
+pub fn get_clinch_statuses(
+    season: &Season,
+    sfl_stage: &SflStage,
+    sfl_records: &[Vec<SflRecord>],
+) -> Vec<ClinchStatus> {
+    let format = season.get_format(&SflStage::all_division());
+    let matches: Vec<MatchPoints> = sfl_records
+        .iter()
+        .filter_map(|records| {
+            let sfl_match = &records.first()?.sfl_match;
+            // 五分にすればありうる結果がすべて出てくる
+            let mut points: Vec<(u32, u32)> = format
+                .get_outcomes(records, &|_: &SflRecord| 0.5_f64)
+                .iter()
+                .map(|(outcome, _)| (outcome.get_team_point(), outcome.get_opponent_team_point()))
+                .collect();
+            points.sort();
+            points.dedup();
+            Some(MatchPoints {
+                team: sfl_match.team.get_index(),
+                opponent_team: sfl_match.opponent_team.get_index(),
+                points,
+            })
+        })
+        .collect();
+    // チームごとの (最低, 最大) ポイント
+    let mut point_ranges = vec![(0_u32, 0_u32); season.teams.len()];
+    for m in matches.iter() {
+        for (team_index, is_team) in [(m.team, true), (m.opponent_team, false)] {
+            let points = m
+                .points
+                .iter()
+                .map(|(point, opponent_point)| if is_team { *point } else { *opponent_point });
+            let (min, max) = &mut point_ranges[team_index];
+            *min += points.clone().min().unwrap_or(0);
+            *max += points.max().unwrap_or(0);
+        }
+    }
+
+    let division_stages = match sfl_stage.kind {
+        AllDivision => season.get_division_stages(),
+        _ => vec![sfl_stage.to_owned()],
+    };
+    let mut statuses = vec![];
+    for stage in division_stages.iter() {
+        let teams: Vec<usize> = season
+            .get_teams(stage)
+            .iter()
+            .map(|team| team.get_index())
+            .collect();
+        let division_matches: Vec<&MatchPoints> = matches
+            .iter()
+            .filter(|m| teams.contains(&m.team) && teams.contains(&m.opponent_team))
+            .collect();
+        let checks = enumerate_checks(
+            &teams,
+            &division_matches,
+            &point_ranges,
+            season.playoff_team_count,
+        );
+        for (n, team_index) in teams.iter().enumerate() {
+            let (guaranteed_point, max_point) = point_ranges[*team_index];
+            let (playoff, first) = match checks.as_ref() {
+                Some(checks) => checks[n],
+                None => {
+                    let others: Vec<(u32, u32)> = teams
+                        .iter()
+                        .filter(|other| *other != team_index)
+                        .map(|other| point_ranges[*other])
+                        .collect();
+                    (
+                        get_bound_check(
+                            guaranteed_point,
+                            max_point,
+                            &others,
+                            season.playoff_team_count,
+                        ),
+                        get_bound_check(guaranteed_point, max_point, &others, 1),
+                    )
+                }
+            };
+            statuses.push(ClinchStatus {
+                team_index: *team_index,
+                guaranteed_point,
+                max_point,
+                playoff_clinched: playoff.clinched,
+                first_clinched: first.clinched,
+                playoff_eliminated: playoff.eliminated,
+                first_eliminated: first.eliminated,
+                playoff_magic_number: playoff.magic_number,
+                first_magic_number: first.magic_number,
+                is_exact: checks.is_some(),
+            });
+        }
+    }
+    statuses
+}
+
+// ディビジョンの残りの試合のポイントを全列挙して、teams の順に (プレイオフ, 1位) を返す
+// 組み合わせが EXACT_STATE_LIMIT を超える場合は None
+fn enumerate_checks(
+    teams: &[usize],
+    matches: &[&MatchPoints],
+    point_ranges: &[(u32, u32)],
+    playoff_team_count: usize,
+) -> Option<Vec<(PlaceCheck, PlaceCheck)>> {
+    let mut state_count = 1_usize;
+    for m in matches.iter() {
+        state_count = state_count.checked_mul(m.points.len().max(1))?;
+        if state_count > EXACT_STATE_LIMIT {
+            return None;
+        }
+    }
+    let mut checks: Vec<(PlaceCheck, PlaceCheck)> = teams
+        .iter()
+        .map(|_| (PlaceCheck::new(playoff_team_count), PlaceCheck::new(1)))
+        .collect();
+    // 試合ごとのポイントの番号を繰り上げながら全組み合わせを回る
+    let mut indexes = vec![0_usize; matches.len()];
+    loop {
+        let mut point_count = vec![0_u32; point_ranges.len()];
+        for (m, index) in matches.iter().zip(indexes.iter()) {
+            let Some((point, opponent_point)) = m.points.get(*index) else {
+                continue;
+            };
+            point_count[m.team] += point;
+            point_count[m.opponent_team] += opponent_point;
+        }
+        let points: Vec<u32> = teams.iter().map(|team| point_count[*team]).collect();
+        for (n, (playoff, first)) in checks.iter_mut().enumerate() {
+            let others: Vec<u32> = points
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != n)
+                .map(|(_, point)| *point)
+                .collect();
+            let guaranteed_point = point_ranges[teams[n]].0;
+            playoff.add(points[n], &others, guaranteed_point);
+            first.add(points[n], &others, guaranteed_point);
+        }
+
+        // 繰り上げ
+        let mut n = 0;
+        loop {
+            if n == indexes.len() {
+                return Some(checks);
+            }
+            indexes[n] += 1;
+            if indexes[n] < matches[n].points.len() {
+                break;
+            }
+            indexes[n] = 0;
+            n += 1;
+        }
+    }
+}
+
+// チームごとのポイントの上下限だけから求める
+fn get_bound_check(
+    guaranteed_point: u32,
+    max_point: u32,
+    others: &[(u32, u32)],
+    place_count: usize,
+) -> PlaceCheck {
+    let magic_number = get_magic_number(guaranteed_point, others, place_count);
+    PlaceCheck {
+        place_count,
+        clinched: magic_number == 0,
+        eliminated: is_eliminated(max_point, others, place_count),
+        magic_number,
+    }
+}
+
+// place_count 位以内に入るのにあと必要なポイント
+// 他のチームの最大ポイントのうち place_count 番目を上回れば、それより上に来られるのは place_count - 1 チームまで
+fn get_magic_number(guaranteed_point: u32, others: &[(u32, u32)], place_count: usize) -> u32 {
+    let mut max_points: Vec<u32> = others.iter().map(|(_, max)| *max).collect();
+    max_points.sort_by(|a, b| b.cmp(a));
+    match max_points.get(place_count - 1) {
+        Some(threshold) => (threshold + 1).saturating_sub(guaranteed_point),
+        None => 0,
+    }
+}
+
+// 最大ポイントを取っても上回れないチームが place_count 以上あれば脱落
+fn is_eliminated(max_point: u32, others: &[(u32, u32)], place_count: usize) -> bool {
+    others
+        .iter()
+        .filter(|(guaranteed_point, _)| *guaranteed_point > max_point)
+        .count()
+        >= place_count
+}
@@ -0,0 +1,369 @@
+use crate::accumulator::PointRange;
+use crate::format::OutcomeDistribution;
+use crate::season::Season;
+use crate::sfl::SflStageKind::{AllDivision, Division};
+use crate::sfl::{SflRecord, SflStage, SflTeam};
+use crate::SflSimulationResult;
+use std::collections::HashMap;
+
+// 残りの組み合わせがこの数以下なら全列挙する
+// 組み合わせは試合ごとの結果（2024年のディビジョンの試合形式では40通り）の積なので、
+// 全列挙になるのは残り3試合ほどの最終節の途中から
+pub const EXACT_STATE_LIMIT: usize = 100_000;
+
+// 全列挙の結果。確率は合計1
+#[derive(Clone, Debug)]
+pub struct ExactResult {
+    // 列挙した組み合わせの数
+    pub state_count: usize,
+    // シーズンの各ディビジョンからプレイオフに進むチーム数
+    playoff_team_count: usize,
+    // チームごと、ディビジョンの順位ごとの確率
+    pub division_place_probability: Vec<Vec<f64>>,
+    // チームごと、プレイオフの順位ごとの確率（優勝、準優勝、3位、5位）
+    pub playoff_place_probability: Vec<Vec<f64>>,
+    // 以下は SflSimulationResult に直すための期待値
+    division_points_battles: Vec<Vec<f64>>,
+    match_points: Vec<Vec<f64>>,
+    knockout_match_points: Vec<Vec<f64>>,
+    division_place_detail: HashMap<(usize, usize, usize), f64>,
+    // 順位ごとの確率とポイントの期待値の和。最高・最低は PointRange に持つ
+    place_to_points: Vec<Vec<(f64, f64, PointRange)>>,
+}
+
+impl ExactResult {
+    // SflSimulationResult::get_place_count と同じ並びの確率
+    pub fn get_place_probability(&self, sfl_stage: &SflStage, team_index: usize) -> Vec<f64> {
+        let probabilities = match sfl_stage.kind {
+            Division => &self.division_place_probability,
+            AllDivision => &self.playoff_place_probability,
+            _ => return vec![],
+        };
+        probabilities.get(team_index).cloned().unwrap_or_default()
+    }
+    pub fn get_playoff_probability(&self, team_index: usize) -> f64 {
+        self.division_place_probability
+            .get(team_index)
+            .map(|p| p.iter().take(self.playoff_team_count).sum())
+            .unwrap_or(0_f64)
+    }
+
+    // 既存の回数ベースの取得関数で使えるように、count 回シミュレートした形に丸める
+    // 確率そのものは get_place_probability などで取る
+    pub fn to_simulation_result(&self, season: &Season, count: usize) -> SflSimulationResult {
+        let round = |p: f64| (p * count as f64).round();
+        let mut result = SflSimulationResult::zero(season);
+        let to_counts = |probabilities: &[Vec<f64>]| -> Vec<Vec<u32>> {
+            probabilities
+                .iter()
+                .map(|p| p.iter().map(|p| round(*p) as u32).collect())
+                .collect()
+        };
+        result.division_place_count = to_counts(&self.division_place_probability);
+        result.playoff_place_count = to_counts(&self.playoff_place_probability);
+        result.match_points = to_counts(&self.match_points);
+        result.knockout_match_points = to_counts(&self.knockout_match_points);
+        result.division_points_battles = self
+            .division_points_battles
+            .iter()
+            .map(|p| p.iter().map(|p| round(*p) as i32).collect())
+            .collect();
+        for ((i, j, k), p) in self.division_place_detail.iter() {
+            result.division_place_detail[*i][*j][*k] = round(*p) as usize;
+        }
+        for (ranges, place_to_points) in result
+            .place_to_points
+            .iter_mut()
+            .zip(self.place_to_points.iter())
+        {
+            for (range, (p, sum_point, exact_range)) in ranges.iter_mut().zip(place_to_points) {
+                *range = PointRange {
+                    time: round(*p) as usize,
+                    sum_point: round(*sum_point) as usize,
+                    ..*exact_range
+                };
+            }
+        }
+        result.count = count;
+        result
+    }
+}
+
+// ディビジョンの順位表の1行 (チーム, ポイント, バトル)
+type Standing = (SflTeam, u32, i32);
+
+// ノックアウトの1試合。チーム側の勝率とポイントの期待値
+type KnockoutCache = HashMap<(bool, SflTeam, SflTeam), (f64, [f64; 4])>;
+
+struct Enumeration<'a, F: Fn(&SflRecord) -> f64> {
+    season: &'a Season,
+    get_win_percentage: &'a F,
+    knockout_cache: KnockoutCache,
+}
+
+impl<F: Fn(&SflRecord) -> f64> Enumeration<'_, F> {
+    fn get_knockout(
+        &mut self,
+        is_grand_final: bool,
+        team: SflTeam,
+        opponent_team: SflTeam,
+    ) -> (f64, [f64; 4]) {
+        if let Some(knockout) = self
+            .knockout_cache
+            .get(&(is_grand_final, team, opponent_team))
+        {
+            return *knockout;
+        }
+        let (records, format) = if is_grand_final {
+            (
+                self.season.get_grand_final_records(&team, &opponent_team),
+                self.season.get_format(&SflStage::grand_final()),
+            )
+        } else {
+            (
+                self.season.get_playoff_records(&team, &opponent_team),
+                self.season.get_format(&SflStage::playoff()),
+            )
+        };
+        let mut knockout = (0_f64, [0_f64; 4]);
+        for (outcome, p) in format.get_outcomes(&records, self.get_win_percentage) {
+            if outcome.get_team_point() > outcome.get_opponent_team_point() {
+                knockout.0 += p;
+            }
+            for (expected, point) in knockout.1.iter_mut().zip(outcome.match_points) {
+                *expected += p * point as f64;
+            }
+        }
+        self.knockout_cache
+            .insert((is_grand_final, team, opponent_team), knockout);
+        knockout
+    }
+
+    // 各ディビジョンの上位3チームからプレイオフの順位を確率で加算する
+    // 試合の流れは SflSimulation::simulate_one_time と同じ
+    fn add_playoff(
+        &mut self,
+        playoff_team: &[Vec<Standing>],
+        probability: f64,
+        exact: &mut ExactResult,
+    ) {
+        let mut representatives: Vec<Vec<(Standing, f64)>> = vec![];
+        for (n, teams) in playoff_team.iter().enumerate() {
+            let (first, second, third) = (teams[0], teams[1], teams[2]);
+            let (p, points) = self.get_knockout(false, third.0, second.0);
+            add_expected(
+                &mut exact.knockout_match_points[n * 2],
+                &points,
+                probability,
+            );
+            exact.playoff_place_probability[second.0.get_index()][3] += probability * p;
+            exact.playoff_place_probability[third.0.get_index()][3] += probability * (1_f64 - p);
+            let mut division_representatives = vec![];
+            for (winner, p) in [(third, p), (second, 1_f64 - p)] {
+                let (q, points) = self.get_knockout(false, winner.0, first.0);
+                add_expected(
+                    &mut exact.knockout_match_points[n * 2 + 1],
+                    &points,
+                    probability * p,
+                );
+                exact.playoff_place_probability[first.0.get_index()][2] += probability * p * q;
+                exact.playoff_place_probability[winner.0.get_index()][2] +=
+                    probability * p * (1_f64 - q);
+                division_representatives.push((winner, p * q));
+                division_representatives.push((first, p * (1_f64 - q)));
+            }
+            representatives.push(division_representatives);
+        }
+        for (representative, p) in representatives[0].iter() {
+            for (opponent_representative, q) in representatives[1].iter() {
+                // HOME / AWAY の決定。ポイント > バトル得失
+                let (team, opponent_team) = if (representative.1 == opponent_representative.1
+                    && representative.2 > opponent_representative.2)
+                    || representative.1 > opponent_representative.1
+                {
+                    (opponent_representative.0, representative.0)
+                } else {
+                    (representative.0, opponent_representative.0)
+                };
+                let weight = probability * p * q;
+                let (r, points) = self.get_knockout(true, team, opponent_team);
+                add_expected(&mut exact.knockout_match_points[4], &points, weight);
+                exact.playoff_place_probability[team.get_index()][0] += weight * r;
+                exact.playoff_place_probability[opponent_team.get_index()][1] += weight * r;
+                exact.playoff_place_probability[opponent_team.get_index()][0] +=
+                    weight * (1_f64 - r);
+                exact.playoff_place_probability[team.get_index()][1] += weight * (1_f64 - r);
+            }
+        }
+    }
+}
+
+fn add_expected(expected: &mut [f64], points: &[f64; 4], probability: f64) {
+    for (expected, point) in expected.iter_mut().zip(points) {
+        *expected += probability * point;
+    }
+}
+
+// 残りの試合の結果を全部並べ、確率で重みをつけて順位を集計する
+// 組み合わせの数が state_limit を超える場合は None
+pub fn enumerate_season<F: Fn(&SflRecord) -> f64>(
+    season: &Season,
+    sfl_records: &[Vec<SflRecord>],
+    get_win_percentage: &F,
+    state_limit: usize,
+) -> Option<ExactResult> {
+    let format = season.get_format(&SflStage::all_division());
+    let match_outcomes: Vec<OutcomeDistribution> = sfl_records
+        .iter()
+        .map(|records| format.get_outcomes(records, get_win_percentage))
+        .collect();
+    enumerate_outcomes(
+        season,
+        sfl_records,
+        &match_outcomes,
+        get_win_percentage,
+        state_limit,
+    )
+}
+
+// 試合ごとの結果の分布を与えて全列挙する。シナリオで一部の試合の結果を絞り込む場合に使う
+// get_win_percentage はプレイオフ以降の勝率に使う
+pub fn enumerate_outcomes<F: Fn(&SflRecord) -> f64>(
+    season: &Season,
+    sfl_records: &[Vec<SflRecord>],
+    match_outcomes: &[OutcomeDistribution],
+    get_win_percentage: &F,
+    state_limit: usize,
+) -> Option<ExactResult> {
+    let mut state_count = 1_usize;
+    for outcomes in match_outcomes.iter() {
+        state_count = state_count.checked_mul(outcomes.len().max(1))?;
+        if state_count > state_limit {
+            return None;
+        }
+    }
+
+    let team_count = season.teams.len();
+    let place_count = season
+        .divisions
+        .iter()
+        .map(|division| division.teams.len())
+        .max()
+        .unwrap_or(0);
+    let mut exact = ExactResult {
+        state_count,
+        playoff_team_count: season.playoff_team_count,
+        division_place_probability: vec![vec![0_f64; place_count]; team_count],
+        playoff_place_probability: vec![vec![0_f64; 4]; team_count],
+        division_points_battles: vec![vec![0_f64; 2]; team_count],
+        match_points: vec![vec![0_f64; 4]; sfl_records.len()],
+        knockout_match_points: vec![vec![0_f64; 4]; season.divisions.len() * 2 + 1],
+        division_place_detail: HashMap::new(),
+        place_to_points: vec![vec![(0_f64, 0_f64, PointRange::default()); place_count]; team_count],
+    };
+    // 試合ごとのポイントは他の試合によらない
+    for (expected, outcomes) in exact.match_points.iter_mut().zip(match_outcomes.iter()) {
+        for (outcome, p) in outcomes.iter() {
+            let points = outcome.match_points.map(|point| point as f64);
+            add_expected(expected, &points, *p);
+        }
+    }
+
+    let division_stages = season.get_division_stages();
+    let division_teams: Vec<Vec<SflTeam>> = division_stages
+        .iter()
+        .map(|stage| season.get_teams(stage))
+        .collect();
+    let has_playoff = season.playoff_team_count == 3
+        && division_teams.len() == 2
+        && division_teams.iter().all(|t| t.len() >= 3);
+    let mut enumeration = Enumeration {
+        season,
+        get_win_percentage,
+        knockout_cache: HashMap::new(),
+    };
+    let matches: Vec<Option<(SflTeam, SflTeam)>> = sfl_records
+        .iter()
+        .map(|records| {
+            let sfl_match = &records.first()?.sfl_match;
+            Some((sfl_match.team, sfl_match.opponent_team))
+        })
+        .collect();
+    let max_team_index = season.get_max_team_index(&SflStage::all_division());
+    // 試合ごとの結果の番号を繰り上げながら全組み合わせを回る
+    let mut indexes = vec![0_usize; match_outcomes.len()];
+    loop {
+        let mut probability = 1_f64;
+        let mut point_count = vec![0_u32; max_team_index + 1];
+        let mut battle_count = vec![0_i32; max_team_index + 1];
+        for (n, outcomes) in match_outcomes.iter().enumerate() {
+            let (Some((outcome, p)), Some((team, opponent_team))) =
+                (outcomes.get(indexes[n]), matches[n])
+            else {
+                continue;
+            };
+            probability *= p;
+            point_count[team.get_index()] += outcome.get_team_point();
+            point_count[opponent_team.get_index()] += outcome.get_opponent_team_point();
+            battle_count[team.get_index()] += outcome.battle;
+            battle_count[opponent_team.get_index()] -= outcome.battle;
+        }
+
+        let mut playoff_team: Vec<Vec<Standing>> = vec![];
+        for teams in division_teams.iter() {
+            let mut sortable: Vec<Standing> = teams
+                .iter()
+                .map(|team| {
+                    let team_index = team.get_index();
+                    (*team, point_count[team_index], battle_count[team_index])
+                })
+                .collect();
+            sortable.sort_by(|(a_team, a_point, a_battle), (b_team, b_point, b_battle)| {
+                b_point
+                    .cmp(a_point)
+                    .then(b_battle.cmp(a_battle))
+                    .then(b_team.cmp(a_team))
+            });
+            for (nth, (team, point, battle)) in sortable.iter().enumerate() {
+                let team_index = team.get_index();
+                exact.division_place_probability[team_index][nth] += probability;
+                exact.division_points_battles[team_index][0] += probability * *point as f64;
+                exact.division_points_battles[team_index][1] += probability * *battle as f64;
+                let (p, sum_point, range) = &mut exact.place_to_points[team_index][nth];
+                *p += probability;
+                *sum_point += probability * *point as f64;
+                if probability > 0_f64 {
+                    range.update_range(*point as usize);
+                }
+            }
+            if let [first, second, third, ..] = sortable.as_slice() {
+                *exact
+                    .division_place_detail
+                    .entry((
+                        first.0.get_index(),
+                        second.0.get_index(),
+                        third.0.get_index(),
+                    ))
+                    .or_insert(0_f64) += probability;
+            }
+            playoff_team.push(sortable.into_iter().take(3).collect());
+        }
+        if has_playoff {
+            enumeration.add_playoff(&playoff_team, probability, &mut exact);
+        }
+
+        // 繰り上げ
+        let mut n = 0;
+        loop {
+            if n == indexes.len() {
+                return Some(exact);
+            }
+            indexes[n] += 1;
+            if indexes[n] < match_outcomes[n].len() {
+                break;
+            }
+            indexes[n] = 0;
+            n += 1;
+        }
+    }
+}
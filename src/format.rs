@@ -0,0 +1,796 @@
+use crate::sfl::{GameType, SflMatch, SflRecord, SflTeam};
+#[cfg(feature = "serialize")]
+use serde::Deserialize;
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Deserialize))]
+pub enum LegCondition {
+    #[default]
+    Always,
+    // それまでのレグがすべて決着していて、両チームのポイントが並んでいる場合のみ行う
+    Tied,
+}
+
+// 先鋒戦、中堅戦、大将戦などの1つの勝負
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Deserialize))]
+pub struct LegFormat {
+    pub game_type: GameType,
+    // 最大セット数。過半数を先取したチームがレグの勝者
+    pub sets: u32,
+    // レグの勝者に入るポイント
+    pub point: u32,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub condition: LegCondition,
+}
+
+impl LegFormat {
+    // レグの勝者になるのに必要な勝ちセット数
+    pub fn get_wins_needed(&self) -> u32 {
+        self.sets / 2 + 1
+    }
+
+    // 決着したセットの位置とそのセットのwin_flagを返す
+    // 無効なセットが挟まる場合はそこまでで判定する
+    fn get_decided_set(&self, records: &[SflRecord]) -> Option<(usize, bool)> {
+        let need = self.get_wins_needed();
+        let mut won = 0;
+        let mut lost = 0;
+        for (index, record) in records.iter().enumerate() {
+            if !record.is_valid {
+                return None;
+            }
+            if record.win_flag {
+                won += 1;
+            } else {
+                lost += 1;
+            }
+            if won == need || lost == need {
+                return Some((index, record.win_flag));
+            }
+        }
+        None
+    }
+
+    // 有効なセットを1つ進める。決着していれば決着セットの側にポイントを入れる
+    fn apply(&self, mut state: OutcomeState, record: &SflRecord, win_flag: bool) -> OutcomeState {
+        state.outcome.battle += if win_flag { 1 } else { -1 };
+        if state.progress != LegProgress::Open {
+            return state;
+        }
+        if win_flag {
+            state.won += 1;
+        } else {
+            state.lost += 1;
+        }
+        let need = self.get_wins_needed();
+        if state.won == need || state.lost == need {
+            state.progress = LegProgress::Decided;
+            let index = match (win_flag, record.game_type.is_leader()) {
+                (true, false) => 0,
+                (false, false) => 1,
+                (true, true) => 2,
+                (false, true) => 3,
+            };
+            state.outcome.match_points[index] += self.point;
+        }
+        state
+    }
+}
+
+fn add_state(states: &mut Vec<(OutcomeState, f64)>, state: OutcomeState, probability: f64) {
+    if probability == 0_f64 {
+        return;
+    }
+    match states.iter_mut().find(|(s, _)| *s == state) {
+        Some((_, p)) => *p += probability,
+        None => states.push((state, probability)),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MatchScore {
+    pub team_point: u32,
+    pub opponent_team_point: u32,
+    // win_thresholdに到達して勝敗が決まった場合のみ。チーム側の勝ちならtrue
+    pub win_flag: Option<bool>,
+}
+
+// 1試合の結果をポイントとバトル得失の単位でまとめたもの
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchOutcome {
+    // AWAY先鋒中堅, HOME先鋒中堅, AWAY大将以降, HOME大将以降 のポイント
+    pub match_points: [u32; 4],
+    // チーム側のバトル得失
+    pub battle: i32,
+}
+
+impl MatchOutcome {
+    pub fn get_team_point(&self) -> u32 {
+        self.match_points[0] + self.match_points[2]
+    }
+    pub fn get_opponent_team_point(&self) -> u32 {
+        self.match_points[1] + self.match_points[3]
+    }
+}
+
+// 1試合で起こりうる結果とその確率
+pub type OutcomeDistribution = Vec<(MatchOutcome, f64)>;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+enum LegProgress {
+    #[default]
+    Open,
+    Decided,
+    // 無効なセットが挟まって決着しなかった
+    Broken,
+    // 行われないレグ
+    Skipped,
+}
+
+// get_outcomes で分岐をたどるときの状態
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct OutcomeState {
+    won: u32,
+    lost: u32,
+    progress: LegProgress,
+    walker: LegWalker,
+    outcome: MatchOutcome,
+}
+
+// レグを行うかどうか
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum LegStatus {
+    Played,
+    // win_threshold に到達して勝敗が決まった後
+    MatchDecided,
+    // 同点でないと行わないレグで、同点でない
+    NotTied,
+}
+
+// 試合形式の規則でレグを順にたどる状態
+// どのレグを行うか（同点のときだけのレグ、win_threshold）はここでだけ決める
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct LegWalker {
+    leg_index: usize,
+    // 次のレグの最初のレコードの位置
+    offset: usize,
+    score: MatchScore,
+    // それまでに行ったレグがすべて決着した
+    all_decided: bool,
+}
+
+impl Default for LegWalker {
+    fn default() -> LegWalker {
+        LegWalker {
+            leg_index: 0,
+            offset: 0,
+            score: MatchScore::default(),
+            all_decided: true,
+        }
+    }
+}
+
+impl LegWalker {
+    // 次のレグとそのレコードの範囲、行うかどうか。すべてのレグが終わったら None
+    fn next_leg<'a>(
+        &self,
+        format: &'a MatchFormat,
+    ) -> Option<(&'a LegFormat, Range<usize>, LegStatus)> {
+        let leg = format.legs.get(self.leg_index)?;
+        let is_tied = self.all_decided && self.score.team_point == self.score.opponent_team_point;
+        let status = if format.is_decided(&self.score) {
+            LegStatus::MatchDecided
+        } else if leg.condition == LegCondition::Tied && !is_tied {
+            LegStatus::NotTied
+        } else {
+            LegStatus::Played
+        };
+        Some((leg, self.offset..self.offset + leg.sets as usize, status))
+    }
+
+    // レグを終えて次のレグに進む。winner はチーム側が取ったら Some(true)、決着しなければ None
+    // 行わないレグでは winner は見ない
+    fn finish_leg(&mut self, format: &MatchFormat, winner: Option<bool>) {
+        let Some((leg, range, status)) = self.next_leg(format) else {
+            return;
+        };
+        self.leg_index += 1;
+        self.offset = range.end;
+        if status != LegStatus::Played {
+            return;
+        }
+        match winner {
+            Some(true) => self.score.team_point += leg.point,
+            Some(false) => self.score.opponent_team_point += leg.point,
+            None => self.all_decided = false,
+        }
+    }
+
+    // win_threshold に到達していれば勝敗も入れる
+    fn get_score(&self, format: &MatchFormat) -> MatchScore {
+        let mut score = self.score;
+        if format.is_decided(&score) {
+            score.win_flag = Some(score.team_point > score.opponent_team_point);
+        }
+        score
+    }
+}
+
+// 入力したセットが試合形式に合わない理由
+// set は入力の何セット目か（1から）。check_results ではレコードの位置
+#[derive(Clone, Debug, PartialEq)]
+pub enum SetError {
+    // すべてのレグが終わった後のセット
+    TooManySets {
+        set: usize,
+    },
+    // 決着したレグのセット
+    LegDecided {
+        set: usize,
+        game_type: GameType,
+    },
+    // win_threshold に到達して勝敗が決まった後のセット
+    MatchDecided {
+        set: usize,
+    },
+    // 同点でないと行わないレグのセット
+    NotTied {
+        set: usize,
+        game_type: GameType,
+        team_point: u32,
+        opponent_team_point: u32,
+    },
+    // 次に行うレグと種類が違う
+    UnexpectedGame {
+        set: usize,
+        expected: GameType,
+        found: GameType,
+    },
+    // 結果がないセットより後に結果がある
+    MissingSet {
+        set: usize,
+    },
+}
+
+impl fmt::Display for SetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetError::TooManySets { set } => {
+                write!(f, "set {}: all legs are already finished", set)
+            }
+            SetError::LegDecided { set, game_type } => {
+                write!(f, "set {}: {:?} is already decided", set, game_type)
+            }
+            SetError::MatchDecided { set } => {
+                write!(f, "set {}: the match is already decided", set)
+            }
+            SetError::NotTied {
+                set,
+                game_type,
+                team_point,
+                opponent_team_point,
+            } => write!(
+                f,
+                "set {}: {:?} is played only on a tie, but the score is {}-{}",
+                set, game_type, team_point, opponent_team_point
+            ),
+            SetError::UnexpectedGame {
+                set,
+                expected,
+                found,
+            } => write!(f, "set {}: expected {:?}, found {:?}", set, expected, found),
+            SetError::MissingSet { set } => {
+                write!(f, "set {}: no result, but a later set has one", set)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
+
+// place_sets で決まるレコードごとの状態
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SetSlot {
+    // 入力の何番目のセットか（0から）
+    Played(usize),
+    // 決着後などで行われなかった
+    Skipped,
+    // まだ行われていない
+    Open,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Deserialize))]
+pub struct MatchFormat {
+    pub legs: Vec<LegFormat>,
+    // 先にこのポイントに到達したチームが勝ち
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub win_threshold: Option<u32>,
+}
+
+impl MatchFormat {
+    pub fn get_games(&self) -> Vec<(u32, GameType)> {
+        self.legs
+            .iter()
+            .flat_map(|leg| (1..=leg.sets).map(move |set_number| (set_number, leg.game_type)))
+            .collect()
+    }
+
+    pub fn to_records(&self, sfl_match: &SflMatch) -> Vec<SflRecord> {
+        self.get_games()
+            .iter()
+            .map(|(set_number, game_type)| {
+                SflRecord {
+                    sfl_match: sfl_match.to_owned(),
+                    set_number: *set_number,
+                    win_flag: false,
+                    game_type: game_type.to_owned(),
+                    // pointはcorrect_recordでセットする
+                    point: 0,
+                    is_valid: false,
+                    is_prediction: true,
+                    player: None,
+                    opponent_player: None,
+                    character: None,
+                    opponent_character: None,
+                }
+            })
+            .collect()
+    }
+
+    // レコードをレグごとに区切る
+    fn split_legs<'a>(&'a self, records: &'a [SflRecord]) -> Vec<(&'a LegFormat, &'a [SflRecord])> {
+        let mut offset = 0;
+        self.legs
+            .iter()
+            .map(|leg| {
+                let sets = leg.sets as usize;
+                let leg_records = &records[offset..offset + sets];
+                offset += sets;
+                (leg, leg_records)
+            })
+            .collect()
+    }
+
+    // is_valid = true フラグが立っているレコードについて見直して一部 is_valid = false に変える
+    // ポイントを決着セットに書き加える
+    // 決着していない場合はもちろんポイントを書かない
+    // ランダム結果と実際結果が混じることがある
+    pub fn correct_records(&self, records: &mut [SflRecord]) {
+        let mut walker = LegWalker::default();
+        while let Some((leg, range, status)) = walker.next_leg(self) {
+            let leg_records = &mut records[range];
+            // 行わないレグ、勝敗が決まった後のレグはすべて無効
+            if status != LegStatus::Played {
+                leg_records
+                    .iter_mut()
+                    .for_each(|record| record.is_valid = false);
+                walker.finish_leg(self, None);
+                continue;
+            }
+            let winner = leg.get_decided_set(leg_records).map(|(index, win_flag)| {
+                // ポイントのリセットはここではしない
+                leg_records[index].point = leg.point;
+                leg_records[index + 1..]
+                    .iter_mut()
+                    .for_each(|record| record.is_valid = false);
+                win_flag
+            });
+            walker.finish_leg(self, winner);
+        }
+    }
+
+    // 行われた順のセット（種類がわかれば種類と、チーム側の勝敗）をレコードの位置に当てはめる
+    // 途中までの入力なら、残りのレコードは Open のまま
+    pub fn place_sets(&self, sets: &[(Option<GameType>, bool)]) -> Result<Vec<SetSlot>, SetError> {
+        let mut slots = vec![SetSlot::Open; self.get_games().len()];
+        let mut walker = LegWalker::default();
+        let mut next = 0;
+        // 最後に決着したレグと、同点でなかったため行わなかったレグ
+        let mut decided_leg: Option<&LegFormat> = None;
+        let mut skipped_leg: Option<&LegFormat> = None;
+        while let Some((leg, range, status)) = walker.next_leg(self) {
+            if status != LegStatus::Played {
+                if status == LegStatus::NotTied {
+                    skipped_leg = Some(leg);
+                }
+                slots[range]
+                    .iter_mut()
+                    .for_each(|slot| *slot = SetSlot::Skipped);
+                walker.finish_leg(self, None);
+                continue;
+            }
+            let need = leg.get_wins_needed();
+            let (mut won, mut lost) = (0, 0);
+            for slot in range {
+                if won == need || lost == need {
+                    slots[slot] = SetSlot::Skipped;
+                    continue;
+                }
+                let Some((game_type, win_flag)) = sets.get(next) else {
+                    return Ok(slots);
+                };
+                if let Some(game_type) = game_type.filter(|game_type| *game_type != leg.game_type) {
+                    return Err(match decided_leg {
+                        Some(decided) if decided.game_type == game_type => SetError::LegDecided {
+                            set: next + 1,
+                            game_type,
+                        },
+                        _ => SetError::UnexpectedGame {
+                            set: next + 1,
+                            expected: leg.game_type,
+                            found: game_type,
+                        },
+                    });
+                }
+                slots[slot] = SetSlot::Played(next);
+                next += 1;
+                if *win_flag {
+                    won += 1;
+                } else {
+                    lost += 1;
+                }
+            }
+            if won < need && lost < need {
+                return Ok(slots);
+            }
+            walker.finish_leg(self, Some(won == need));
+            decided_leg = Some(leg);
+        }
+        let score = walker.get_score(self);
+        let Some((game_type, _)) = sets.get(next) else {
+            return Ok(slots);
+        };
+        let set = next + 1;
+        Err(match (skipped_leg, decided_leg, game_type) {
+            _ if score.win_flag.is_some() => SetError::MatchDecided { set },
+            (Some(skipped), _, None) => SetError::NotTied {
+                set,
+                game_type: skipped.game_type,
+                team_point: score.team_point,
+                opponent_team_point: score.opponent_team_point,
+            },
+            (Some(skipped), _, Some(game_type)) if skipped.game_type == *game_type => {
+                SetError::NotTied {
+                    set,
+                    game_type: *game_type,
+                    team_point: score.team_point,
+                    opponent_team_point: score.opponent_team_point,
+                }
+            }
+            (_, Some(decided), Some(game_type)) if decided.game_type == *game_type => {
+                SetError::LegDecided {
+                    set,
+                    game_type: *game_type,
+                }
+            }
+            _ => SetError::TooManySets { set },
+        })
+    }
+
+    // レコードの位置ごとの結果を確かめる。None は結果がないセット
+    // 決着後のセットは None にする。結果がないセットの後に結果があってはいけない
+    // 入力より後ろのレコードは None と同じ。決着後なら Skipped、まだ行われうるなら Open
+    pub fn check_results(&self, results: &[Option<bool>]) -> Result<Vec<SetSlot>, SetError> {
+        let games = self.get_games().len();
+        if results.len() > games {
+            return Err(SetError::TooManySets { set: games + 1 });
+        }
+        let mut slots = vec![SetSlot::Open; games];
+        let mut walker = LegWalker::default();
+        // 最初に結果がなかったセット。以降はすべて結果がないこと
+        let mut missing: Option<usize> = None;
+        while let Some((leg, range, status)) = walker.next_leg(self) {
+            let score = walker.get_score(self);
+            let need = leg.get_wins_needed();
+            let (mut won, mut lost) = (0, 0);
+            for slot in range {
+                let result = results.get(slot).unwrap_or(&None);
+                let set = slot + 1;
+                match (result, missing) {
+                    (Some(_), Some(missing)) => {
+                        return Err(SetError::MissingSet { set: missing + 1 })
+                    }
+                    (Some(_), None) if status == LegStatus::MatchDecided => {
+                        return Err(SetError::MatchDecided { set })
+                    }
+                    (Some(_), None) if status == LegStatus::NotTied => {
+                        return Err(SetError::NotTied {
+                            set,
+                            game_type: leg.game_type,
+                            team_point: score.team_point,
+                            opponent_team_point: score.opponent_team_point,
+                        })
+                    }
+                    (Some(_), None) if won == need || lost == need => {
+                        return Err(SetError::LegDecided {
+                            set,
+                            game_type: leg.game_type,
+                        })
+                    }
+                    (Some(win_flag), None) => {
+                        slots[slot] = SetSlot::Played(slot);
+                        if *win_flag {
+                            won += 1;
+                        } else {
+                            lost += 1;
+                        }
+                    }
+                    (None, _)
+                        if missing.is_none()
+                            && (status != LegStatus::Played || won == need || lost == need) =>
+                    {
+                        slots[slot] = SetSlot::Skipped;
+                    }
+                    (None, _) => {
+                        missing.get_or_insert(slot);
+                    }
+                }
+            }
+            let winner = match (won == need, lost == need) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+            walker.finish_leg(self, winner);
+        }
+        Ok(slots)
+    }
+
+    // check_results で確かめた結果をレコードに書き込む。エラーの場合は何も変えない
+    // ポイントは書き込むたび初期化するので、後で correct_records を呼ぶこと
+    pub fn set_results(
+        &self,
+        records: &mut [SflRecord],
+        results: &[Option<bool>],
+    ) -> Result<(), SetError> {
+        let slots = self.check_results(results)?;
+        for (record, slot) in records.iter_mut().zip(slots) {
+            record.point = 0;
+            match slot {
+                SetSlot::Played(index) => {
+                    record.win_flag = results[index].unwrap();
+                    record.is_valid = true;
+                    record.is_prediction = false;
+                }
+                SetSlot::Skipped => {
+                    record.win_flag = false;
+                    record.is_valid = false;
+                    record.is_prediction = false;
+                }
+                // まだ行われていないセットは初期状態に戻す
+                SetSlot::Open => {
+                    record.win_flag = false;
+                    record.is_valid = false;
+                    record.is_prediction = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_decided(&self, score: &MatchScore) -> bool {
+        match self.win_threshold {
+            Some(threshold) => {
+                score.team_point >= threshold || score.opponent_team_point >= threshold
+            }
+            None => false,
+        }
+    }
+
+    // レコードを書き換えずにポイントと勝敗を集計する
+    pub fn evaluate(&self, records: &[SflRecord]) -> MatchScore {
+        let mut walker = LegWalker::default();
+        while let Some((leg, range, status)) = walker.next_leg(self) {
+            if status == LegStatus::MatchDecided {
+                break;
+            }
+            let winner = match status {
+                LegStatus::Played => leg
+                    .get_decided_set(&records[range])
+                    .map(|(_, win_flag)| win_flag),
+                _ => None,
+            };
+            walker.finish_leg(self, winner);
+        }
+        walker.get_score(self)
+    }
+
+    // 予想のセットを get_win_percentage の勝率で分岐させたときの試合結果の分布
+    // correct_records と同じ規則でたどり、同じ状態になった分岐はまとめる
+    pub fn get_outcomes<F: Fn(&SflRecord) -> f64>(
+        &self,
+        records: &[SflRecord],
+        get_win_percentage: &F,
+    ) -> OutcomeDistribution {
+        let mut states = vec![(OutcomeState::default(), 1_f64)];
+        for (leg, leg_records) in self.split_legs(records) {
+            for (state, _) in states.iter_mut() {
+                state.won = 0;
+                state.lost = 0;
+                state.progress = match state.walker.next_leg(self) {
+                    Some((_, _, LegStatus::Played)) => LegProgress::Open,
+                    _ => LegProgress::Skipped,
+                };
+            }
+            for record in leg_records {
+                let mut next_states = vec![];
+                for (state, probability) in states {
+                    if !matches!(state.progress, LegProgress::Open | LegProgress::Broken) {
+                        add_state(&mut next_states, state, probability);
+                    } else if record.is_prediction {
+                        let team_win_percentage = get_win_percentage(record);
+                        for (win_flag, p) in [
+                            (true, team_win_percentage),
+                            (false, 1_f64 - team_win_percentage),
+                        ] {
+                            add_state(
+                                &mut next_states,
+                                leg.apply(state, record, win_flag),
+                                probability * p,
+                            );
+                        }
+                    } else if record.is_valid {
+                        add_state(
+                            &mut next_states,
+                            leg.apply(state, record, record.win_flag),
+                            probability,
+                        );
+                    } else {
+                        // 以降のセットは get_decided_set で見られない
+                        let mut state = state;
+                        if state.progress == LegProgress::Open {
+                            state.progress = LegProgress::Broken;
+                        }
+                        add_state(&mut next_states, state, probability);
+                    }
+                }
+                states = next_states;
+            }
+            for (state, _) in states.iter_mut() {
+                let winner =
+                    (state.progress == LegProgress::Decided).then_some(state.won > state.lost);
+                state.walker.finish_leg(self, winner);
+            }
+        }
+        let mut outcomes: OutcomeDistribution = vec![];
+        for (state, probability) in states {
+            match outcomes
+                .iter_mut()
+                .find(|(outcome, _)| *outcome == state.outcome)
+            {
+                Some((_, p)) => *p += probability,
+                None => outcomes.push((state.outcome, probability)),
+            }
+        }
+        outcomes
+    }
+
+    // get_outcomes をポイントだけでまとめたスコアの分布。チーム側のポイントが多い順
+    pub fn get_score_distribution<F: Fn(&SflRecord) -> f64>(
+        &self,
+        records: &[SflRecord],
+        get_win_percentage: &F,
+    ) -> Vec<(MatchScore, f64)> {
+        let mut scores: Vec<(MatchScore, f64)> = vec![];
+        for (outcome, probability) in self.get_outcomes(records, get_win_percentage) {
+            let mut score = MatchScore {
+                team_point: outcome.get_team_point(),
+                opponent_team_point: outcome.get_opponent_team_point(),
+                win_flag: None,
+            };
+            if self.is_decided(&score) {
+                score.win_flag = Some(score.team_point > score.opponent_team_point);
+            }
+            match scores.iter_mut().find(|(s, _)| *s == score) {
+                Some((_, p)) => *p += probability,
+                None => scores.push((score, probability)),
+            }
+        }
+        scores.sort_by(|(a, _), (b, _)| {
+            b.team_point
+                .cmp(&a.team_point)
+                .then(a.opponent_team_point.cmp(&b.opponent_team_point))
+        });
+        scores
+    }
+
+    // 勝者とポイント。win_threshold に到達していない（途中までの結果など）場合は None
+    pub fn get_win_team(&self, records: &[SflRecord]) -> Option<(SflTeam, u32, u32)> {
+        let score = self.evaluate(records);
+        let sfl_match = &records.first()?.sfl_match;
+        let win_team = if score.win_flag? {
+            sfl_match.team
+        } else {
+            sfl_match.opponent_team
+        };
+        Some((win_team, score.team_point, score.opponent_team_point))
+    }
+
+    // 読み込んだ試合形式を確かめる
+    // win_threshold があれば、どの勝敗の組み合わせでもどちらかが到達すること
+    pub fn validate(&self) -> Result<(), String> {
+        if self.legs.is_empty() {
+            return Err("no legs".to_string());
+        }
+        for (index, leg) in self.legs.iter().enumerate() {
+            if leg.sets % 2 == 0 {
+                return Err(format!(
+                    "leg {} ({:?}): sets must be odd, found {}",
+                    index + 1,
+                    leg.game_type,
+                    leg.sets
+                ));
+            }
+        }
+        let Some(threshold) = self.win_threshold else {
+            return Ok(());
+        };
+        if threshold == 0 {
+            return Err("win_threshold must be positive".to_string());
+        }
+        let total_point: u32 = self.legs.iter().map(|leg| leg.point).sum();
+        if threshold > total_point {
+            return Err(format!(
+                "win_threshold {} cannot be reached (legs total {} points)",
+                threshold, total_point
+            ));
+        }
+        // レグの勝敗をすべてたどる。同じ状態はまとめる
+        let mut walkers = vec![LegWalker::default()];
+        for _ in self.legs.iter() {
+            let mut next_walkers = vec![];
+            for walker in walkers {
+                for winner in [true, false] {
+                    let mut next = walker;
+                    next.finish_leg(self, Some(winner));
+                    if !next_walkers.contains(&next) {
+                        next_walkers.push(next);
+                    }
+                }
+            }
+            walkers = next_walkers;
+        }
+        match walkers.iter().find(|walker| !self.is_decided(&walker.score)) {
+            Some(walker) => Err(format!(
+                "a match can end {}-{} without reaching win_threshold {}; add a leg with condition Tied",
+                walker.score.team_point, walker.score.opponent_team_point, threshold
+            )),
+            None => Ok(()),
+        }
+    }
+
+    // プレイオフ、グランドファイナルは勝者を決めるので win_threshold が必要
+    pub fn validate_knockout(&self) -> Result<(), String> {
+        if self.win_threshold.is_none() {
+            return Err("win_threshold is required".to_string());
+        }
+        self.validate()
+    }
+}
+
+// シーズンで使う試合形式
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Deserialize))]
+pub struct SeasonFormats {
+    pub division: MatchFormat,
+    pub playoff: MatchFormat,
+    pub grand_final: MatchFormat,
+}
+
+impl SeasonFormats {
+    pub fn validate(&self) -> Result<(), String> {
+        self.division
+            .validate()
+            .map_err(|e| format!("division: {}", e))?;
+        self.playoff
+            .validate_knockout()
+            .map_err(|e| format!("playoff: {}", e))?;
+        self.grand_final
+            .validate_knockout()
+            .map_err(|e| format!("grand_final: {}", e))
+    }
+}
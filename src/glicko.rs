@@ -0,0 +1,152 @@
+// Glicko-2
+// http://www.glicko.net/glicko/glicko2.pdf
+// レーティングとRDは外からはElo と同じ1500基準の尺度で扱い、計算のときだけGlicko-2の尺度に直す
+
+const SCALE: f64 = 173.7178_f64;
+// ボラティリティの変化のしやすさ
+const TAU: f64 = 0.5_f64;
+const EPSILON: f64 = 0.000001_f64;
+pub const INITIAL_RATING: f64 = 1500_f64;
+pub const INITIAL_DEVIATION: f64 = 350_f64;
+pub const INITIAL_VOLATILITY: f64 = 0.06_f64;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Glicko2Rating {
+    pub rating: f64,
+    // RD
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Glicko2Rating {
+    fn default() -> Glicko2Rating {
+        Glicko2Rating {
+            rating: INITIAL_RATING,
+            deviation: INITIAL_DEVIATION,
+            volatility: INITIAL_VOLATILITY,
+        }
+    }
+}
+
+impl Glicko2Rating {
+    fn mu(&self) -> f64 {
+        (self.rating - INITIAL_RATING) / SCALE
+    }
+    fn phi(&self) -> f64 {
+        self.deviation / SCALE
+    }
+}
+
+fn g(phi: f64) -> f64 {
+    1_f64 / (1_f64 + 3_f64 * phi.powi(2) / std::f64::consts::PI.powi(2)).sqrt()
+}
+
+fn expected(mu: f64, opponent_mu: f64, opponent_phi: f64) -> f64 {
+    1_f64 / (1_f64 + (-g(opponent_phi) * (mu - opponent_mu)).exp())
+}
+
+// 1レーティング期間分の結果で更新する
+// resultsは相手のレーティングと勝ちなら1、負けなら0
+// 結果がない場合はRDだけ広がる（初期値より広くはしない）
+pub fn update_glicko2(player: &Glicko2Rating, results: &[(Glicko2Rating, f64)]) -> Glicko2Rating {
+    let mu = player.mu();
+    let phi = player.phi();
+    let sigma = player.volatility;
+    if results.is_empty() {
+        return Glicko2Rating {
+            rating: player.rating,
+            deviation: ((phi.powi(2) + sigma.powi(2)).sqrt() * SCALE).min(INITIAL_DEVIATION),
+            volatility: sigma,
+        };
+    }
+    let mut v_inverse = 0_f64;
+    let mut delta_sum = 0_f64;
+    for (opponent, score) in results.iter() {
+        let opponent_phi = opponent.phi();
+        let e = expected(mu, opponent.mu(), opponent_phi);
+        v_inverse += g(opponent_phi).powi(2) * e * (1_f64 - e);
+        delta_sum += g(opponent_phi) * (score - e);
+    }
+    let v = 1_f64 / v_inverse;
+    let delta = v * delta_sum;
+
+    // ボラティリティの更新（Illinois法）
+    let a = sigma.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta.powi(2) - phi.powi(2) - v - ex) / (2_f64 * (phi.powi(2) + v + ex).powi(2))
+            - (x - a) / TAU.powi(2)
+    };
+    let mut big_a = a;
+    let mut big_b = if delta.powi(2) > phi.powi(2) + v {
+        (delta.powi(2) - phi.powi(2) - v).ln()
+    } else {
+        let mut k = 1_f64;
+        while f(a - k * TAU) < 0_f64 {
+            k += 1_f64;
+        }
+        a - k * TAU
+    };
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > EPSILON {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0_f64 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2_f64;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    let new_sigma = (big_a / 2_f64).exp();
+
+    let phi_star = (phi.powi(2) + new_sigma.powi(2)).sqrt();
+    let new_phi = 1_f64 / (1_f64 / phi_star.powi(2) + 1_f64 / v).sqrt();
+    let new_mu = mu + new_phi.powi(2) * delta_sum;
+    Glicko2Rating {
+        rating: new_mu * SCALE + INITIAL_RATING,
+        deviation: new_phi * SCALE,
+        volatility: new_sigma,
+    }
+}
+
+// 両者のRDを合わせた分だけ勝率は50%に寄る
+pub fn get_glicko2_win_percentage(a: &Glicko2Rating, b: &Glicko2Rating) -> (f64, f64) {
+    let phi = (a.phi().powi(2) + b.phi().powi(2)).sqrt();
+    let a_win_percentage = expected(a.mu(), b.mu(), phi);
+    (a_win_percentage, 1_f64 - a_win_percentage)
+}
+
+// gamesは (レーティング期間, 自分のキー, 相手のキー, 自分が勝ったか) を期間順に並べたもの
+// 期間中は期間開始時点のレーティングを使い、期間の終わりに全キーをまとめて更新する
+pub fn calc_glicko2_ratings(
+    games: &[(u32, usize, usize, bool)],
+    initial_ratings: Vec<Glicko2Rating>,
+) -> Vec<Glicko2Rating> {
+    let size = initial_ratings.len();
+    let mut ratings = initial_ratings;
+    let mut start = 0;
+    while start < games.len() {
+        let period = games[start].0;
+        let end = games[start..]
+            .iter()
+            .position(|game| game.0 != period)
+            .map_or(games.len(), |n| start + n);
+        let mut results: Vec<Vec<(Glicko2Rating, f64)>> = vec![vec![]; size];
+        for (_, a, b, a_win) in games[start..end].iter() {
+            let score = if *a_win { 1_f64 } else { 0_f64 };
+            results[*a].push((ratings[*b], score));
+            results[*b].push((ratings[*a], 1_f64 - score));
+        }
+        ratings = ratings
+            .iter()
+            .zip(results.iter())
+            .map(|(rating, results)| update_glicko2(rating, results))
+            .collect();
+        start = end;
+    }
+    ratings
+}
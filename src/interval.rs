@@ -0,0 +1,35 @@
+// シミュレーションで出した確率の誤差
+
+// 95%区間
+pub const Z_95: f64 = 1.959964_f64;
+
+// モンテカルロ標準誤差
+pub fn get_standard_error(success_count: u32, count: usize) -> f64 {
+    if count == 0 {
+        return f64::NAN;
+    }
+    let p = success_count as f64 / count as f64;
+    (p * (1_f64 - p) / count as f64).sqrt()
+}
+
+// ウィルソンのスコア区間
+// 0%や100%でも幅が0にならない
+pub fn get_wilson_interval(success_count: u32, count: usize, z: f64) -> (f64, f64) {
+    if count == 0 {
+        return (0_f64, 1_f64);
+    }
+    let n = count as f64;
+    let p = success_count as f64 / n;
+    let z2 = z.powi(2);
+    let center = (p + z2 / (2_f64 * n)) / (1_f64 + z2 / n);
+    let half_width = z / (1_f64 + z2 / n) * (p * (1_f64 - p) / n + z2 / (4_f64 * n.powi(2))).sqrt();
+    (
+        (center - half_width).max(0_f64),
+        (center + half_width).min(1_f64),
+    )
+}
+
+// 全列挙した確率の [確率, 標準誤差, 95%区間の下限, 上限]。誤差0で区間は確率そのもの
+pub fn get_exact_interval(probability: f64) -> Vec<f64> {
+    vec![probability, 0_f64, probability, probability]
+}
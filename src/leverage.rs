@@ -0,0 +1,93 @@
+use crate::scenario::SflScenarioResult;
+use crate::sfl::SflStage;
+
+// 1試合の結果によってチームのプレイオフ進出確率、1位確率がどれだけ動くか
+// swing は結果ごとの条件付き確率が平均からどれだけ離れるかを、結果の確率で重みをつけて平均したもの
+#[derive(Clone, Debug, PartialEq)]
+pub struct TeamLeverage {
+    pub team_index: usize,
+    pub playoff_probability: f64,
+    pub playoff_swing: f64,
+    pub first_probability: f64,
+    pub first_swing: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchLeverage {
+    pub match_index: usize,
+    // 全チームの playoff_swing と first_swing の合計。大きいほど注目の試合
+    pub leverage: f64,
+    teams: Vec<TeamLeverage>,
+}
+
+impl MatchLeverage {
+    // swing の大きい順
+    pub fn get_teams(&self) -> Vec<TeamLeverage> {
+        self.teams.to_owned()
+    }
+}
+
+impl MatchLeverage {
+    // 試合のスコアごとの (確率, そのスコアに決めたシミュレーション結果) から求める
+    pub fn new(
+        match_index: usize,
+        team_count: usize,
+        conditions: &[(f64, SflScenarioResult)],
+    ) -> MatchLeverage {
+        let division = SflStage::division(0);
+        let mut teams: Vec<TeamLeverage> = (0..team_count)
+            .map(|team_index| {
+                let (playoff_probability, playoff_swing) = get_swing(
+                    conditions
+                        .iter()
+                        .map(|(p, result)| (*p, result.get_playoff_probability(team_index))),
+                );
+                let (first_probability, first_swing) =
+                    get_swing(conditions.iter().map(|(p, result)| {
+                        let probability = result.get_place_probability(&division, team_index);
+                        (*p, probability.first().copied().unwrap_or(0_f64))
+                    }));
+                TeamLeverage {
+                    team_index,
+                    playoff_probability,
+                    playoff_swing,
+                    first_probability,
+                    first_swing,
+                }
+            })
+            .collect();
+        teams.sort_by(|a, b| {
+            (b.playoff_swing + b.first_swing).total_cmp(&(a.playoff_swing + a.first_swing))
+        });
+        let leverage = teams
+            .iter()
+            .map(|team| team.playoff_swing + team.first_swing)
+            .sum();
+        MatchLeverage {
+            match_index,
+            leverage,
+            teams,
+        }
+    }
+}
+
+// (結果の確率, 条件付き確率) から、結果の確率で重みをつけた確率と、平均からの差の重みつき平均を返す
+// ほとんど起きない結果で大きく動いても swing は小さい
+pub fn get_swing<I: Iterator<Item = (f64, f64)>>(conditions: I) -> (f64, f64) {
+    let conditions: Vec<(f64, f64)> = conditions.collect();
+    let total: f64 = conditions.iter().map(|(p, _)| p).sum();
+    if total <= 0_f64 {
+        return (0_f64, 0_f64);
+    }
+    let mean = conditions
+        .iter()
+        .map(|(p, probability)| p * probability)
+        .sum::<f64>()
+        / total;
+    let spread = conditions
+        .iter()
+        .map(|(p, probability)| p * (probability - mean).abs())
+        .sum::<f64>()
+        / total;
+    (mean, spread)
+}
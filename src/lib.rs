@@ -1,37 +1,179 @@
+pub mod accumulator;
+pub mod bradley_terry;
+pub mod clinch;
+pub mod exact;
+pub mod format;
+pub mod glicko;
+pub mod interval;
+pub mod leverage;
+pub mod model;
+pub mod prior;
+pub mod results;
+pub mod scenario;
+pub mod season;
 pub mod sfl;
+pub mod snapshot;
 mod utils;
-use crate::sfl::SflRatingSetting::HomeAwayGameType;
-use crate::sfl::SflStage::{
-    JP2024AllDivision, JP2024DivisionF, JP2024DivisionS, JP2024GrandFinal, JP2024Playoff,
+#[cfg(feature = "wasm")]
+pub mod wasm;
+use crate::accumulator::{check_shape, merge_counts, scale_counts, MergeError, PointRange};
+use crate::bradley_terry::BradleyTerry;
+use crate::clinch::{get_clinch_statuses, ClinchStatus};
+use crate::exact::{enumerate_outcomes, ExactResult, EXACT_STATE_LIMIT};
+use crate::format::{MatchOutcome, OutcomeDistribution};
+use crate::glicko::{
+    calc_glicko2_ratings, get_glicko2_win_percentage, Glicko2Rating, INITIAL_DEVIATION,
 };
+use crate::interval::{get_exact_interval, get_standard_error, get_wilson_interval, Z_95};
+use crate::leverage::MatchLeverage;
+use crate::model::WinProbabilityModel;
+use crate::prior::{RatingPrior, RatingSnapshot};
+#[cfg(feature = "serialize")]
+use crate::results::parse_json;
+use crate::results::{import_results, parse_csv, MatchResult, ResultsError};
+use crate::scenario::{ForcedScore, ScenarioError, SflScenario, SflScenarioResult};
+use crate::season::Season;
+#[cfg(feature = "serialize")]
+use crate::season::SeasonError;
+use crate::sfl::SflRatingSetting::{HomeAwayGameType, Player};
+use crate::sfl::SflStageKind::{AllDivision, Division};
 use crate::sfl::{
-    create_key_function_and_init_ratings, get_win_percentage, update_rating, SflMatch, SflRecord,
+    create_key_function_and_init_ratings, get_rating_index, get_win_percentage, update_rating,
+    RatingIndexFunction, SflMatch, SflPlayer, SflRatingModel, SflRatingSetting, SflRecord,
     SflStage, SflTeam,
 };
+use crate::snapshot::{get_input_hash, SimulationSnapshot, SnapshotError};
+pub use crate::utils::set_panic_hook;
 use rand::prelude::*;
-use wasm_bindgen::prelude::*;
-use wasm_bindgen_test::console_log;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
 
-#[wasm_bindgen]
 pub struct SflRating {
+    setting: SflRatingSetting,
+    model: SflRatingModel,
+    // calc_ratings するまではNone
+    rate_index_function: Option<RatingIndexFunction>,
     ratings: Vec<f64>,
+    // Glicko2のときだけ使う
+    deviations: Vec<f64>,
+    volatilities: Vec<f64>,
+    // Noneなら全員1500から
+    prior: Option<RatingPrior>,
+}
+
+impl Default for SflRating {
+    fn default() -> SflRating {
+        SflRating::new()
+    }
 }
 
 impl SflRating {
     pub fn new() -> SflRating {
-        SflRating { ratings: vec![] }
+        SflRating::with_setting(HomeAwayGameType)
+    }
+    pub fn with_setting(setting: SflRatingSetting) -> SflRating {
+        SflRating::with_model(setting, SflRatingModel::Elo)
+    }
+    pub fn with_model(setting: SflRatingSetting, model: SflRatingModel) -> SflRating {
+        SflRating {
+            setting,
+            model,
+            rate_index_function: None,
+            ratings: vec![],
+            deviations: vec![],
+            volatilities: vec![],
+            prior: None,
+        }
+    }
+    // 設定を変えた場合は calc_ratings し直すこと
+    pub fn set_setting(&mut self, setting: SflRatingSetting) {
+        self.setting = setting;
+        self.rate_index_function = None;
+        self.ratings = vec![];
+    }
+    pub fn set_model(&mut self, model: SflRatingModel) {
+        self.model = model;
+        self.rate_index_function = None;
+        self.ratings = vec![];
+    }
+    pub fn set_prior(&mut self, prior: Option<RatingPrior>) {
+        self.prior = prior;
+    }
+    pub fn get_prior(&self) -> Option<&RatingPrior> {
+        self.prior.as_ref()
+    }
+    pub fn get_snapshot(&self, season: &Season) -> RatingSnapshot {
+        RatingSnapshot::from_rating(season, self)
+    }
+    pub fn get_setting(&self) -> SflRatingSetting {
+        self.setting
     }
-    pub fn calc_ratings(&mut self, sfl_stage: &SflStage, sfl_records: &Vec<Vec<SflRecord>>) {
+    pub fn get_model(&self) -> SflRatingModel {
+        self.model
+    }
+    pub fn calc_ratings(
+        &mut self,
+        season: &Season,
+        sfl_stage: &SflStage,
+        sfl_records: &[Vec<SflRecord>],
+    ) {
         let (rate_index_function, mut ratings) =
-            create_key_function_and_init_ratings(HomeAwayGameType, sfl_stage.get_teams());
+            create_key_function_and_init_ratings(self.setting, season, sfl_stage);
+        self.rate_index_function = Some(rate_index_function);
+        let mut initial_deviations = vec![INITIAL_DEVIATION; ratings.len()];
+        if let Some(prior) = self.prior.as_ref() {
+            for (index, (rating, deviation)) in prior
+                .get_initial_ratings(season, self.setting, ratings.len())
+                .into_iter()
+                .enumerate()
+            {
+                ratings[index] = rating;
+                initial_deviations[index] = deviation;
+            }
+        }
+        if self.model == SflRatingModel::Glicko2 {
+            // 節をレーティング期間とする
+            let games: Vec<(u32, usize, usize, bool)> = sfl_records
+                .iter()
+                .flatten()
+                .filter(|record| record.is_valid && !record.is_prediction)
+                .filter_map(|record| {
+                    let (team_index, opponent_team_index) = rate_index_function(record)?;
+                    Some((
+                        record.sfl_match.section,
+                        team_index,
+                        opponent_team_index,
+                        record.win_flag,
+                    ))
+                })
+                .collect();
+            let initial_ratings: Vec<Glicko2Rating> = ratings
+                .iter()
+                .zip(initial_deviations.iter())
+                .map(|(rating, deviation)| Glicko2Rating {
+                    rating: *rating,
+                    deviation: *deviation,
+                    ..Glicko2Rating::default()
+                })
+                .collect();
+            let glicko_ratings = calc_glicko2_ratings(&games, initial_ratings);
+            self.ratings = glicko_ratings.iter().map(|r| r.rating).collect();
+            self.deviations = glicko_ratings.iter().map(|r| r.deviation).collect();
+            self.volatilities = glicko_ratings.iter().map(|r| r.volatility).collect();
+            return;
+        }
         for records in sfl_records.iter() {
-            for record in records.into_iter() {
+            for record in records.iter() {
                 // 無効なセットおよび予想のセットは無視
                 // ただし1マッチ最大12セットのうち、途中のセットが無効になることはあるので、breakはしない
                 if !record.is_valid || record.is_prediction {
                     continue;
                 }
-                let (team_index, opponent_team_index) = rate_index_function(record);
+                let Some((team_index, opponent_team_index)) = rate_index_function(record) else {
+                    continue;
+                };
                 let team_rating = ratings.get(team_index).unwrap();
                 let opponent_team_rating = ratings.get(opponent_team_index).unwrap();
                 let (updated_rating, updated_opponent_rating) =
@@ -41,14 +183,54 @@ impl SflRating {
             }
         }
         self.ratings = ratings;
+        self.deviations = vec![];
+        self.volatilities = vec![];
     }
-    pub fn get_rating(&self, team_index: usize, is_home: bool, is_reader: bool) -> f64 {
-        let index = team_index * 4 + if is_home { 1 } else { 0 } + if is_reader { 2 } else { 0 };
-        self.ratings[index]
+    fn get_glicko2_rating(&self, index: usize) -> Option<Glicko2Rating> {
+        Some(Glicko2Rating {
+            rating: *self.ratings.get(index)?,
+            deviation: *self.deviations.get(index)?,
+            volatility: *self.volatilities.get(index)?,
+        })
+    }
+    // プレイヤー単位の設定ではチームのレーティングはない
+    pub fn get_rating(&self, team_index: usize, is_home: bool, is_reader: bool) -> Option<f64> {
+        let index = get_rating_index(self.setting, team_index, is_home, is_reader)?;
+        self.ratings.get(index).copied()
+    }
+    // Glicko2のRD。Eloでは None
+    pub fn get_rating_deviation(
+        &self,
+        team_index: usize,
+        is_home: bool,
+        is_reader: bool,
+    ) -> Option<f64> {
+        let index = get_rating_index(self.setting, team_index, is_home, is_reader)?;
+        self.deviations.get(index).copied()
+    }
+    pub fn get_player_rating(&self, player: &SflPlayer) -> Option<f64> {
+        match self.setting {
+            Player => self.ratings.get(player.get_index()).copied(),
+            _ => None,
+        }
+    }
+    // レコードのチーム側の勝率。レーティングを引けない場合はNone
+    pub fn get_team_win_percentage(&self, record: &SflRecord) -> Option<f64> {
+        let (team_index, opponent_team_index) = self.rate_index_function?(record)?;
+        let (team_win_percentage, _) = match self.model {
+            SflRatingModel::Elo => get_win_percentage(
+                *self.ratings.get(team_index)?,
+                *self.ratings.get(opponent_team_index)?,
+            ),
+            SflRatingModel::Glicko2 => get_glicko2_win_percentage(
+                &self.get_glicko2_rating(team_index)?,
+                &self.get_glicko2_rating(opponent_team_index)?,
+            ),
+        };
+        Some(team_win_percentage)
     }
 }
 
-#[wasm_bindgen]
 pub struct DivisionPlaceDetail {
     pub first: usize,
     pub second: usize,
@@ -56,7 +238,6 @@ pub struct DivisionPlaceDetail {
     pub time: usize,
 }
 
-#[wasm_bindgen]
 pub struct PlaceToPointDetail {
     pub time: usize,
     pub sum_point: usize,
@@ -64,23 +245,29 @@ pub struct PlaceToPointDetail {
     pub lowest_point: usize,
 }
 
+pub struct MatchScoreProbability {
+    pub team_point: u32,
+    pub opponent_team_point: u32,
+    pub probability: f64,
+}
+
 impl PlaceToPointDetail {
-    fn from_vec(place_to_points: &Vec<usize>) -> Vec<PlaceToPointDetail>{
-        let mut result = vec![];
-        for n in 0..6 {
-            result.push(PlaceToPointDetail {
-                time: place_to_points[n * 4],
-                sum_point: place_to_points[n * 4 + 1],
-                highest_pont: place_to_points[n * 4 + 2],
-                lowest_point: place_to_points[n * 4 + 3],
-            });
-        }
-        result
+    // 一度もその順位にならなかった場合、最高・最低は0
+    fn from_ranges(place_to_points: &[PointRange]) -> Vec<PlaceToPointDetail> {
+        place_to_points
+            .iter()
+            .map(|range| PlaceToPointDetail {
+                time: range.time,
+                sum_point: range.sum_point,
+                highest_pont: range.highest_point.unwrap_or(0),
+                lowest_point: range.lowest_point.unwrap_or(0),
+            })
+            .collect()
     }
 }
 
 impl DivisionPlaceDetail {
-    fn new(params: &Vec<usize>) -> DivisionPlaceDetail {
+    fn new(params: &[usize]) -> DivisionPlaceDetail {
         DivisionPlaceDetail {
             first: params[0],
             second: params[1],
@@ -90,19 +277,18 @@ impl DivisionPlaceDetail {
     }
 }
 
-#[derive(Copy, Clone)]
-#[wasm_bindgen]
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct SflStats {
-    points: [u32; 12],
-    battles: [i32; 12],
+    points: Vec<u32>,
+    battles: Vec<i32>,
 }
 
-#[wasm_bindgen]
 impl SflStats {
-    fn new() -> SflStats {
+    fn new(team_count: usize) -> SflStats {
         SflStats {
-            points: [0_u32; 12],
-            battles: [0_i32; 12],
+            points: vec![0_u32; team_count],
+            battles: vec![0_i32; team_count],
         }
     }
     pub fn get_points(&self) -> Vec<u32> {
@@ -113,350 +299,390 @@ impl SflStats {
     }
 }
 
+// 後から増えたフィールドがない古いJSONも読めるよう、足りないフィールドは既定値にする
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default))]
 pub struct SflSimulationResult {
     pub division_place_count: Vec<Vec<u32>>,
     pub division_points_battles: Vec<Vec<i32>>,
     pub playoff_place_count: Vec<Vec<u32>>,
     pub match_points: Vec<Vec<u32>>,
+    // プレイオフ、グランドファイナルの試合ごとのポイント
+    // ディビジョンごとに1回戦、2回戦の順で並べ、最後にグランドファイナル
+    pub knockout_match_points: Vec<Vec<u32>>,
     pub division_places: Vec<Vec<usize>>,
     pub playoff_places: Vec<usize>,
     pub division_place_detail: Vec<Vec<Vec<usize>>>,
     pub division_place_detail_flatten: Vec<Vec<usize>>,
-    pub place_to_points: Vec<Vec<usize>>,
+    // チームごと、順位ごと
+    pub place_to_points: Vec<Vec<PointRange>>,
+    // この結果を出したシミュレーションの回数とシード
+    pub count: usize,
+    pub seed: u64,
+}
+
+// 以前の固定シード [5; 32] と同じになる
+pub const DEFAULT_SEED: u64 = 0x0505_0505_0505_0505;
+
+// 乱数の系列の数。回数は系列ごとに割り振る
+pub const STREAM_COUNT: usize = 64;
+
+// count 回のうち stream 番の系列で回す回数。余りは先頭の系列から1回ずつ
+pub fn get_stream_share(count: usize, stream_count: usize, stream: usize) -> usize {
+    count / stream_count + usize::from(stream < count % stream_count)
+}
+
+// 8バイトを4回繰り返して32バイトのシードにする
+pub fn create_rng(seed: u64) -> StdRng {
+    create_stream_rng(seed, 0)
+}
+
+// 末尾8バイトに系列の番号を混ぜる。0番はcreate_rngと同じ
+pub fn create_stream_rng(seed: u64, stream: usize) -> StdRng {
+    let bytes = seed.to_le_bytes();
+    let stream_bytes = (stream as u64).to_le_bytes();
+    let mut seed_bytes = [0_u8; 32];
+    for (n, byte) in seed_bytes.iter_mut().enumerate() {
+        *byte = bytes[n % 8];
+        if n >= 24 {
+            *byte ^= stream_bytes[n % 8];
+        }
+    }
+    StdRng::from_seed(seed_bytes)
 }
 
 impl SflSimulationResult {
-    pub fn new() -> SflSimulationResult {
+    pub fn get_place_count(&self, sfl_stage: &SflStage, team_index: usize) -> Vec<u32> {
+        match sfl_stage.kind {
+            Division => {
+                for (index, count) in self.division_place_count.iter().enumerate() {
+                    if index == team_index {
+                        return count.to_owned();
+                    }
+                }
+                vec![]
+            }
+            AllDivision => {
+                for (index, count) in self.playoff_place_count.iter().enumerate() {
+                    if index == team_index {
+                        return count.to_owned();
+                    }
+                }
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+
+    // 1回もシミュレートしていない結果。merge の単位元になる
+    pub fn zero(season: &Season) -> SflSimulationResult {
+        let team_count = season.teams.len();
+        let match_count = season.get_matches(&SflStage::all_division()).len();
+        // 順位の数は最も大きいディビジョンに合わせる
+        let place_count = season
+            .divisions
+            .iter()
+            .map(|division| division.teams.len())
+            .max()
+            .unwrap();
         SflSimulationResult {
-            division_place_count: vec![vec![0_u32; 6]; 12],
-            division_points_battles: vec![vec![0_i32; 2]; 12],
-            playoff_place_count: vec![vec![0_u32; 4]; 12],
-            match_points: vec![vec![0_u32; 4]; 60],
-            division_places: vec![vec![]; 2],
+            division_place_count: vec![vec![0_u32; place_count]; team_count],
+            division_points_battles: vec![vec![0_i32; 2]; team_count],
+            playoff_place_count: vec![vec![0_u32; 4]; team_count],
+            match_points: vec![vec![0_u32; 4]; match_count],
+            knockout_match_points: vec![vec![0_u32; 4]; season.divisions.len() * 2 + 1],
+            division_places: vec![vec![]; season.divisions.len()],
             playoff_places: vec![],
-            division_place_detail: vec![vec![vec![0; 12]; 12]; 12],
+            division_place_detail: vec![vec![vec![0; team_count]; team_count]; team_count],
             division_place_detail_flatten: vec![],
-            place_to_points: vec![
-                vec![
-                    0, 0, 0, 400, 0, 0, 0, 400, 0, 0, 0, 400, 0, 0, 0, 400, 0, 0, 0, 400, 0, 0, 0,
-                    400,
-                ];
-                12
-            ],
+            place_to_points: vec![vec![PointRange::default(); place_count]; team_count],
+            count: 0,
+            seed: DEFAULT_SEED,
         }
     }
 
-    pub fn current_simulated_result() -> SflSimulationResult {
-        SflSimulationResult {
-            division_places: vec![vec![0, 5, 2, 3, 4, 1], vec![9, 8, 6, 7, 10, 11]],
-            playoff_places: vec![9, 0, 5, 2, 6, 8, 10, 3, 7, 4, 1, 11],
-            division_place_count: vec![
-                vec![4448, 3591, 1399, 514, 47, 1],
-                vec![0, 3, 45, 341, 2312, 7299],
-                vec![1280, 2339, 4401, 1622, 313, 45],
-                vec![292, 688, 2013, 5731, 1032, 244],
-                vec![3, 46, 236, 1085, 6220, 2410],
-                vec![3977, 3333, 1906, 707, 76, 1],
-                vec![521, 3580, 3883, 1420, 595, 1],
-                vec![10, 443, 1756, 4232, 3434, 125],
-                vec![429, 4821, 3055, 1208, 487, 0],
-                vec![9038, 830, 119, 12, 1, 0],
-                vec![2, 326, 1187, 3072, 5155, 258],
-                vec![0, 0, 0, 56, 328, 9616],
-            ],
-            division_points_battles: vec![
-                vec![2464300, 130664],
-                vec![1467385, -123573],
-                vec![2249835, 71590],
-                vec![2027345, 261],
-                vec![1689980, -151909],
-                vec![2463265, 72967],
-                vec![2268415, 33135],
-                vec![1965840, -49780],
-                vec![2338345, -5593],
-                vec![2803030, 194552],
-                vec![1866855, 21470],
-                vec![1221505, -193784],
-            ],
-            playoff_place_count: vec![
-                vec![1962, 1444, 3518, 2514],
-                vec![4, 3, 19, 22],
-                vec![1181, 1508, 2211, 3120],
-                vec![169, 411, 829, 1584],
-                vec![7, 30, 64, 184],
-                vec![1667, 1614, 3359, 2576],
-                vec![852, 748, 2954, 3430],
-                vec![73, 129, 528, 1479],
-                vec![546, 713, 3041, 4005],
-                vec![3368, 3265, 3067, 287],
-                vec![171, 135, 410, 799],
-                vec![0, 0, 0, 0],
-            ],
-            match_points: vec![
-                vec![200000, 0, 50000, 200000],
-                vec![200000, 0, 200000, 0],
-                vec![200000, 0, 50000, 200000],
-                vec![100000, 100000, 0, 200000],
-                vec![100000, 100000, 200000, 0],
-                vec![100000, 100000, 200000, 0],
-                vec![100000, 100000, 200000, 0],
-                vec![200000, 0, 0, 250000],
-                vec![100000, 100000, 200000, 0],
-                vec![100000, 100000, 0, 200000],
-                vec![0, 200000, 0, 200000],
-                vec![0, 200000, 0, 200000],
-                vec![100000, 100000, 200000, 0],
-                vec![200000, 0, 200000, 0],
-                vec![0, 200000, 200000, 50000],
-                vec![200000, 0, 0, 250000],
-                vec![200000, 0, 0, 250000],
-                vec![200000, 0, 200000, 0],
-                vec![100000, 100000, 200000, 0],
-                vec![100000, 100000, 200000, 0],
-                vec![100000, 100000, 200000, 0],
-                vec![0, 200000, 200000, 50000],
-                vec![0, 200000, 0, 200000],
-                vec![0, 200000, 0, 200000],
-                vec![100000, 100000, 0, 200000],
-                vec![100000, 100000, 0, 200000],
-                vec![0, 200000, 0, 200000],
-                vec![0, 200000, 200000, 50000],
-                vec![100000, 100000, 200000, 0],
-                vec![0, 200000, 200000, 50000],
-                vec![0, 200000, 0, 200000],
-                vec![200000, 0, 200000, 0],
-                vec![100000, 100000, 200000, 0],
-                vec![200000, 0, 0, 250000],
-                vec![200000, 0, 200000, 0],
-                vec![100000, 100000, 0, 200000],
-                vec![100000, 100000, 200000, 0],
-                vec![0, 200000, 250000, 0],
-                vec![0, 200000, 0, 200000],
-                vec![0, 200000, 0, 200000],
-                vec![200000, 0, 200000, 0],
-                vec![0, 200000, 250000, 0],
-                vec![82680, 117320, 105630, 107480],
-                vec![116560, 83440, 112245, 100390],
-                vec![110880, 89120, 105160, 107435],
-                vec![92410, 107590, 108780, 103440],
-                vec![73250, 126750, 109575, 103855],
-                vec![106700, 93300, 135980, 76000],
-                vec![85480, 114520, 101405, 110775],
-                vec![88460, 111540, 105500, 107145],
-                vec![94480, 105520, 122415, 90250],
-                vec![75200, 124800, 124470, 89560],
-                vec![116830, 83170, 154920, 56060],
-                vec![88500, 111500, 123195, 89685],
-                vec![108110, 91890, 134585, 77375],
-                vec![116350, 83650, 137785, 73735],
-                vec![99630, 100370, 142820, 69980],
-                vec![99330, 100670, 105310, 107400],
-                vec![86960, 113040, 86935, 125560],
-                vec![89800, 110200, 142380, 70885],
-            ],
-            division_place_detail: vec![vec![vec![0; 12]; 12]; 12],
-            division_place_detail_flatten: vec![
-                vec![0, 2, 3, 269],
-                vec![0, 2, 4, 17],
-                vec![0, 2, 5, 1129],
-                vec![0, 3, 2, 97],
-                vec![0, 3, 4, 5],
-                vec![0, 3, 5, 129],
-                vec![0, 4, 2, 5],
-                vec![0, 4, 3, 2],
-                vec![0, 4, 5, 11],
-                vec![0, 5, 1, 7],
-                vec![0, 5, 2, 2100],
-                vec![0, 5, 3, 593],
-                vec![0, 5, 4, 84],
-                vec![2, 0, 3, 194],
-                vec![2, 0, 4, 5],
-                vec![2, 0, 5, 480],
-                vec![2, 3, 0, 94],
-                vec![2, 3, 4, 2],
-                vec![2, 3, 5, 62],
-                vec![2, 4, 5, 3],
-                vec![2, 5, 0, 341],
-                vec![2, 5, 3, 96],
-                vec![2, 5, 4, 3],
-                vec![3, 0, 2, 36],
-                vec![3, 0, 4, 1],
-                vec![3, 0, 5, 54],
-                vec![3, 2, 0, 48],
-                vec![3, 2, 4, 4],
-                vec![3, 2, 5, 35],
-                vec![3, 4, 0, 2],
-                vec![3, 4, 2, 1],
-                vec![3, 4, 5, 2],
-                vec![3, 5, 0, 57],
-                vec![3, 5, 2, 48],
-                vec![3, 5, 4, 4],
-                vec![4, 0, 3, 1],
-                vec![4, 2, 5, 1],
-                vec![4, 3, 0, 1],
-                vec![5, 0, 1, 36],
-                vec![5, 0, 2, 2008],
-                vec![5, 0, 3, 683],
-                vec![5, 0, 4, 93],
-                vec![5, 1, 0, 2],
-                vec![5, 1, 3, 1],
-                vec![5, 2, 0, 660],
-                vec![5, 2, 1, 1],
-                vec![5, 2, 3, 170],
-                vec![5, 2, 4, 5],
-                vec![5, 3, 0, 182],
-                vec![5, 3, 2, 103],
-                vec![5, 3, 4, 13],
-                vec![5, 4, 0, 12],
-                vec![5, 4, 1, 1],
-                vec![5, 4, 2, 3],
-                vec![5, 4, 3, 4],
-                vec![6, 7, 8, 5],
-                vec![6, 7, 9, 14],
-                vec![6, 8, 7, 1],
-                vec![6, 8, 9, 46],
-                vec![6, 8, 10, 1],
-                vec![6, 9, 7, 148],
-                vec![6, 9, 8, 280],
-                vec![6, 9, 10, 23],
-                vec![6, 10, 9, 3],
-                vec![7, 6, 9, 3],
-                vec![7, 8, 6, 1],
-                vec![7, 8, 9, 1],
-                vec![7, 9, 6, 2],
-                vec![7, 9, 8, 2],
-                vec![7, 9, 10, 1],
-                vec![8, 6, 7, 1],
-                vec![8, 6, 9, 48],
-                vec![8, 7, 6, 4],
-                vec![8, 7, 9, 3],
-                vec![8, 9, 6, 293],
-                vec![8, 9, 7, 51],
-                vec![8, 9, 10, 28],
-                vec![8, 10, 9, 1],
-                vec![9, 6, 7, 749],
-                vec![9, 6, 8, 2450],
-                vec![9, 6, 10, 329],
-                vec![9, 7, 6, 195],
-                vec![9, 7, 8, 173],
-                vec![9, 7, 10, 49],
-                vec![9, 8, 6, 3270],
-                vec![9, 8, 7, 745],
-                vec![9, 8, 10, 756],
-                vec![9, 10, 6, 117],
-                vec![9, 10, 7, 60],
-                vec![9, 10, 8, 145],
-                vec![10, 9, 6, 1],
-                vec![10, 9, 7, 1],
-            ],
-            place_to_points: vec![
-                vec![
-                    4448, 1162605, 300, 215, 3591, 870470, 290, 205, 1399, 314130, 260, 190, 514,
-                    107775, 240, 180, 47, 9140, 210, 180, 1, 180, 180, 180,
-                ],
-                vec![
-                    0, 0, 0, 400, 3, 600, 200, 200, 45, 8810, 210, 180, 341, 62650, 210, 155, 2312,
-                    376960, 200, 120, 7299, 1018365, 190, 90,
-                ],
-                vec![
-                    1280, 322640, 275, 220, 2339, 558595, 275, 205, 4401, 973655, 265, 175, 1622,
-                    329605, 245, 165, 313, 57695, 210, 155, 45, 7645, 185, 155,
-                ],
-                vec![
-                    292, 71325, 265, 220, 688, 159880, 255, 200, 2013, 438170, 255, 175, 5731,
-                    1134610, 245, 155, 1032, 183260, 210, 145, 244, 40100, 195, 145,
-                ],
-                vec![
-                    3, 685, 235, 225, 46, 10250, 235, 205, 236, 49330, 225, 175, 1085, 210345, 225,
-                    155, 6220, 1061580, 215, 115, 2410, 357790, 190, 115,
-                ],
-                vec![
-                    3977, 1044800, 300, 220, 3333, 818890, 290, 210, 1906, 434540, 260, 190, 707,
-                    149990, 240, 180, 76, 14865, 210, 180, 1, 180, 180, 180,
-                ],
-                vec![
-                    521, 136340, 290, 230, 3580, 867520, 280, 210, 3883, 860965, 265, 180, 1420,
-                    289650, 240, 170, 595, 113770, 225, 170, 1, 170, 170, 170,
-                ],
-                vec![
-                    10, 2425, 255, 230, 443, 102795, 255, 205, 1756, 382690, 255, 185, 4232,
-                    840380, 235, 155, 3434, 618365, 215, 135, 125, 19185, 175, 135,
-                ],
-                vec![
-                    429, 113155, 290, 230, 4821, 1190290, 290, 210, 3055, 688985, 260, 190, 1208,
-                    251625, 240, 180, 487, 94290, 220, 170, 0, 0, 0, 400,
-                ],
-                vec![
-                    9038, 2564860, 325, 225, 830, 207710, 275, 215, 119, 27615, 255, 215, 12, 2630,
-                    235, 205, 1, 215, 215, 215, 0, 0, 0, 400,
-                ],
-                vec![
-                    2, 480, 245, 235, 326, 73970, 245, 205, 1187, 251895, 235, 175, 3072, 598475,
-                    225, 145, 5155, 904540, 215, 125, 258, 37495, 175, 125,
-                ],
-                vec![
-                    0, 0, 0, 400, 0, 0, 0, 400, 0, 0, 0, 400, 56, 9710, 190, 150, 328, 52170, 180,
-                    130, 9616, 1159625, 180, 70,
-                ],
-            ],
+    // 同じシーズンで別に回した結果を足し合わせる
+    // 順位ごとの最高・最低ポイントは大きい方・小さい方を取る
+    // 並び替えた順位などは足し合わせられないので、必要なら summarize で作り直す
+    // 形が合わなければ何も変えずにエラー
+    pub fn merge(&mut self, other: &SflSimulationResult) -> Result<(), MergeError> {
+        self.check_merge(other)?;
+        self.add_result(other);
+        Ok(())
+    }
+
+    fn check_merge(&self, other: &SflSimulationResult) -> Result<(), MergeError> {
+        check_shape(
+            "division_place_count",
+            &self.division_place_count,
+            &other.division_place_count,
+        )?;
+        check_shape(
+            "division_points_battles",
+            &self.division_points_battles,
+            &other.division_points_battles,
+        )?;
+        check_shape(
+            "playoff_place_count",
+            &self.playoff_place_count,
+            &other.playoff_place_count,
+        )?;
+        check_shape("match_points", &self.match_points, &other.match_points)?;
+        check_shape(
+            "knockout_match_points",
+            &self.knockout_match_points,
+            &other.knockout_match_points,
+        )?;
+        check_shape(
+            "division_place_detail",
+            &self.division_place_detail,
+            &other.division_place_detail,
+        )?;
+        for (detail, other) in self
+            .division_place_detail
+            .iter()
+            .zip(other.division_place_detail.iter())
+        {
+            check_shape("division_place_detail", detail, other)?;
+        }
+        check_shape(
+            "place_to_points",
+            &self.place_to_points,
+            &other.place_to_points,
+        )
+    }
+
+    // 同じシーズンから作った結果どうしか、check_merge で形を確かめてから呼ぶ
+    fn add_result(&mut self, other: &SflSimulationResult) {
+        merge_counts(&mut self.division_place_count, &other.division_place_count);
+        merge_counts(
+            &mut self.division_points_battles,
+            &other.division_points_battles,
+        );
+        merge_counts(&mut self.playoff_place_count, &other.playoff_place_count);
+        merge_counts(&mut self.match_points, &other.match_points);
+        merge_counts(
+            &mut self.knockout_match_points,
+            &other.knockout_match_points,
+        );
+        for (detail, other) in self
+            .division_place_detail
+            .iter_mut()
+            .zip(other.division_place_detail.iter())
+        {
+            merge_counts(detail, other);
+        }
+        for (ranges, other) in self
+            .place_to_points
+            .iter_mut()
+            .zip(other.place_to_points.iter())
+        {
+            for (range, other) in ranges.iter_mut().zip(other.iter()) {
+                range.merge(other);
+            }
+        }
+        self.count += other.count;
+    }
+
+    // 回数を factor 倍した結果にする。確率や平均は変わらない
+    pub fn scale(&mut self, factor: u32) {
+        scale_counts(&mut self.division_place_count, factor);
+        scale_counts(&mut self.division_points_battles, factor as i32);
+        scale_counts(&mut self.playoff_place_count, factor);
+        scale_counts(&mut self.match_points, factor);
+        scale_counts(&mut self.knockout_match_points, factor);
+        for detail in self.division_place_detail.iter_mut() {
+            scale_counts(detail, factor as usize);
+        }
+        for range in self.place_to_points.iter_mut().flatten() {
+            range.scale(factor as usize);
+        }
+        self.count *= factor as usize;
+        self.summarize_flatten();
+    }
+
+    // 足し合わせた回数からディビジョンとプレイオフの順位、上位3チームの組み合わせを作り直す
+    pub fn summarize(&mut self, season: &Season) {
+        let mut division_places: Vec<Vec<usize>> = season
+            .get_division_stages()
+            .iter()
+            .map(|division| {
+                season
+                    .get_teams(division)
+                    .iter()
+                    .map(|team| team.get_index())
+                    .collect()
+            })
+            .collect();
+        for places in division_places.iter_mut() {
+            places.sort_by(|team_index_a, team_index_b| {
+                self.division_points_battles[*team_index_b][0]
+                    .cmp(&self.division_points_battles[*team_index_a][0])
+            });
+        }
+        self.division_places = division_places;
+        let mut playoff_places: Vec<usize> = season
+            .get_teams(&SflStage::all_division())
+            .iter()
+            .map(|team| team.get_index())
+            .collect();
+        playoff_places.sort_by(|team_index_a, team_index_b| {
+            self.playoff_place_count[*team_index_b][0]
+                .cmp(&self.playoff_place_count[*team_index_a][0])
+        });
+        self.playoff_places = playoff_places;
+        self.summarize_flatten();
+    }
+
+    fn summarize_flatten(&mut self) {
+        self.division_place_detail_flatten = vec![];
+        for (i, details) in self.division_place_detail.iter().enumerate() {
+            for (j, detail) in details.iter().enumerate() {
+                for (k, time) in detail.iter().enumerate() {
+                    if *time > 0 {
+                        self.division_place_detail_flatten
+                            .push(vec![i, j, k, *time]);
+                    }
+                }
+            }
         }
     }
+
+    // 同梱しているシミュレーション結果。中身は seasons/jp2024.snapshot.json
+    #[cfg(feature = "serialize")]
+    pub fn current_simulated_result() -> SflSimulationResult {
+        SimulationSnapshot::jp2024()
+            .to_result(&Season::jp2024())
+            .unwrap()
+    }
 }
-#[wasm_bindgen]
 pub struct SflSimulation {
     pub count: usize,
+    // option.random_seed が false のときに使うシード
+    pub seed: u64,
     pub option: SimulationOption,
     pub sfl_stage: SflStage,
+    season: Season,
     sfl_records: Vec<Vec<SflRecord>>,
-    #[wasm_bindgen(skip)]
     pub sfl_rating: SflRating,
+    // Noneならsfl_ratingで勝率を決める
+    win_probability_model: Option<Box<dyn WinProbabilityModel>>,
+    // 入力のハッシュに含めるモデルの鍵。get_model_key がNoneのモデルでは差し替えるたびに引く乱数
+    win_probability_model_key: Option<String>,
     pub max_team_index: usize,
     pub sfl_stats: SflStats,
     result: SflSimulationResult,
+    // 全列挙した場合のみ
+    exact: Option<ExactResult>,
+    // シミュレーションの開始時に試合ごとに求める。Noneの試合はセットごとに抽選する
+    // option.sample_match_outcomes のときとシナリオでスコアを決めた試合のみ
+    match_outcomes: Vec<Option<OutcomeDistribution>>,
+    // simulate_scenario の間だけ入る
+    scenario_scores: Vec<ForcedScore>,
+    // result を出したときの get_input_hash。シミュレートしていなければNone
+    result_input_hash: Option<String>,
 }
 
-#[wasm_bindgen]
 impl SflSimulation {
-    #[wasm_bindgen(constructor)]
+    // simulated なら同梱しているシミュレーション結果を持たせる
+    #[cfg(feature = "serialize")]
     pub fn new(simulated: bool) -> SflSimulation {
-        let sfl_matches = JP2024AllDivision.get_matches();
-        let sfl_records = sfl_matches
-            .iter()
-            .map(|sfl_match| sfl_match.to_records())
-            .collect();
-        let max_team_index: usize = JP2024AllDivision.get_max_team_index();
-        SflSimulation {
+        let mut simulation = SflSimulation::with_season(Season::jp2024());
+        if simulated {
+            simulation
+                .apply_simulation_snapshot(SimulationSnapshot::jp2024())
+                .unwrap();
+        }
+        simulation
+    }
+
+    // 同梱していないシーズンはシミュレート済みの結果を持たない
+    #[cfg(feature = "serialize")]
+    pub fn from_season_json(json: &str) -> Result<SflSimulation, SeasonError> {
+        let season = Season::from_json(json)?;
+        Ok(SflSimulation::with_season(season))
+    }
+
+    fn with_season(season: Season) -> SflSimulation {
+        let sfl_stage = SflStage::all_division();
+        let sfl_records = season.get_initial_records(&sfl_stage);
+        let max_team_index: usize = season.get_max_team_index(&sfl_stage);
+        let sfl_stats = SflStats::new(season.teams.len());
+        let result = SflSimulationResult::zero(&season);
+        let mut simulation = SflSimulation {
             count: 10000,
-            option: SimulationOption { enable_rate: true },
-            sfl_stage: JP2024AllDivision,
+            seed: DEFAULT_SEED,
+            option: SimulationOption {
+                enable_rate: true,
+                random_seed: false,
+                exact_state_limit: EXACT_STATE_LIMIT,
+                sample_match_outcomes: false,
+            },
+            sfl_stage,
+            season,
             sfl_records,
             sfl_rating: SflRating::new(),
+            win_probability_model: None,
+            win_probability_model_key: None,
             max_team_index,
-            sfl_stats: SflStats::new(),
-            result: if simulated {
-                SflSimulationResult::current_simulated_result()
-            } else {
-                SflSimulationResult::new()
-            },
-        }
+            sfl_stats,
+            result,
+            exact: None,
+            match_outcomes: vec![],
+            scenario_scores: vec![],
+            result_input_hash: None,
+        };
+        simulation.calc_ratings();
+        simulation
+    }
+
+    pub fn sfl_stats(&self) -> SflStats {
+        self.sfl_stats.to_owned()
     }
 
-    pub fn get_team_names(&self, stage: SflStage) -> Vec<String> {
-        stage
-            .get_teams()
+    pub fn get_team_names(&self, stage: &SflStage) -> Vec<String> {
+        self.season
+            .get_teams(stage)
             .iter()
-            .map(|team| format!("{:?}", team))
+            .map(|team| self.season.get_team_code(team).to_string())
             .collect()
     }
     pub fn enable_rate(&mut self, flag: bool) {
-        self.option = SimulationOption { enable_rate: flag }
+        self.option.enable_rate = flag;
+    }
+    // trueなら simulate のたびに新しいシードを引く。使ったシードは get_used_seed で取れる
+    pub fn use_random_seed(&mut self, flag: bool) {
+        self.option.random_seed = flag;
+    }
+    pub fn get_used_seed(&self) -> u64 {
+        self.result.seed
+    }
+    pub fn get_used_count(&self) -> usize {
+        self.result.count
     }
     pub fn get_matches(&self) -> Vec<SflMatch> {
-        self.sfl_stage.get_matches()
+        self.season.get_matches(&self.sfl_stage)
+    }
+    // 試合形式のレグの順に、レグの勝者になるのに必要な勝ちセット数
+    pub fn get_leg_wins_needed(&self) -> Vec<u32> {
+        self.season
+            .get_format(&self.sfl_stage)
+            .legs
+            .iter()
+            .map(|leg| leg.get_wins_needed())
+            .collect()
     }
     pub fn get_match_records(&self, match_index: usize) -> Vec<SflRecord> {
         self.sfl_records[match_index].to_owned()
     }
+    // 範囲外の knockout_index なら None
+    pub fn get_knockout_match_points(&self, knockout_index: usize) -> Option<Vec<u32>> {
+        self.result
+            .knockout_match_points
+            .get(knockout_index)
+            .cloned()
+    }
     pub fn get_match_points(&self, match_index: usize) -> Vec<u32> {
         for (index, m) in self.result.match_points.iter().enumerate() {
             if index == match_index {
@@ -465,44 +691,180 @@ impl SflSimulation {
         }
         vec![]
     }
-    pub fn set_match_result(&mut self, match_index: usize, results: Vec<JsValue>) {
-        self.sfl_records[match_index]
-            .iter_mut()
-            .enumerate()
-            .for_each(|(index, record)| {
-                let record_result = results.get(index);
-                if record_result.is_none() {
-                    // 入力の長さが足りない分は初期状態に戻す
-                    record.point = 0;
-                    record.win_flag = false;
-                    record.is_valid = false;
-                    record.is_prediction = true;
-                } else {
-                    // ポイントは更新するたび初期化
-                    record.point = 0;
-                    record.win_flag = record_result.unwrap().as_bool().unwrap();
-                    record.is_valid = true;
-                    record.is_prediction = false;
-                }
-            });
-        for records in self.sfl_records.iter_mut() {
-            self.sfl_stage.correct_records(records);
+    fn calc_ratings(&mut self) {
+        self.sfl_rating
+            .calc_ratings(&self.season, &self.sfl_stage, &self.sfl_records);
+        if let Some(model) = self.win_probability_model.as_mut() {
+            model.fit(&self.season, &self.sfl_stage, &self.sfl_records);
         }
-        self.calc_ratings();
-        self.update_stats();
     }
 
-    fn calc_ratings(&mut self) {
-        self.sfl_rating
-            .calc_ratings(&self.sfl_stage, &self.sfl_records)
+    // 実績全体から Bradley–Terry モデルを推定する（シミュレーションには使わない）
+    pub fn fit_bradley_terry(
+        &self,
+        use_home: bool,
+        use_leader: bool,
+        prior_variance: Option<f64>,
+    ) -> BradleyTerry {
+        let mut model = BradleyTerry::new(use_home, use_leader, prior_variance);
+        model.fit(&self.season, &self.sfl_stage, &self.sfl_records);
+        model
+    }
+    // Bradley–Terry モデルで勝率を決める
+    pub fn set_bradley_terry_model(&mut self, model: BradleyTerry) {
+        self.set_win_probability_model(Box::new(model));
+    }
+    // レーティングで勝率を決める状態に戻す
+    pub fn clear_win_probability_model(&mut self) {
+        self.win_probability_model = None;
+        self.win_probability_model_key = None;
     }
 
+    // プレイヤー単位の設定ではロースターの平均を返す
     pub fn get_rating(&self, team_index: usize, is_home: bool, is_reader: bool) -> f64 {
-        self.sfl_rating.get_rating(team_index, is_home, is_reader)
+        if let Some(rating) = self.sfl_rating.get_rating(team_index, is_home, is_reader) {
+            return rating;
+        }
+        let ratings: Vec<f64> = self
+            .season
+            .get_roster(&SflTeam::new(team_index))
+            .iter()
+            .filter_map(|player| self.sfl_rating.get_player_rating(player))
+            .collect();
+        if ratings.is_empty() {
+            return 1500_f64;
+        }
+        ratings.iter().sum::<f64>() / ratings.len() as f64
+    }
+    pub fn get_rating_setting(&self) -> SflRatingSetting {
+        self.sfl_rating.get_setting()
+    }
+    // 設定を変えるとレーティングを計算し直す
+    pub fn set_rating_setting(&mut self, setting: SflRatingSetting) {
+        self.sfl_rating.set_setting(setting);
+        self.calc_ratings();
+    }
+    pub fn get_rating_model(&self) -> SflRatingModel {
+        self.sfl_rating.get_model()
+    }
+    pub fn set_rating_model(&mut self, model: SflRatingModel) {
+        self.sfl_rating.set_model(model);
+        self.calc_ratings();
+    }
+    // 現在のレーティングをJSONで保存する
+    #[cfg(feature = "serialize")]
+    pub fn get_rating_snapshot(&self) -> String {
+        self.sfl_rating.get_snapshot(&self.season).to_json()
+    }
+    // 保存したレーティングを初期値にする
+    // regression は平均への回帰の割合（0〜1）
+    #[cfg(feature = "serialize")]
+    pub fn set_rating_prior(
+        &mut self,
+        snapshot_json: &str,
+        regression: f64,
+    ) -> Result<(), serde_json::Error> {
+        let snapshot = RatingSnapshot::from_json(snapshot_json)?;
+        self.sfl_rating
+            .set_prior(Some(RatingPrior::new(snapshot, regression)));
+        self.calc_ratings();
+        Ok(())
+    }
+    // 入力した結果、レーティングの設定と事前のレーティング、勝率のモデルから求めたハッシュ
+    pub fn get_input_hash(&self) -> String {
+        get_input_hash(
+            &self.season,
+            &self.sfl_records,
+            self.option.enable_rate,
+            &self.sfl_rating,
+            self.win_probability_model_key.as_deref(),
+        )
+    }
+    // 今の結果が今の入力から出したものでなければtrue。読み込んだスナップショットにも使える
+    pub fn is_result_stale(&self) -> bool {
+        self.result_input_hash.as_deref() != Some(self.get_input_hash().as_str())
+    }
+    // 今の結果を入力のハッシュと一緒にJSONで保存する
+    #[cfg(feature = "serialize")]
+    pub fn get_simulation_snapshot(&self) -> String {
+        self.to_simulation_snapshot().to_json()
+    }
+    // 保存したシミュレーション結果を読み込む。入力と合っているかは is_result_stale で確かめる
+    #[cfg(feature = "serialize")]
+    pub fn load_simulation_snapshot(&mut self, json: &str) -> Result<(), SnapshotError> {
+        self.apply_simulation_snapshot(SimulationSnapshot::from_json(json)?)
+    }
+    #[cfg(feature = "serialize")]
+    pub fn load_simulation_snapshot_bytes(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        self.apply_simulation_snapshot(SimulationSnapshot::from_bytes(bytes)?)
+    }
+    // 結果ファイルを読み込み、すべての試合の結果を置き換える。形式は docs/results.md
+    #[cfg(feature = "serialize")]
+    pub fn import_results_json(&mut self, json: &str) -> Result<(), ResultsError> {
+        self.import_results(&parse_json(json)?)
+    }
+    pub fn import_results_csv(&mut self, csv: &str) -> Result<(), ResultsError> {
+        self.import_results(&parse_csv(csv)?)
+    }
+    pub fn clear_rating_prior(&mut self) {
+        self.sfl_rating.set_prior(None);
+        self.calc_ratings();
+    }
+    pub fn get_rating_deviation(
+        &self,
+        team_index: usize,
+        is_home: bool,
+        is_reader: bool,
+    ) -> Option<f64> {
+        self.sfl_rating
+            .get_rating_deviation(team_index, is_home, is_reader)
+    }
+    pub fn get_player_names(&self, team_index: usize) -> Vec<String> {
+        self.season
+            .get_roster(&SflTeam::new(team_index))
+            .iter()
+            .map(|player| self.season.get_player(player).name.to_owned())
+            .collect()
+    }
+    // 選手の番号はシーズン内の番号（get_player_namesの順ではない）
+    pub fn get_roster(&self, team_index: usize) -> Vec<usize> {
+        self.season
+            .get_roster(&SflTeam::new(team_index))
+            .iter()
+            .map(|player| player.get_index())
+            .collect()
+    }
+    pub fn get_player_rating(&self, player_index: usize) -> Option<f64> {
+        self.sfl_rating
+            .get_player_rating(&SflPlayer::new(player_index))
+    }
+    // セットを戦ったプレイヤーとキャラクターを記録する
+    // 結果が未入力のセットに入れた場合は出場予定として扱う
+    pub fn set_record_player(
+        &mut self,
+        match_index: usize,
+        set_index: usize,
+        player: Option<usize>,
+        opponent_player: Option<usize>,
+        character: Option<String>,
+        opponent_character: Option<String>,
+    ) {
+        let Some(record) = self
+            .sfl_records
+            .get_mut(match_index)
+            .and_then(|records| records.get_mut(set_index))
+        else {
+            return;
+        };
+        record.player = player.map(SflPlayer::new);
+        record.opponent_player = opponent_player.map(SflPlayer::new);
+        record.character = character;
+        record.opponent_character = opponent_character;
+        self.calc_ratings();
     }
     fn update_stats(&mut self) {
         // チームごとに現在ポイントと現在バトル得失を集計
-        for team in self.sfl_stage.get_teams() {
+        for team in self.season.get_teams(&self.sfl_stage) {
             let team_index = team.get_index();
             // チームが含まれる有効なレコードのみ抽出
             let records: Vec<&SflRecord> = self
@@ -549,32 +911,22 @@ impl SflSimulation {
         }
     }
 
-    pub fn get_current_places(&self, stage: SflStage) -> Vec<usize> {
-        let mut division_s_places = vec![];
-        let mut division_f_places = vec![];
-        for (index, d) in self.result.division_places.iter().enumerate() {
-            if index == 0 {
-                for p in d.iter() {
-                    division_s_places.push(*p)
-                }
-            } else {
-                for p in d.iter() {
-                    division_f_places.push(*p)
-                }
-            }
-        }
-
-        match stage {
-            JP2024DivisionS => division_s_places,
-            JP2024DivisionF => division_f_places,
-            JP2024AllDivision => {
+    // 範囲外のディビジョンやプレイオフ以降のステージなら None
+    pub fn get_current_places(&self, stage: &SflStage) -> Option<Vec<usize>> {
+        match stage.kind {
+            Division => self
+                .result
+                .division_places
+                .get(stage.division_index)
+                .cloned(),
+            AllDivision => {
                 let mut playoff_places = vec![];
                 for p in self.result.playoff_places.iter() {
                     playoff_places.push(*p)
                 }
-                playoff_places
+                Some(playoff_places)
             }
-            _ => vec![],
+            _ => None,
         }
     }
 
@@ -589,9 +941,8 @@ impl SflSimulation {
             .collect::<Vec<DivisionPlaceDetail>>()
     }
     pub fn get_place_to_point_detail(&mut self, team_index: usize) -> Vec<PlaceToPointDetail> {
-        let team_place_to_points = &self.result
-            .place_to_points[team_index];
-        PlaceToPointDetail::from_vec(team_place_to_points)
+        let team_place_to_points = &self.result.place_to_points[team_index];
+        PlaceToPointDetail::from_ranges(team_place_to_points)
     }
     pub fn get_expect_point(&self, team_index: usize) -> i32 {
         self.result.division_points_battles[team_index][0]
@@ -599,189 +950,356 @@ impl SflSimulation {
     pub fn get_expect_battle(&self, team_index: usize) -> i32 {
         self.result.division_points_battles[team_index][1]
     }
-    pub fn get_place_count(&self, sfl_stage: SflStage, team_index: usize) -> Vec<u32> {
-        match sfl_stage {
-            JP2024DivisionS | JP2024DivisionF => {
-                for (index, count) in self.result.division_place_count.iter().enumerate() {
-                    if index == team_index {
-                        return count.to_owned();
-                    }
-                }
-                vec![]
+    pub fn get_place_count(&self, sfl_stage: &SflStage, team_index: usize) -> Vec<u32> {
+        self.result.get_place_count(sfl_stage, team_index)
+    }
+    // 実際の結果に scenario の仮の結果を重ねてシミュレートする。実際の結果と simulate の結果はそのまま残る
+    // 直前の simulate と同じシードで回すので、シナリオ同士やシナリオなしの結果と並べて比べられる
+    // レーティングは実際の結果のものを使う
+    pub fn simulate_scenario(
+        &mut self,
+        scenario: &SflScenario,
+    ) -> Result<SflScenarioResult, ScenarioError> {
+        let (sfl_records, scenario_scores) =
+            scenario.apply(&self.season, &self.sfl_stage, &self.sfl_records)?;
+        let baseline_records = std::mem::replace(&mut self.sfl_records, sfl_records);
+        let baseline_result = std::mem::take(&mut self.result);
+        let baseline_exact = self.exact.take();
+        self.scenario_scores = scenario_scores;
+
+        let mut rngs = self.start_simulation_with_seed(baseline_result.seed);
+        if !self.simulate_exact() {
+            self.simulate_batch(&mut rngs, self.count);
+        }
+        self.result.summarize(&self.season);
+        let result = std::mem::replace(&mut self.result, baseline_result);
+        let exact = std::mem::replace(&mut self.exact, baseline_exact);
+
+        self.sfl_records = baseline_records;
+        self.scenario_scores = vec![];
+        self.match_outcomes = vec![];
+        Ok(SflScenarioResult::new(
+            scenario.get_name(),
+            &self.season,
+            result,
+            exact,
+        ))
+    }
+
+    // 終わっていない試合ごとに、ありうるスコアに決めてシミュレートし、各チームの確率の動きで並べる
+    // 1試合につきスコアの数だけ simulate_scenario を回す
+    pub fn get_match_leverages(&mut self) -> Result<Vec<MatchLeverage>, ScenarioError> {
+        let format = self.season.get_format(&self.sfl_stage).to_owned();
+        let mut leverages = vec![];
+        for (match_index, records) in self.sfl_records.to_owned().iter().enumerate() {
+            if !records.iter().any(|record| record.is_prediction) {
+                continue;
             }
-            JP2024AllDivision => {
-                for (index, count) in self.result.playoff_place_count.iter().enumerate() {
-                    if index == team_index {
-                        return count.to_owned();
-                    }
-                }
-                vec![]
+            let scores = format.get_score_distribution(records, &|record: &SflRecord| {
+                self.get_set_win_percentage(record)
+            });
+            let mut conditions = vec![];
+            for (score, probability) in scores {
+                let mut scenario = SflScenario::new(&format!(
+                    "{}-{}",
+                    score.team_point, score.opponent_team_point
+                ));
+                scenario.force_match_score(
+                    match_index,
+                    score.team_point,
+                    score.opponent_team_point,
+                );
+                conditions.push((probability, self.simulate_scenario(&scenario)?));
             }
-            _ => vec![],
+            leverages.push(MatchLeverage::new(
+                match_index,
+                self.season.teams.len(),
+                &conditions,
+            ));
         }
+        leverages.sort_by(|a, b| b.leverage.total_cmp(&a.leverage));
+        Ok(leverages)
+    }
+
+    // 2チームの試合のうち、まだ終わっていない最初の試合。順番は問わない
+    pub fn find_match_index(&self, team_code: &str, opponent_team_code: &str) -> Option<usize> {
+        let team = self.season.find_team(team_code)?;
+        let opponent_team = self.season.find_team(opponent_team_code)?;
+        self.sfl_records.iter().position(|records| {
+            let Some(record) = records.first() else {
+                return false;
+            };
+            let sfl_match = &record.sfl_match;
+            let is_pair = (sfl_match.team == team && sfl_match.opponent_team == opponent_team)
+                || (sfl_match.team == opponent_team && sfl_match.opponent_team == team);
+            is_pair && records.iter().any(|record| record.is_prediction)
+        })
     }
-    pub fn simulate(&mut self, output_flag: bool) {
-        let seed: [u8; 32] = [5; 32];
-        let mut rng: StdRng = rand::SeedableRng::from_seed(seed);
-        self.result = SflSimulationResult::new();
-        for _ in 0..10000 {
-            self.simulate_one_time(&mut rng);
+
+    // [確率, 標準誤差, 95%区間の下限, 上限]
+    // success_count は get_place_count などで取れる回数
+    // 全列挙した場合は誤差0で区間は確率そのもの
+    pub fn get_probability_interval(&self, success_count: u32) -> Vec<f64> {
+        let count = self.result.count;
+        if self.exact.is_some() && count > 0 {
+            return get_exact_interval(success_count as f64 / count as f64);
         }
-        let mut division_places: Vec<Vec<usize>> = [JP2024DivisionS, JP2024DivisionF]
+        let (low, high) = get_wilson_interval(success_count, count, Z_95);
+        let probability = if count == 0 {
+            f64::NAN
+        } else {
+            success_count as f64 / count as f64
+        };
+        vec![
+            probability,
+            get_standard_error(success_count, count),
+            low,
+            high,
+        ]
+    }
+    pub fn get_place_interval(
+        &self,
+        sfl_stage: &SflStage,
+        team_index: usize,
+        place: usize,
+    ) -> Vec<f64> {
+        if let Some(exact) = self.exact.as_ref() {
+            let probabilities = exact.get_place_probability(sfl_stage, team_index);
+            return get_exact_interval(probabilities.get(place).copied().unwrap_or(0_f64));
+        }
+        let success_count = self
+            .get_place_count(sfl_stage, team_index)
+            .get(place)
+            .copied()
+            .unwrap_or(0);
+        self.get_probability_interval(success_count)
+    }
+    pub fn get_playoff_interval(&self, team_index: usize) -> Vec<f64> {
+        if let Some(exact) = self.exact.as_ref() {
+            return get_exact_interval(exact.get_playoff_probability(team_index));
+        }
+        self.get_probability_interval(self.get_playoff_count(team_index))
+    }
+    // プレイオフ進出確率の95%区間のうち最も広いものの幅
+    pub fn get_max_playoff_interval_width(&self) -> f64 {
+        if self.exact.is_some() {
+            return 0_f64;
+        }
+        self.season
+            .get_teams(&SflStage::all_division())
             .iter()
-            .map(|division| {
-                division
-                    .get_teams()
+            .map(|team| {
+                let (low, high) = get_wilson_interval(
+                    self.get_playoff_count(team.get_index()),
+                    self.result.count,
+                    Z_95,
+                );
+                high - low
+            })
+            .fold(0_f64, f64::max)
+    }
+    // 残りの試合の結果によらずプレイオフ進出、1位が決まったか、脱落したか
+    pub fn get_clinch_statuses(&self, sfl_stage: &SflStage) -> Vec<ClinchStatus> {
+        get_clinch_statuses(&self.season, sfl_stage, &self.sfl_records)
+    }
+    // 現在の実績と勝率からの1試合のスコアの分布。チーム側のポイントが多い順
+    pub fn get_match_score_distribution(&self, match_index: usize) -> Vec<MatchScoreProbability> {
+        let Some(records) = self.sfl_records.get(match_index) else {
+            return vec![];
+        };
+        self.season
+            .get_format(&self.sfl_stage)
+            .get_score_distribution(records, &|record: &SflRecord| {
+                self.get_set_win_percentage(record)
+            })
+            .into_iter()
+            .map(|(score, probability)| MatchScoreProbability {
+                team_point: score.team_point,
+                opponent_team_point: score.opponent_team_point,
+                probability,
+            })
+            .collect()
+    }
+    // 直前の simulate が全列挙だったか
+    pub fn is_exact(&self) -> bool {
+        self.exact.is_some()
+    }
+    pub fn get_exact_state_count(&self) -> usize {
+        self.exact.as_ref().map_or(0, |exact| exact.state_count)
+    }
+    // get_place_count と同じ並びの丸めていない確率。全列挙していなければ空
+    pub fn get_exact_place_probability(&self, sfl_stage: &SflStage, team_index: usize) -> Vec<f64> {
+        self.exact
+            .as_ref()
+            .map(|exact| exact.get_place_probability(sfl_stage, team_index))
+            .unwrap_or_default()
+    }
+    fn get_playoff_count(&self, team_index: usize) -> u32 {
+        self.result
+            .division_place_count
+            .get(team_index)
+            .map(|count| count.iter().take(self.season.playoff_team_count).sum())
+            .unwrap_or(0)
+    }
+
+    // STREAM_COUNT 本の乱数の系列を返す
+    fn start_simulation(&mut self) -> Vec<StdRng> {
+        let seed = if self.option.random_seed {
+            rand::random()
+        } else {
+            self.seed
+        };
+        self.result_input_hash = Some(self.get_input_hash());
+        self.start_simulation_with_seed(seed)
+    }
+
+    fn start_simulation_with_seed(&mut self, seed: u64) -> Vec<StdRng> {
+        self.result = SflSimulationResult::zero(&self.season);
+        self.result.seed = seed;
+        self.exact = None;
+        let sample_match_outcomes =
+            self.option.sample_match_outcomes && self.sfl_rating.get_setting() != Player;
+        self.match_outcomes = (0..self.sfl_records.len())
+            .map(|index| {
+                let is_forced = self
+                    .scenario_scores
                     .iter()
-                    .map(|team| team.get_index())
-                    .collect()
+                    .any(|score| score.match_index == index);
+                if sample_match_outcomes || is_forced {
+                    self.get_match_outcome(index)
+                } else {
+                    None
+                }
             })
             .collect();
-        for places in division_places.iter_mut() {
-            places.sort_by(|team_index_a, team_index_b| {
-                self.result.division_points_battles[*team_index_b][0]
-                    .cmp(&self.result.division_points_battles[*team_index_a][0])
-            });
-        }
-        self.result.division_places = division_places;
-        let mut playoff_places: Vec<usize> = JP2024AllDivision
-            .get_teams()
-            .iter()
-            .map(|team| team.get_index())
+        (0..STREAM_COUNT)
+            .map(|stream| create_stream_rng(seed, stream))
+            .collect()
+    }
+
+    // 系列ごとに回数を割り振って回し、系列の順に足し合わせる
+    // parallel フィーチャーではスレッドで回すが、スレッド数やフィーチャーによらず同じシードなら同じ結果になる
+    fn simulate_batch(&mut self, rngs: &mut [StdRng], count: usize) {
+        let stream_count = rngs.len();
+        let simulate_stream = |(stream, rng): (usize, &mut StdRng)| {
+            self.simulate_share(rng, get_stream_share(count, stream_count, stream))
+        };
+        #[cfg(feature = "parallel")]
+        let results: Vec<SflSimulationResult> = rngs
+            .par_iter_mut()
+            .enumerate()
+            .map(simulate_stream)
             .collect();
-        playoff_places.sort_by(|team_index_a, team_index_b| {
-            self.result.playoff_place_count[*team_index_b][0]
-                .cmp(&self.result.playoff_place_count[*team_index_a][0])
-        });
-        self.result.playoff_places = playoff_places;
-        for i in 0..12 {
-            for j in 0..12 {
-                for k in 0..12 {
-                    if self.result.division_place_detail[i][j][k] > 0 {
-                        self.result.division_place_detail_flatten.push(vec![
-                            i,
-                            j,
-                            k,
-                            self.result.division_place_detail[i][j][k],
-                        ]);
-                    }
-                }
-            }
+        #[cfg(not(feature = "parallel"))]
+        let results: Vec<SflSimulationResult> =
+            rngs.iter_mut().enumerate().map(simulate_stream).collect();
+        for result in results.iter() {
+            self.result.add_result(result);
         }
-        if output_flag {
-            console_log!("{:?}", self.result.division_place_count);
-        } else {
-            let simulated_result_str: Vec<String> = [
-                format!("division_places: {:?}", self.result.division_places),
-                format!("playoff_places: {:?}", self.result.playoff_places),
-                format!(
-                    "division_place_count: {:?}",
-                    self.result.division_place_count
-                ),
-                format!(
-                    "division_points_battles: {:?}",
-                    self.result.division_points_battles
-                ),
-                format!("playoff_place_count: {:?}", self.result.playoff_place_count),
-                format!("match_points: {:?}", self.result.match_points),
-                format!(
-                    "division_place_detail_flatten: {:?}",
-                    self.result.division_place_detail_flatten
-                ),
-                format!("place_to_points: {:?}", self.result.place_to_points),
-            ]
+    }
+
+    fn simulate_share(&self, rng: &mut StdRng, share: usize) -> SflSimulationResult {
+        let mut result = SflSimulationResult::zero(&self.season);
+        for _ in 0..share {
+            self.simulate_one_time(rng, &mut result);
+            result.count += 1;
+        }
+        result
+    }
+
+    // シナリオでスコアを決めた試合は、そのスコアになる結果だけに絞る
+    // そのスコアになりえなければ None。ありえないスコアは SflScenario::apply でエラーにしている
+    fn get_match_outcome(&self, index: usize) -> Option<OutcomeDistribution> {
+        let format = self.season.get_format(&self.sfl_stage);
+        let records = &self.sfl_records[index];
+        let outcomes = format.get_outcomes(records, &|record: &SflRecord| {
+            self.get_set_win_percentage(record)
+        });
+        let Some(score) = self
+            .scenario_scores
             .iter()
-            .map(|str| str.replace("[", "vec!["))
-            .collect();
-            let joined = simulated_result_str.join(",");
-            console_log!("{}", joined);
+            .find(|score| score.match_index == index)
+        else {
+            return Some(outcomes);
+        };
+        // 勝率が0か1のセットがあってそのスコアにならない場合は五分で絞る
+        score
+            .condition(&outcomes)
+            .or_else(|| score.condition(&format.get_outcomes(records, &|_: &SflRecord| 0.5_f64)))
+    }
+
+    // 残りの組み合わせが少なければ全列挙して count 回分の結果に直す
+    // プレイヤー単位のレーティングでは出場プレイヤーの抽選があるので全列挙しない
+    fn simulate_exact(&mut self) -> bool {
+        if self.option.exact_state_limit == 0 || self.sfl_rating.get_setting() == Player {
+            return false;
         }
+        let Some(match_outcomes) = (0..self.sfl_records.len())
+            .map(|index| self.get_match_outcome(index))
+            .collect::<Option<Vec<OutcomeDistribution>>>()
+        else {
+            return false;
+        };
+        let Some(exact) = enumerate_outcomes(
+            &self.season,
+            &self.sfl_records,
+            &match_outcomes,
+            &|record: &SflRecord| self.get_set_win_percentage(record),
+            self.option.exact_state_limit,
+        ) else {
+            return false;
+        };
+        let seed = self.result.seed;
+        self.result = exact.to_simulation_result(&self.season, self.count);
+        self.result.seed = seed;
+        self.exact = Some(exact);
+        true
     }
-    fn simulate_one_time(&mut self, rng: &mut StdRng) {
-        let (rate_index_function, _) =
-            create_key_function_and_init_ratings(HomeAwayGameType, self.sfl_stage.get_teams());
 
-        let mut sfl_records = self.sfl_records.to_owned();
+    // 1試合をセットごとに抽選してポイント、バトル得失を集計する
+    fn simulate_match_records(
+        &self,
+        index: usize,
+        rng: &mut StdRng,
+        result: &mut SflSimulationResult,
+        point_count: &mut [u32],
+        battle_count: &mut [i32],
+    ) {
+        let mut records = self.sfl_records[index].to_owned();
         // レーティングに基づきランダムに結果をセット
-        for records in sfl_records.iter_mut() {
-            for record in records.iter_mut() {
-                // 前の試行でポイントが入っているのでリセットする
-                record.point = 0;
-                // すでに行われた結果では is_prediction: false となっているので continue
-                if !record.is_prediction {
-                    // 乱数を消費して影響を減らす
-                    let _: bool = if self.option.enable_rate {
-                        rng.gen_bool(0.5_f64)
-                    } else {
-                        rng.random()
-                    };
-                    continue;
-                }
-                let (team_index, opponent_team_index) = rate_index_function(record);
-                let team_rating = self.sfl_rating.ratings.get(team_index).unwrap();
-                let opponent_team_rating =
-                    self.sfl_rating.ratings.get(opponent_team_index).unwrap();
-                if self.option.enable_rate {
-                    let (team_win_percentage, _) =
-                        get_win_percentage(*team_rating, *opponent_team_rating);
-                    record.win_flag = rng.gen_bool(team_win_percentage);
-                } else {
-                    record.win_flag = rng.random();
-                }
-                record.is_valid = true;
-            }
-
-            // 予想分の補正処理
-            self.sfl_stage.correct_records(records);
-            // let sum: u32 = records.iter().map(|r| r.point).sum();
-            // // ポイントのセットがうまくいっていないと1試合のポイントが45を超える
-            // if sum > 45 || sum < 40 {
-            //     console_log!("{:?}", sum);
-            //     console_log!("{:?}", records);
-            //     console_log!("{:?}", x);
-            //     panic!()
-            // }
-        }
-
-        for (index, sfl_match) in sfl_records.iter().enumerate() {
-            let mut van_away_point = 0_u32;
-            let mut van_home_point = 0_u32;
-            let mut general_away_point = 0_u32;
-            let mut general_home_point = 0_u32;
-            for record in sfl_match {
-                if !record.is_valid || record.point == 0 {
-                    continue;
-                }
-                // is_home = false の前提でコードが書かれている…
-                if record.win_flag {
-                    if record.game_type.is_leader() {
-                        general_away_point += record.point;
-                    } else {
-                        van_away_point += record.point;
-                    }
+        self.sample_lineup(&mut records, rng);
+        for record in records.iter_mut() {
+            // 前の試行でポイントが入っているのでリセットする
+            record.point = 0;
+            // すでに行われた結果では is_prediction: false となっているので continue
+            if !record.is_prediction {
+                // 乱数を消費して影響を減らす
+                let _: bool = if self.option.enable_rate {
+                    rng.random_bool(0.5_f64)
                 } else {
-                    if record.game_type.is_leader() {
-                        general_home_point += record.point;
-                    } else {
-                        van_home_point += record.point;
-                    }
-                }
+                    rng.random()
+                };
+                continue;
             }
-            self.result.match_points[index][0] += van_away_point;
-            self.result.match_points[index][1] += van_home_point;
-            self.result.match_points[index][2] += general_away_point;
-            self.result.match_points[index][3] += general_home_point;
+            self.predict_record(record, rng);
         }
-        // 一次元vectorに変更
-        let sfl_records: Vec<&SflRecord> = sfl_records.iter().flat_map(|x| x).collect();
 
-        // この試行におけるポイント、バトル得失を集計するvector
-        // チームの分だけ初期化
-        let mut point_count = vec![0_u32; self.max_team_index + 1];
-        let mut battle_count = vec![0_i32; self.max_team_index + 1];
+        // 予想分の補正処理
+        self.season
+            .get_format(&self.sfl_stage)
+            .correct_records(&mut records);
+        // let sum: u32 = records.iter().map(|r| r.point).sum();
+        // // ポイントのセットがうまくいっていないと1試合のポイントが45を超える
+        // if sum > 45 || sum < 40 {
+        //     console_log!("{:?}", sum);
+        //     console_log!("{:?}", records);
+        //     console_log!("{:?}", x);
+        //     panic!()
+        // }
+        add_match_points(&mut result.match_points[index], &records);
 
         // レコードごとにポイント集計開始
-        for record in sfl_records.iter() {
+        for record in records.iter() {
             // 無効ならスキップ
             if !record.is_valid {
                 continue;
@@ -798,11 +1316,67 @@ impl SflSimulation {
                 battle_count[opponent_team_index] += 1;
             }
         }
-        let mut playoff_team: Vec<Vec<(SflTeam, u32, i32)>> = vec![vec![], vec![]];
-        for (n, stage) in [JP2024DivisionS, JP2024DivisionF].iter().enumerate() {
+    }
+    // 1試合の結果をまとめて抽選してポイント、バトル得失を集計する
+    fn simulate_match_outcome(
+        &self,
+        index: usize,
+        outcomes: &OutcomeDistribution,
+        rng: &mut StdRng,
+        result: &mut SflSimulationResult,
+        point_count: &mut [u32],
+        battle_count: &mut [i32],
+    ) {
+        let (Some(outcome), Some(record)) = (
+            sample_outcome(outcomes, rng),
+            self.sfl_records[index].first(),
+        ) else {
+            return;
+        };
+        for (match_point, point) in result.match_points[index]
+            .iter_mut()
+            .zip(outcome.match_points)
+        {
+            *match_point += point;
+        }
+        let team_index = record.sfl_match.team.get_index();
+        let opponent_team_index = record.sfl_match.opponent_team.get_index();
+        point_count[team_index] += outcome.get_team_point();
+        point_count[opponent_team_index] += outcome.get_opponent_team_point();
+        battle_count[team_index] += outcome.battle;
+        battle_count[opponent_team_index] -= outcome.battle;
+    }
+    fn simulate_one_time(&self, rng: &mut StdRng, result: &mut SflSimulationResult) {
+        // この試行におけるポイント、バトル得失を集計するvector
+        // チームの分だけ初期化
+        let mut point_count = vec![0_u32; self.max_team_index + 1];
+        let mut battle_count = vec![0_i32; self.max_team_index + 1];
+        for index in 0..self.sfl_records.len() {
+            match self.match_outcomes.get(index).and_then(Option::as_ref) {
+                Some(outcomes) => self.simulate_match_outcome(
+                    index,
+                    outcomes,
+                    rng,
+                    result,
+                    &mut point_count,
+                    &mut battle_count,
+                ),
+                None => self.simulate_match_records(
+                    index,
+                    rng,
+                    result,
+                    &mut point_count,
+                    &mut battle_count,
+                ),
+            }
+        }
+        let division_stages = self.season.get_division_stages();
+        let mut playoff_team: Vec<Vec<(SflTeam, u32, i32)>> = vec![vec![]; division_stages.len()];
+        for (n, stage) in division_stages.iter().enumerate() {
             // ポイントとバトルでソートして順位を算出
-            let mut sortable: Vec<(usize, u32, i32, SflTeam)> = stage
-                .get_teams()
+            let mut sortable: Vec<(usize, u32, i32, SflTeam)> = self
+                .season
+                .get_teams(stage)
                 .iter()
                 .map(|team| {
                     let team_index = team.get_index();
@@ -814,80 +1388,87 @@ impl SflSimulation {
             sortable.sort_by(
                 |(a_team, a_point, a_battle, ..), (b_team, b_point, b_battle, ..)| {
                     b_point
-                        .cmp(&a_point)
-                        .then(b_battle.cmp(&a_battle))
+                        .cmp(a_point)
+                        .then(b_battle.cmp(a_battle))
                         .then(b_team.cmp(a_team))
                 },
             );
             // 順位のカウントアップとポイント・バトルの合計更新
-            for nth in 0..6 {
+            for nth in 0..sortable.len() {
                 let (team_index, point, battle, team) = sortable.get(nth).unwrap().to_owned();
-                self.result.division_place_count[team_index][nth] += 1;
-                self.result.division_points_battles[team_index][0] += point as i32;
-                self.result.division_points_battles[team_index][1] += battle;
+                result.division_place_count[team_index][nth] += 1;
+                result.division_points_battles[team_index][0] += point as i32;
+                result.division_points_battles[team_index][1] += battle;
                 if nth < 3 {
                     playoff_team[n].push((team, point, battle));
                 }
-                self.result.place_to_points[team_index][nth * 4] += 1;
-                self.result.place_to_points[team_index][nth * 4 + 1] += point as usize;
-                self.result.place_to_points[team_index][nth * 4 + 2] =
-                    (point as usize).max(self.result.place_to_points[team_index][nth * 4 + 2]);
-                self.result.place_to_points[team_index][nth * 4 + 3] =
-                    (point as usize).min(self.result.place_to_points[team_index][nth * 4 + 3]);
+                result.place_to_points[team_index][nth].add(point as usize);
+            }
+            if let [first, second, third, ..] = sortable.as_slice() {
+                result.division_place_detail[first.0][second.0][third.0] += 1;
             }
-            let first = sortable.get(0).unwrap().0;
-            let second = sortable.get(1).unwrap().0;
-            let third = sortable.get(2).unwrap().0;
-            self.result.division_place_detail[first][second][third] += 1;
         }
-        for n in 0..2_usize {
+        // プレイオフは2ディビジョンの上位3チームによる形式のみ対応
+        if self.season.playoff_team_count != 3
+            || playoff_team.len() != 2
+            || playoff_team.iter().any(|teams| teams.len() < 3)
+        {
+            return;
+        }
+        // 勝敗が決まらない試合があった試行はプレイオフ以降を数えない
+        let Some(knockout) = self.simulate_knockout(&mut playoff_team, rng) else {
+            return;
+        };
+        for (match_points, records) in result
+            .knockout_match_points
+            .iter_mut()
+            .zip(knockout.records.iter())
+        {
+            add_match_points(match_points, records);
+        }
+        for (team, place) in knockout.places {
+            result.playoff_place_count[team.get_index()][place] += 1;
+        }
+    }
+
+    // プレイオフとグランドファイナルを抽選する。勝敗が決まらない試合があれば None
+    fn simulate_knockout(
+        &self,
+        playoff_team: &mut [Vec<(SflTeam, u32, i32)>],
+        rng: &mut StdRng,
+    ) -> Option<KnockoutResult> {
+        let mut knockout_records = vec![];
+        let mut places = vec![];
+        for division_teams in playoff_team.iter_mut() {
             for m in 0..2_usize {
-                let team_info = &playoff_team[n][2 - m];
-                let opponent_team_info = &playoff_team[n][1 - m];
-                let records =
-                    &mut JP2024Playoff.get_playoff_records(&team_info.0, &opponent_team_info.0);
+                let team_info = &division_teams[2 - m];
+                let opponent_team_info = &division_teams[1 - m];
+                let mut records = self
+                    .season
+                    .get_playoff_records(&team_info.0, &opponent_team_info.0);
+                self.sample_lineup(&mut records, rng);
                 for record in records.iter_mut() {
-                    let (team_index, opponent_team_index) = rate_index_function(record);
-                    let team_rating = self.sfl_rating.ratings.get(team_index).unwrap();
-                    let opponent_team_rating =
-                        self.sfl_rating.ratings.get(opponent_team_index).unwrap();
-                    if self.option.enable_rate {
-                        let (team_win_percentage, _) =
-                            get_win_percentage(*team_rating, *opponent_team_rating);
-                        record.win_flag = rng.gen_bool(team_win_percentage);
-                    } else {
-                        record.win_flag = rng.random();
-                    }
-                    record.is_valid = true;
+                    self.predict_record(record, rng);
                 }
 
-                // 予想分の簡易得点処理
-                let (win_team, ..) = JP2024Playoff.get_win_team(records);
+                // 予想分の補正処理
+                let format = self.season.get_format(&SflStage::playoff());
+                format.correct_records(&mut records);
+                let (win_team, ..) = format.get_win_team(&records)?;
+                knockout_records.push(records);
+                let (winner, loser) = if team_info.0 == win_team {
+                    (team_info.to_owned(), opponent_team_info.to_owned())
+                } else {
+                    (opponent_team_info.to_owned(), team_info.to_owned())
+                };
                 if m == 0 {
-                    if team_info.0 == win_team {
-                        // プレイオフ5位
-                        self.result.playoff_place_count[opponent_team_info.0.get_index()][3] += 1;
-                        // place_sim_counts[opponent_team_info.0.get_index()][11] += 1;
-                        playoff_team[n] = vec![playoff_team[n][0].to_owned(), team_info.to_owned()];
-                    } else {
-                        // プレイオフ5位
-                        self.result.playoff_place_count[team_info.0.get_index()][3] += 1;
-                        // place_sim_counts[team_info.0.get_index()][11] += 1;
-                        playoff_team[n] =
-                            vec![playoff_team[n][0].to_owned(), opponent_team_info.to_owned()];
-                    }
+                    // プレイオフ5位
+                    places.push((loser.0, 3));
+                    *division_teams = vec![division_teams[0].to_owned(), winner];
                 } else {
-                    if team_info.0 == win_team {
-                        // プレイオフ3位
-                        self.result.playoff_place_count[opponent_team_info.0.get_index()][2] += 1;
-                        // place_sim_counts[opponent_team_info.0.get_index()][10] += 1;
-                        playoff_team[n] = vec![team_info.to_owned()];
-                    } else {
-                        // プレイオフ3位
-                        self.result.playoff_place_count[team_info.0.get_index()][2] += 1;
-                        // place_sim_counts[team_info.0.get_index()][10] += 1;
-                        playoff_team[n] = vec![opponent_team_info.to_owned()];
-                    }
+                    // プレイオフ3位
+                    places.push((loser.0, 2));
+                    *division_teams = vec![winner];
                 }
             }
         }
@@ -907,36 +1488,252 @@ impl SflSimulation {
             gf_opponent_team_info = &playoff_team[0][0];
         }
         // グランドファイナル処理ここから
-        let records = &mut JP2024GrandFinal
+        let mut records = self
+            .season
             .get_grand_final_records(&gf_team_info.0, &gf_opponent_team_info.0);
+        self.sample_lineup(&mut records, rng);
         for record in records.iter_mut() {
-            let (team_index, opponent_team_index) = rate_index_function(record);
-            let team_rating = self.sfl_rating.ratings.get(team_index).unwrap();
-            let opponent_team_rating = self.sfl_rating.ratings.get(opponent_team_index).unwrap();
-            if self.option.enable_rate {
-                let (team_win_percentage, _) =
-                    get_win_percentage(*team_rating, *opponent_team_rating);
-                record.win_flag = rng.gen_bool(team_win_percentage);
-            } else {
-                record.win_flag = rng.random();
-            }
-            record.is_valid = true;
+            self.predict_record(record, rng);
         }
-        let (win_team, ..) = JP2024GrandFinal.get_win_team(records);
+        let format = self.season.get_format(&SflStage::grand_final());
+        format.correct_records(&mut records);
+        let (win_team, ..) = format.get_win_team(&records)?;
+        knockout_records.push(records);
         // 優勝
-        self.result.playoff_place_count[win_team.get_index()][0] += 1;
+        places.push((win_team, 0));
+        // 準優勝
         if playoff_team[0][0].0 == win_team {
-            // 準優勝
-            self.result.playoff_place_count[playoff_team[1][0].0.get_index()][1] += 1;
+            places.push((playoff_team[1][0].0, 1));
         } else {
-            // 準優勝
-            self.result.playoff_place_count[playoff_team[0][0].0.get_index()][1] += 1;
+            places.push((playoff_team[0][0].0, 1));
+        }
+        Some(KnockoutResult {
+            records: knockout_records,
+            places,
+        })
+    }
+}
+
+impl SflSimulation {
+    // 同梱していないシーズンはシミュレート済みの結果を持たない
+    pub fn from_season(season: Season) -> SflSimulation {
+        SflSimulation::with_season(season)
+    }
+    pub fn get_season(&self) -> &Season {
+        &self.season
+    }
+    // Web Workerなど別に回した同じシーズンのシミュレーション結果を足し合わせる
+    // シードは元の結果のものを残す
+    // シーズンや形が違えば何も変えずにエラー
+    pub fn merge_simulation(&mut self, other: &SflSimulation) -> Result<(), MergeError> {
+        if self.season.id != other.season.id {
+            return Err(MergeError::Season {
+                expected: self.season.id.clone(),
+                found: other.season.id.clone(),
+            });
+        }
+        self.result.merge(&other.result)?;
+        self.result.summarize(&self.season);
+        Ok(())
+    }
+    pub fn simulate(&mut self) {
+        let mut rngs = self.start_simulation();
+        if !self.simulate_exact() {
+            self.simulate_batch(&mut rngs, self.count);
+        }
+        self.result.summarize(&self.season);
+    }
+
+    // stream 番の系列だけを、simulate でその系列に割り振られる回数だけ回す
+    // Web Workerなどで系列を分けて回し、merge_simulation で足し合わせると同じシードの simulate と同じ結果になる
+    // 全列挙はしない
+    pub fn simulate_stream(&mut self, stream: usize) {
+        let mut rngs = self.start_simulation();
+        if let Some(rng) = rngs.get_mut(stream) {
+            let share = get_stream_share(self.count, STREAM_COUNT, stream);
+            let result = self.simulate_share(rng, share);
+            self.result.add_result(&result);
+        }
+        self.result.summarize(&self.season);
+    }
+
+    // プレイオフ進出確率の区間の最大幅が target_width を下回るまで batch_count 回ずつシミュレートする
+    // max_count 回に達したら収束していなくても止める。実際の回数は get_used_count で取れる
+    pub fn simulate_until(&mut self, target_width: f64, batch_count: usize, max_count: usize) {
+        let mut rngs = self.start_simulation();
+        if self.simulate_exact() {
+            self.result.summarize(&self.season);
+            return;
+        }
+        while self.result.count < max_count {
+            let count = batch_count.max(1).min(max_count - self.result.count);
+            self.simulate_batch(&mut rngs, count);
+            if self.get_max_playoff_interval_width() < target_width {
+                break;
+            }
+        }
+        self.result.summarize(&self.season);
+    }
+
+    pub fn get_result(&self) -> &SflSimulationResult {
+        &self.result
+    }
+    pub fn to_simulation_snapshot(&self) -> SimulationSnapshot {
+        let input_hash = self.result_input_hash.to_owned().unwrap_or_default();
+        SimulationSnapshot::new(&self.season, &input_hash, &self.result)
+    }
+    // 全列挙の結果は持たないので、読み込んだ後の区間はシミュレーションとして扱う
+    pub fn apply_simulation_snapshot(
+        &mut self,
+        snapshot: SimulationSnapshot,
+    ) -> Result<(), SnapshotError> {
+        self.result = snapshot.to_result(&self.season)?;
+        self.result_input_hash = Some(snapshot.input_hash);
+        self.exact = None;
+        Ok(())
+    }
+    #[cfg(feature = "serialize")]
+    pub fn load_simulation_snapshot_file<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<(), SnapshotError> {
+        self.apply_simulation_snapshot(SimulationSnapshot::read_file(path)?)
+    }
+    // results はレコードの位置ごとのチーム側の勝敗。決着後などで行われなかったセットは None
+    // 足りない分のセットは予想に戻す。試合形式に合わない場合は何も変えずにエラーを返す
+    pub fn set_match_result(
+        &mut self,
+        match_index: usize,
+        results: &[Option<bool>],
+    ) -> Result<(), ResultsError> {
+        let sfl_match = self
+            .sfl_records
+            .get(match_index)
+            .and_then(|records| records.first())
+            .map(|record| record.sfl_match.to_owned())
+            .ok_or(ResultsError::UnknownMatchIndex(match_index))?;
+        let format = self.season.get_format(&self.sfl_stage);
+        format
+            .set_results(&mut self.sfl_records[match_index], results)
+            .map_err(|error| ResultsError::InvalidSet {
+                section: sfl_match.section,
+                away: self.season.get_team_code(&sfl_match.team).to_string(),
+                home: self
+                    .season
+                    .get_team_code(&sfl_match.opponent_team)
+                    .to_string(),
+                error,
+            })?;
+        for records in self.sfl_records.iter_mut() {
+            format.correct_records(records);
+        }
+        self.calc_ratings();
+        self.update_stats();
+        Ok(())
+    }
+
+    // 書いていない試合は予想に戻す。エラーの場合は何も変えない
+    pub fn import_results(&mut self, results: &[MatchResult]) -> Result<(), ResultsError> {
+        self.sfl_records = import_results(&self.season, &self.sfl_stage, results)?;
+        self.calc_ratings();
+        self.update_stats();
+        Ok(())
+    }
+
+    // 勝率を決めるモデルを差し替える。現在の実績ですぐにfitする
+    pub fn set_win_probability_model(&mut self, mut model: Box<dyn WinProbabilityModel>) {
+        model.fit(&self.season, &self.sfl_stage, &self.sfl_records);
+        self.win_probability_model_key = Some(
+            model
+                .get_model_key()
+                .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>())),
+        );
+        self.win_probability_model = Some(model);
+    }
+
+    pub fn get_team_win_percentage(&self, record: &SflRecord) -> Option<f64> {
+        match self.win_probability_model.as_ref() {
+            Some(model) => model.get_team_win_percentage(record),
+            None => self.sfl_rating.get_team_win_percentage(record),
+        }
+    }
+
+    // プレイヤー単位のレーティングのときだけ出場プレイヤーを抽選する
+    fn sample_lineup(&self, records: &mut [SflRecord], rng: &mut StdRng) {
+        if self.sfl_rating.get_setting() == Player {
+            self.season.sample_lineup(records, rng);
+        }
+    }
+
+    // 予想に使う勝率。レーティングを使わない場合は五分
+    fn get_set_win_percentage(&self, record: &SflRecord) -> f64 {
+        if self.option.enable_rate {
+            self.get_team_win_percentage(record).unwrap_or(0.5_f64)
+        } else {
+            0.5_f64
+        }
+    }
+
+    // 勝率に基づきランダムに結果をセット
+    // 勝率がわからないレコードは五分とする
+    fn predict_record(&self, record: &mut SflRecord, rng: &mut StdRng) {
+        if self.option.enable_rate {
+            record.win_flag = rng.random_bool(self.get_set_win_percentage(record));
+        } else {
+            record.win_flag = rng.random();
+        }
+        record.is_valid = true;
+    }
+}
+
+// プレイオフとグランドファイナルの1回分の結果
+struct KnockoutResult {
+    // 試合ごとのレコード。knockout_match_points と同じ順
+    records: Vec<Vec<SflRecord>>,
+    // チームとプレイオフの順位の位置
+    places: Vec<(SflTeam, usize)>,
+}
+
+// 確率に従って1つ選ぶ
+fn sample_outcome<'a>(
+    outcomes: &'a OutcomeDistribution,
+    rng: &mut StdRng,
+) -> Option<&'a MatchOutcome> {
+    let mut x: f64 = rng.random();
+    for (outcome, p) in outcomes.iter() {
+        if x < *p {
+            return Some(outcome);
+        }
+        x -= p;
+    }
+    // 丸め誤差で残った分は最後の結果
+    outcomes.last().map(|(outcome, _)| outcome)
+}
+
+// 1試合のポイントを AWAY先鋒中堅, HOME先鋒中堅, AWAY大将以降, HOME大将以降 の順で加算する
+fn add_match_points(match_points: &mut [u32], records: &[SflRecord]) {
+    for record in records {
+        if !record.is_valid || record.point == 0 {
+            continue;
         }
+        // is_home = false の前提でコードが書かれている…
+        let index = match (record.win_flag, record.game_type.is_leader()) {
+            (true, false) => 0,
+            (false, false) => 1,
+            (true, true) => 2,
+            (false, true) => 3,
+        };
+        match_points[index] += record.point;
     }
 }
 
-#[wasm_bindgen]
 #[derive(Copy, Clone)]
 pub struct SimulationOption {
     pub enable_rate: bool,
+    pub random_seed: bool,
+    // 残りの試合の組み合わせがこの数以下ならシミュレーションせずに全列挙する。0なら常にシミュレーション
+    pub exact_state_limit: usize,
+    // ディビジョンの試合をセットごとではなく、試合のスコアの分布からまとめて抽選する
+    // プレイヤー単位のレーティングでは使わない
+    pub sample_match_outcomes: bool,
 }
@@ -0,0 +1,57 @@
+use crate::format::MatchScore;
+use crate::season::Season;
+use crate::sfl::{SflMatch, SflRecord, SflStage};
+use crate::SflRating;
+
+// parallel フィーチャーではモデルをスレッドをまたいで使うので Send + Sync が必要
+#[cfg(feature = "parallel")]
+pub trait ThreadSafe: Send + Sync {}
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> ThreadSafe for T {}
+#[cfg(not(feature = "parallel"))]
+pub trait ThreadSafe {}
+#[cfg(not(feature = "parallel"))]
+impl<T> ThreadSafe for T {}
+
+// シミュレーションで1セットごとの勝率を決めるモデル
+pub trait WinProbabilityModel: ThreadSafe {
+    // 実績のレコードからモデルを作り直す
+    fn fit(&mut self, season: &Season, sfl_stage: &SflStage, sfl_records: &[Vec<SflRecord>]);
+    // レコードのチーム側の勝率。わからない場合はNone（シミュレーションでは五分とする）
+    fn get_team_win_percentage(&self, record: &SflRecord) -> Option<f64>;
+    // 入力のハッシュに含める、モデルの種類と設定。fit は実績だけで決まるので実績は含めなくてよい
+    // None なら中身がわからないモデルとして、差し替えるたびに結果を古い扱いにする
+    fn get_model_key(&self) -> Option<String> {
+        None
+    }
+}
+
+// 1試合のスコアの分布を、セットごとの勝率からマルコフ連鎖で求める
+// 勝率がわからないセットは五分とする
+pub fn get_match_score_distribution(
+    season: &Season,
+    sfl_match: &SflMatch,
+    model: &dyn WinProbabilityModel,
+) -> Vec<(MatchScore, f64)> {
+    let format = season.get_format(&sfl_match.sfl_stage);
+    let records = format.to_records(sfl_match);
+    format.get_score_distribution(&records, &|record: &SflRecord| {
+        model.get_team_win_percentage(record).unwrap_or(0.5_f64)
+    })
+}
+
+impl WinProbabilityModel for SflRating {
+    fn fit(&mut self, season: &Season, sfl_stage: &SflStage, sfl_records: &[Vec<SflRecord>]) {
+        self.calc_ratings(season, sfl_stage, sfl_records);
+    }
+    fn get_team_win_percentage(&self, record: &SflRecord) -> Option<f64> {
+        SflRating::get_team_win_percentage(self, record)
+    }
+    // 事前のレーティングがあるときは鍵で表せないので None
+    fn get_model_key(&self) -> Option<String> {
+        match self.prior {
+            Some(_) => None,
+            None => Some(format!("{:?}/{:?}", self.setting, self.model)),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::glicko::{INITIAL_DEVIATION, INITIAL_RATING};
+use crate::season::Season;
+use crate::sfl::{get_rating_index, SflRatingModel, SflRatingSetting};
+use crate::SflRating;
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+
+// シーズン終了時点などのレーティングを保存したもの
+// シーズンが変わると番号が変わるので、チームはコード、プレイヤーは名前で持つ
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct RatingSnapshot {
+    pub season_id: String,
+    pub setting: SflRatingSetting,
+    pub model: SflRatingModel,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub teams: Vec<TeamRatingSnapshot>,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub players: Vec<PlayerRatingSnapshot>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct TeamRatingSnapshot {
+    pub code: String,
+    // get_rating_index と同じ4つの枠
+    pub ratings: Vec<f64>,
+    // Glicko2のときだけ
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub deviations: Vec<f64>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PlayerRatingSnapshot {
+    pub name: String,
+    pub rating: f64,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub deviation: Option<f64>,
+}
+
+impl RatingSnapshot {
+    pub fn from_rating(season: &Season, sfl_rating: &SflRating) -> RatingSnapshot {
+        let mut teams = vec![];
+        let mut players = vec![];
+        match sfl_rating.setting {
+            SflRatingSetting::Player => {
+                for (index, player) in season.players.iter().enumerate() {
+                    let Some(rating) = sfl_rating.ratings.get(index) else {
+                        continue;
+                    };
+                    players.push(PlayerRatingSnapshot {
+                        name: player.name.to_owned(),
+                        rating: *rating,
+                        deviation: sfl_rating.deviations.get(index).copied(),
+                    });
+                }
+            }
+            _ => {
+                for (index, code) in season.teams.iter().enumerate() {
+                    let Some(ratings) = sfl_rating.ratings.get(index * 4..index * 4 + 4) else {
+                        continue;
+                    };
+                    teams.push(TeamRatingSnapshot {
+                        code: code.to_owned(),
+                        ratings: ratings.to_vec(),
+                        deviations: sfl_rating
+                            .deviations
+                            .get(index * 4..index * 4 + 4)
+                            .map(|deviations| deviations.to_vec())
+                            .unwrap_or_default(),
+                    });
+                }
+            }
+        }
+        RatingSnapshot {
+            season_id: season.id.to_owned(),
+            setting: sfl_rating.setting,
+            model: sfl_rating.model,
+            teams,
+            players,
+        }
+    }
+
+    #[cfg(feature = "serialize")]
+    pub fn from_json(json: &str) -> Result<RatingSnapshot, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[cfg(feature = "serialize")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    // 保存したときの設定で HOME / 大将戦 に当たる枠を引く
+    fn get_team_rating(&self, code: &str, is_home: bool, is_leader: bool) -> Option<(f64, f64)> {
+        let slot = get_rating_index(self.setting, 0, is_home, is_leader)?;
+        let team = self.teams.iter().find(|team| team.code == code)?;
+        let rating = *team.ratings.get(slot)?;
+        let deviation = team
+            .deviations
+            .get(slot)
+            .copied()
+            .unwrap_or(INITIAL_DEVIATION);
+        Some((rating, deviation))
+    }
+
+    fn get_player_rating(&self, name: &str) -> Option<(f64, f64)> {
+        let player = self.players.iter().find(|player| player.name == name)?;
+        Some((player.rating, player.deviation.unwrap_or(INITIAL_DEVIATION)))
+    }
+}
+
+// 前シーズンのレーティングを初期値にする
+// regression は平均への回帰の割合。0なら前シーズンのまま、1なら全員1500から
+#[derive(Clone, Debug)]
+pub struct RatingPrior {
+    pub snapshot: RatingSnapshot,
+    pub regression: f64,
+}
+
+impl RatingPrior {
+    pub fn new(snapshot: RatingSnapshot, regression: f64) -> RatingPrior {
+        RatingPrior {
+            snapshot,
+            regression: regression.clamp(0_f64, 1_f64),
+        }
+    }
+
+    fn regress(&self, (rating, deviation): (f64, f64)) -> (f64, f64) {
+        (
+            rating + (INITIAL_RATING - rating) * self.regression,
+            deviation + (INITIAL_DEVIATION - deviation) * self.regression,
+        )
+    }
+
+    // create_key_function_and_init_ratings と同じ並びで初期レーティングとRDを返す
+    // 前シーズンにいないチーム、プレイヤーは1500から
+    pub fn get_initial_ratings(
+        &self,
+        season: &Season,
+        setting: SflRatingSetting,
+        size: usize,
+    ) -> Vec<(f64, f64)> {
+        (0..size)
+            .map(|index| {
+                let prior = match setting {
+                    SflRatingSetting::Player => season
+                        .players
+                        .get(index)
+                        .and_then(|player| self.snapshot.get_player_rating(&player.name)),
+                    _ => {
+                        let is_home = index % 2 == 1;
+                        let is_leader = index % 4 >= 2;
+                        season.teams.get(index / 4).and_then(|code| {
+                            self.snapshot.get_team_rating(code, is_home, is_leader)
+                        })
+                    }
+                };
+                prior
+                    .map(|prior| self.regress(prior))
+                    .unwrap_or((INITIAL_RATING, INITIAL_DEVIATION))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,488 @@
+// 実際の結果を読み込む
+// 試合は節、節内の順序、チームで指定し、セットごとの勝者（とプレイヤー、キャラクター）を行われた順に並べる
+// 形式は docs/results.md
+use crate::format::{SetError, SetSlot};
+use crate::season::Season;
+use crate::sfl::{GameType, SflPlayer, SflRecord, SflStage, SflTeam};
+#[cfg(feature = "serialize")]
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum ResultsError {
+    Parse {
+        line: usize,
+        message: String,
+    },
+    UnknownTeam(String),
+    // 日程にない試合
+    UnknownMatch {
+        section: u32,
+        branch: Option<u32>,
+        away: String,
+        home: String,
+    },
+    DuplicateMatch {
+        section: u32,
+        away: String,
+        home: String,
+    },
+    // 試合の中のセットの誤り。set は何セット目か（1から）
+    UnknownWinner {
+        section: u32,
+        away: String,
+        home: String,
+        set: usize,
+        winner: String,
+    },
+    // プレイヤーがいないか、そのチームのプレイヤーではない
+    UnknownPlayer {
+        section: u32,
+        away: String,
+        home: String,
+        set: usize,
+        name: String,
+    },
+    InvalidSet {
+        section: u32,
+        away: String,
+        home: String,
+        error: SetError,
+    },
+    // set_match_result で、試合の番号が範囲外
+    UnknownMatchIndex(usize),
+    // set_match_result で、JSから渡された値が真偽値でもnullでもない。set はレコードの位置（1から）
+    NotBoolean {
+        match_index: usize,
+        set: usize,
+    },
+}
+
+impl fmt::Display for ResultsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResultsError::Parse { line, message } => {
+                write!(f, "failed to parse results (line {}): {}", line, message)
+            }
+            ResultsError::UnknownTeam(code) => write!(f, "unknown team: {}", code),
+            ResultsError::UnknownMatch {
+                section,
+                branch,
+                away,
+                home,
+            } => match branch {
+                Some(branch) => write!(
+                    f,
+                    "no match in section {}-{}: {} vs {}",
+                    section, branch, away, home
+                ),
+                None => write!(f, "no match in section {}: {} vs {}", section, away, home),
+            },
+            ResultsError::DuplicateMatch {
+                section,
+                away,
+                home,
+            } => write!(
+                f,
+                "section {} {} vs {} appears more than once",
+                section, away, home
+            ),
+            ResultsError::UnknownWinner {
+                section,
+                away,
+                home,
+                set,
+                winner,
+            } => write!(
+                f,
+                "section {} {} vs {}: set {}: {} is not in this match",
+                section, away, home, set, winner
+            ),
+            ResultsError::UnknownPlayer {
+                section,
+                away,
+                home,
+                set,
+                name,
+            } => write!(
+                f,
+                "section {} {} vs {}: set {}: unknown player {}",
+                section, away, home, set, name
+            ),
+            ResultsError::InvalidSet {
+                section,
+                away,
+                home,
+                error,
+            } => write!(f, "section {} {} vs {}: {}", section, away, home, error),
+            ResultsError::UnknownMatchIndex(match_index) => {
+                write!(f, "unknown match index: {}", match_index)
+            }
+            ResultsError::NotBoolean { match_index, set } => write!(
+                f,
+                "match {}: set {}: result must be a boolean or null",
+                match_index, set
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResultsError {}
+
+// 1試合分の結果。sets は行われた順
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Deserialize))]
+pub struct MatchResult {
+    pub section: u32,
+    // 省略した場合は節とチームだけで探す
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub branch: Option<u32>,
+    pub away: String,
+    pub home: String,
+    pub sets: Vec<SetResult>,
+}
+
+// 勝者のチームコードだけの文字列でも書ける
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Deserialize))]
+#[cfg_attr(feature = "serialize", serde(from = "SetResultDocument"))]
+pub struct SetResult {
+    pub winner: String,
+    // 省略した場合は順番から決める。書いた場合は試合形式と合っているか確かめる
+    pub game: Option<GameType>,
+    pub away_player: Option<String>,
+    pub home_player: Option<String>,
+    pub away_character: Option<String>,
+    pub home_character: Option<String>,
+}
+
+#[cfg(feature = "serialize")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SetResultDocument {
+    Winner(String),
+    Detail {
+        winner: String,
+        #[serde(default)]
+        game: Option<GameType>,
+        #[serde(default)]
+        away_player: Option<String>,
+        #[serde(default)]
+        home_player: Option<String>,
+        #[serde(default)]
+        away_character: Option<String>,
+        #[serde(default)]
+        home_character: Option<String>,
+    },
+}
+
+#[cfg(feature = "serialize")]
+impl From<SetResultDocument> for SetResult {
+    fn from(document: SetResultDocument) -> SetResult {
+        match document {
+            SetResultDocument::Winner(winner) => SetResult {
+                winner,
+                ..SetResult::default()
+            },
+            SetResultDocument::Detail {
+                winner,
+                game,
+                away_player,
+                home_player,
+                away_character,
+                home_character,
+            } => SetResult {
+                winner,
+                game,
+                away_player,
+                home_player,
+                away_character,
+                home_character,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serialize")]
+pub fn parse_json(json: &str) -> Result<Vec<MatchResult>, ResultsError> {
+    serde_json::from_str(json).map_err(|e| ResultsError::Parse {
+        line: e.line(),
+        message: e.to_string(),
+    })
+}
+
+// 1行1セット。1行目は列名で、section, away, home, winner は必須
+// 同じ試合の行は行われた順に続けて書く。空行と # で始まる行は読み飛ばす
+// , や " を含む値は " で囲み、値の中の " は "" と書く。値の中で改行はできない
+pub fn parse_csv(csv: &str) -> Result<Vec<MatchResult>, ResultsError> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    let Some((header_line, header)) = lines.next() else {
+        return Ok(vec![]);
+    };
+    let columns = split_row(header_line, header)?;
+    for required in ["section", "away", "home", "winner"] {
+        if !columns.iter().any(|column| column == required) {
+            return Err(ResultsError::Parse {
+                line: header_line,
+                message: format!("missing column: {}", required),
+            });
+        }
+    }
+    let mut results: Vec<MatchResult> = vec![];
+    for (line, row) in lines {
+        let cells = split_row(line, row)?;
+        if cells.len() > columns.len() {
+            return Err(ResultsError::Parse {
+                line,
+                message: format!("expected {} columns, found {}", columns.len(), cells.len()),
+            });
+        }
+        // 空の値は省略と同じ
+        let get = |name: &str| {
+            columns
+                .iter()
+                .position(|column| *column == name)
+                .and_then(|index| cells.get(index))
+                .filter(|cell| !cell.is_empty())
+                .cloned()
+        };
+        let parse_number = |name: &str| -> Result<Option<u32>, ResultsError> {
+            get(name)
+                .map(|value| {
+                    value.parse().map_err(|_| ResultsError::Parse {
+                        line,
+                        message: format!("{} must be a number: {}", name, value),
+                    })
+                })
+                .transpose()
+        };
+        let required = |name: &str| {
+            get(name).ok_or(ResultsError::Parse {
+                line,
+                message: format!("{} is empty", name),
+            })
+        };
+        let section = parse_number("section")?.ok_or(ResultsError::Parse {
+            line,
+            message: "section is empty".to_string(),
+        })?;
+        let branch = parse_number("branch")?;
+        let (away, home) = (required("away")?, required("home")?);
+        let game = get("game")
+            .map(|game| {
+                parse_game_type(&game).ok_or(ResultsError::Parse {
+                    line,
+                    message: format!("unknown game: {}", game),
+                })
+            })
+            .transpose()?;
+        let set = SetResult {
+            winner: required("winner")?,
+            game,
+            away_player: get("away_player"),
+            home_player: get("home_player"),
+            away_character: get("away_character"),
+            home_character: get("home_character"),
+        };
+        match results.last_mut() {
+            Some(last)
+                if last.section == section
+                    && last.branch == branch
+                    && last.away == away
+                    && last.home == home =>
+            {
+                last.sets.push(set)
+            }
+            _ => results.push(MatchResult {
+                section,
+                branch,
+                away,
+                home,
+                sets: vec![set],
+            }),
+        }
+    }
+    Ok(results)
+}
+
+// 1行を値に分ける。" で囲まない値は前後の空白を除く
+fn split_row(line: usize, row: &str) -> Result<Vec<String>, ResultsError> {
+    let mut cells = vec![];
+    let mut chars = row.chars().peekable();
+    loop {
+        let mut cell = String::new();
+        while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => cell.push('"'),
+                    Some('"') => break,
+                    Some(c) => cell.push(c),
+                    None => {
+                        return Err(ResultsError::Parse {
+                            line,
+                            message: "unterminated quote".to_string(),
+                        })
+                    }
+                }
+            }
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if chars.peek().is_some_and(|c| *c != ',') {
+                return Err(ResultsError::Parse {
+                    line,
+                    message: "unexpected character after quote".to_string(),
+                });
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                if c == '"' {
+                    return Err(ResultsError::Parse {
+                        line,
+                        message: "quote in an unquoted value".to_string(),
+                    });
+                }
+                cell.push(c);
+            }
+            cell = cell.trim().to_string();
+        }
+        cells.push(cell);
+        if chars.next().is_none() {
+            return Ok(cells);
+        }
+    }
+}
+
+fn parse_game_type(game: &str) -> Option<GameType> {
+    match game {
+        "VAN" => Some(GameType::VAN),
+        "MID" => Some(GameType::MID),
+        "GENERAL" => Some(GameType::GENERAL),
+        "EXTRA" => Some(GameType::EXTRA),
+        "PlayoffExtra" => Some(GameType::PlayoffExtra),
+        _ => None,
+    }
+}
+
+// 日程と試合形式に照らして結果をレコードにする
+// 書いていない試合は初期状態（予想）のまま。途中までの試合は残りのセットが予想になる
+pub fn import_results(
+    season: &Season,
+    sfl_stage: &SflStage,
+    results: &[MatchResult],
+) -> Result<Vec<Vec<SflRecord>>, ResultsError> {
+    let matches = season.get_matches(sfl_stage);
+    let format = season.get_format(sfl_stage);
+    let mut sfl_records = season.get_initial_records(sfl_stage);
+    let mut imported = vec![false; matches.len()];
+    for result in results {
+        let away = find_team(season, &result.away)?;
+        let home = find_team(season, &result.home)?;
+        let match_index = matches
+            .iter()
+            .position(|m| {
+                m.section == result.section
+                    && result.branch.is_none_or(|branch| m.branch == branch)
+                    && m.team == away
+                    && m.opponent_team == home
+            })
+            .ok_or(ResultsError::UnknownMatch {
+                section: result.section,
+                branch: result.branch,
+                away: result.away.to_owned(),
+                home: result.home.to_owned(),
+            })?;
+        if imported[match_index] {
+            return Err(ResultsError::DuplicateMatch {
+                section: result.section,
+                away: result.away.to_owned(),
+                home: result.home.to_owned(),
+            });
+        }
+        imported[match_index] = true;
+
+        let mut sets = vec![];
+        for (index, set) in result.sets.iter().enumerate() {
+            let win_flag = if set.winner == result.away {
+                true
+            } else if set.winner == result.home {
+                false
+            } else {
+                return Err(ResultsError::UnknownWinner {
+                    section: result.section,
+                    away: result.away.to_owned(),
+                    home: result.home.to_owned(),
+                    set: index + 1,
+                    winner: set.winner.to_owned(),
+                });
+            };
+            sets.push((set.game, win_flag));
+        }
+        let slots = format
+            .place_sets(&sets)
+            .map_err(|error| ResultsError::InvalidSet {
+                section: result.section,
+                away: result.away.to_owned(),
+                home: result.home.to_owned(),
+                error,
+            })?;
+        let records = &mut sfl_records[match_index];
+        for (record, slot) in records.iter_mut().zip(slots) {
+            match slot {
+                SetSlot::Played(index) => {
+                    let set = &result.sets[index];
+                    let find_player = |name: &Option<String>, team: &SflTeam| {
+                        find_player(season, result, index, name, team)
+                    };
+                    record.win_flag = sets[index].1;
+                    record.is_valid = true;
+                    record.is_prediction = false;
+                    record.player = find_player(&set.away_player, &away)?;
+                    record.opponent_player = find_player(&set.home_player, &home)?;
+                    record.character = set.away_character.to_owned();
+                    record.opponent_character = set.home_character.to_owned();
+                }
+                SetSlot::Skipped => {
+                    record.win_flag = false;
+                    record.is_valid = false;
+                    record.is_prediction = false;
+                }
+                SetSlot::Open => {}
+            }
+        }
+        format.correct_records(records);
+    }
+    Ok(sfl_records)
+}
+
+fn find_team(season: &Season, code: &str) -> Result<SflTeam, ResultsError> {
+    season
+        .find_team(code)
+        .ok_or(ResultsError::UnknownTeam(code.to_string()))
+}
+
+fn find_player(
+    season: &Season,
+    result: &MatchResult,
+    index: usize,
+    name: &Option<String>,
+    team: &SflTeam,
+) -> Result<Option<SflPlayer>, ResultsError> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    season
+        .find_player(name)
+        .filter(|player| season.get_player(player).team == *team)
+        .map(Some)
+        .ok_or(ResultsError::UnknownPlayer {
+            section: result.section,
+            away: result.away.to_owned(),
+            home: result.home.to_owned(),
+            set: index + 1,
+            name: name.to_owned(),
+        })
+}
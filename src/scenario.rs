@@ -0,0 +1,320 @@
+use crate::exact::ExactResult;
+use crate::format::{OutcomeDistribution, SetError, SetSlot};
+use crate::season::Season;
+use crate::sfl::{GameType, SflRecord, SflStage};
+use crate::SflSimulationResult;
+use std::fmt;
+
+// シナリオを実際の結果に重ねられない理由
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScenarioError {
+    UnknownMatchIndex(usize),
+    UnknownSetIndex {
+        match_index: usize,
+        set_index: usize,
+    },
+    // set_match_result と同じく試合形式に合わない
+    InvalidSet {
+        match_index: usize,
+        error: SetError,
+    },
+    ImpossibleScore {
+        match_index: usize,
+        team_point: u32,
+        opponent_team_point: u32,
+    },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::UnknownMatchIndex(match_index) => {
+                write!(f, "match index {} is out of range", match_index)
+            }
+            ScenarioError::UnknownSetIndex {
+                match_index,
+                set_index,
+            } => write!(
+                f,
+                "set index {} is out of range in match {}",
+                set_index, match_index
+            ),
+            ScenarioError::InvalidSet { match_index, error } => {
+                write!(f, "match {}: {}", match_index, error)
+            }
+            ScenarioError::ImpossibleScore {
+                match_index,
+                team_point,
+                opponent_team_point,
+            } => write!(
+                f,
+                "match {} cannot end {}-{}",
+                match_index, team_point, opponent_team_point
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+// シナリオで決める結果。スコアはチーム(AWAY)側、相手(HOME)側の順
+#[derive(Clone, Debug, PartialEq)]
+enum ForcedResult {
+    Set {
+        match_index: usize,
+        set_index: usize,
+        win_flag: bool,
+    },
+    // 行われた順のセット。インポートと同じく決着したレグのセットは飛ばして埋め、残りは予想に戻す
+    Sets {
+        match_index: usize,
+        results: Vec<bool>,
+    },
+    Score(ForcedScore),
+}
+
+// スコアだけ決めた試合。どのセットで取ったかは抽選する
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ForcedScore {
+    pub match_index: usize,
+    pub team_point: u32,
+    pub opponent_team_point: u32,
+}
+
+impl ForcedScore {
+    // このスコアになる結果だけを残して確率を付け直す。ありえなければNone
+    pub fn condition(&self, outcomes: &OutcomeDistribution) -> Option<OutcomeDistribution> {
+        let matched: OutcomeDistribution = outcomes
+            .iter()
+            .filter(|(outcome, _)| {
+                outcome.get_team_point() == self.team_point
+                    && outcome.get_opponent_team_point() == self.opponent_team_point
+            })
+            .copied()
+            .collect();
+        let total: f64 = matched.iter().map(|(_, p)| p).sum();
+        if total <= 0_f64 {
+            return None;
+        }
+        Some(matched.into_iter().map(|(o, p)| (o, p / total)).collect())
+    }
+}
+
+// 実際の結果に仮の結果を重ねたもの。実際の結果は書き換えない
+#[derive(Clone, Debug, Default)]
+pub struct SflScenario {
+    name: String,
+    forced_results: Vec<ForcedResult>,
+}
+
+impl SflScenario {
+    pub fn new(name: &str) -> SflScenario {
+        SflScenario {
+            name: name.to_string(),
+            forced_results: vec![],
+        }
+    }
+    pub fn get_name(&self) -> String {
+        self.name.to_owned()
+    }
+    pub fn force_set(&mut self, match_index: usize, set_index: usize, win_flag: bool) {
+        self.forced_results.push(ForcedResult::Set {
+            match_index,
+            set_index,
+            win_flag,
+        });
+    }
+    // results は行われた順のセットごとに true ならチーム側の勝ち。決着して行われなかったセットは含めない
+    pub fn force_match_sets(&mut self, match_index: usize, results: &[bool]) {
+        self.forced_results.push(ForcedResult::Sets {
+            match_index,
+            results: results.to_vec(),
+        });
+    }
+    pub fn force_match_score(
+        &mut self,
+        match_index: usize,
+        team_point: u32,
+        opponent_team_point: u32,
+    ) {
+        self.forced_results.push(ForcedResult::Score(ForcedScore {
+            match_index,
+            team_point,
+            opponent_team_point,
+        }));
+    }
+    pub fn clear(&mut self) {
+        self.forced_results.clear();
+    }
+}
+
+impl SflScenario {
+    // 実際のレコードに仮の結果を重ねたレコードと、スコアを決めた試合を返す
+    // 後から足した結果ほど優先する。セットを決めた試合は set_match_result と同じく試合形式に合わないとエラー
+    // スコアは重ねたレコードからありうるものでないとエラー
+    pub fn apply(
+        &self,
+        season: &Season,
+        sfl_stage: &SflStage,
+        sfl_records: &[Vec<SflRecord>],
+    ) -> Result<(Vec<Vec<SflRecord>>, Vec<ForcedScore>), ScenarioError> {
+        let format = season.get_format(sfl_stage);
+        let mut records = sfl_records.to_vec();
+        let mut forced_scores: Vec<ForcedScore> = vec![];
+        let mut forced_matches: Vec<usize> = vec![];
+        for forced_result in self.forced_results.iter() {
+            match forced_result {
+                ForcedResult::Set {
+                    match_index,
+                    set_index,
+                    win_flag,
+                } => {
+                    let record = get_match_records(&mut records, *match_index)?
+                        .get_mut(*set_index)
+                        .ok_or(ScenarioError::UnknownSetIndex {
+                            match_index: *match_index,
+                            set_index: *set_index,
+                        })?;
+                    set_record(record, *win_flag);
+                    forced_matches.push(*match_index);
+                }
+                ForcedResult::Sets {
+                    match_index,
+                    results,
+                } => {
+                    let match_records = get_match_records(&mut records, *match_index)?;
+                    // 行われた順のセットをレコードの位置に並べ直す
+                    let sets: Vec<(Option<GameType>, bool)> =
+                        results.iter().map(|win_flag| (None, *win_flag)).collect();
+                    let placed: Vec<Option<bool>> = format
+                        .place_sets(&sets)
+                        .map_err(|error| ScenarioError::InvalidSet {
+                            match_index: *match_index,
+                            error,
+                        })?
+                        .iter()
+                        .map(|slot| match slot {
+                            SetSlot::Played(index) => Some(results[*index]),
+                            SetSlot::Skipped | SetSlot::Open => None,
+                        })
+                        .collect();
+                    format
+                        .set_results(match_records, &placed)
+                        .map_err(|error| ScenarioError::InvalidSet {
+                            match_index: *match_index,
+                            error,
+                        })?;
+                    forced_matches.push(*match_index);
+                }
+                ForcedResult::Score(score) => {
+                    get_match_records(&mut records, score.match_index)?;
+                    forced_scores.retain(|s| s.match_index != score.match_index);
+                    forced_scores.push(*score);
+                }
+            }
+        }
+        for match_index in forced_matches {
+            let match_records = &mut records[match_index];
+            // 決着後などで行われなかったセットと、まだ行われていないセットは None
+            let results: Vec<Option<bool>> = match_records
+                .iter()
+                .map(|record| (record.is_valid && !record.is_prediction).then_some(record.win_flag))
+                .collect();
+            format
+                .set_results(match_records, &results)
+                .map_err(|error| ScenarioError::InvalidSet { match_index, error })?;
+        }
+        for match_records in records.iter_mut() {
+            format.correct_records(match_records);
+        }
+        for score in forced_scores.iter() {
+            // 五分にすればありうる結果がすべて出てくる
+            let outcomes =
+                format.get_outcomes(&records[score.match_index], &|_: &SflRecord| 0.5_f64);
+            if score.condition(&outcomes).is_none() {
+                return Err(ScenarioError::ImpossibleScore {
+                    match_index: score.match_index,
+                    team_point: score.team_point,
+                    opponent_team_point: score.opponent_team_point,
+                });
+            }
+        }
+        Ok((records, forced_scores))
+    }
+}
+
+fn get_match_records(
+    records: &mut [Vec<SflRecord>],
+    match_index: usize,
+) -> Result<&mut Vec<SflRecord>, ScenarioError> {
+    records
+        .get_mut(match_index)
+        .ok_or(ScenarioError::UnknownMatchIndex(match_index))
+}
+
+fn set_record(record: &mut SflRecord, win_flag: bool) {
+    record.win_flag = win_flag;
+    record.is_valid = true;
+    record.is_prediction = false;
+    record.point = 0;
+}
+
+// シナリオごとのシミュレーション結果。同じシードで回すので並べて比べられる
+// 全列挙した場合、確率は丸めた回数ではなく exact から取る
+#[derive(Clone, Debug)]
+pub struct SflScenarioResult {
+    name: String,
+    result: SflSimulationResult,
+    exact: Option<ExactResult>,
+    playoff_team_count: usize,
+}
+
+impl SflScenarioResult {
+    pub fn get_name(&self) -> String {
+        self.name.to_owned()
+    }
+    pub fn is_exact(&self) -> bool {
+        self.exact.is_some()
+    }
+    pub fn get_used_count(&self) -> usize {
+        self.result.count
+    }
+    pub fn get_place_count(&self, sfl_stage: &SflStage, team_index: usize) -> Vec<u32> {
+        self.result.get_place_count(sfl_stage, team_index)
+    }
+    pub fn get_place_probability(&self, sfl_stage: &SflStage, team_index: usize) -> Vec<f64> {
+        if let Some(exact) = self.exact.as_ref() {
+            return exact.get_place_probability(sfl_stage, team_index);
+        }
+        let count = self.result.count.max(1) as f64;
+        self.get_place_count(sfl_stage, team_index)
+            .iter()
+            .map(|c| *c as f64 / count)
+            .collect()
+    }
+    pub fn get_playoff_probability(&self, team_index: usize) -> f64 {
+        if let Some(exact) = self.exact.as_ref() {
+            return exact.get_playoff_probability(team_index);
+        }
+        self.get_place_probability(&SflStage::division(0), team_index)
+            .iter()
+            .take(self.playoff_team_count)
+            .sum()
+    }
+}
+
+impl SflScenarioResult {
+    pub fn new(
+        name: String,
+        season: &Season,
+        result: SflSimulationResult,
+        exact: Option<ExactResult>,
+    ) -> SflScenarioResult {
+        SflScenarioResult {
+            name,
+            result,
+            exact,
+            playoff_team_count: season.playoff_team_count,
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use crate::format::{MatchFormat, SeasonFormats};
+use crate::sfl::SflStageKind::{AllDivision, Division, GrandFinal, Playoff};
+use crate::sfl::{SflMatch, SflPlayer, SflRecord, SflStage, SflTeam};
+use rand::prelude::*;
+#[cfg(feature = "serialize")]
+use serde::Deserialize;
+#[cfg(feature = "serialize")]
+use std::collections::HashMap;
+use std::fmt;
+
+// 同梱しているシーズン定義
+#[cfg(feature = "serialize")]
+const JP2024_SEASON_JSON: &str = include_str!("../seasons/jp2024.json");
+
+#[derive(Debug)]
+pub enum SeasonError {
+    Parse(String),
+    UnknownTeam(String),
+    DuplicateTeam(String),
+    DuplicatePlayer(String),
+    TeamNotInDivision { division: String, team: String },
+    NoDivision,
+    InvalidFormat(String),
+}
+
+impl fmt::Display for SeasonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeasonError::Parse(message) => write!(f, "failed to parse season: {}", message),
+            SeasonError::UnknownTeam(code) => write!(f, "unknown team: {}", code),
+            SeasonError::DuplicateTeam(code) => write!(f, "duplicate team: {}", code),
+            SeasonError::DuplicatePlayer(name) => write!(f, "duplicate player: {}", name),
+            SeasonError::TeamNotInDivision { division, team } => {
+                write!(f, "team {} is not in division {}", team, division)
+            }
+            SeasonError::NoDivision => write!(f, "season has no division"),
+            SeasonError::InvalidFormat(message) => write!(f, "invalid format: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SeasonError {}
+
+// シーズン定義ファイルの形式
+#[cfg(feature = "serialize")]
+#[derive(Deserialize)]
+struct SeasonDocument {
+    id: String,
+    name: String,
+    // 省略した場合は2024年のルール
+    #[serde(default)]
+    formats: Option<SeasonFormats>,
+    // 各ディビジョンからプレイオフに進むチーム数
+    #[serde(default = "default_playoff_teams")]
+    playoff_teams: usize,
+    divisions: Vec<DivisionDocument>,
+    // チームコードごとの所属プレイヤー
+    #[serde(default)]
+    rosters: HashMap<String, Vec<PlayerDocument>>,
+}
+
+#[cfg(feature = "serialize")]
+fn default_playoff_teams() -> usize {
+    3
+}
+
+#[cfg(feature = "serialize")]
+#[derive(Deserialize)]
+struct PlayerDocument {
+    name: String,
+    #[serde(default)]
+    characters: Vec<String>,
+}
+
+#[cfg(feature = "serialize")]
+#[derive(Deserialize)]
+struct DivisionDocument {
+    name: String,
+    teams: Vec<String>,
+    matches: Vec<MatchDocument>,
+}
+
+#[cfg(feature = "serialize")]
+#[derive(Deserialize)]
+struct MatchDocument {
+    date: String,
+    section: u32,
+    branch: u32,
+    away: String,
+    home: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct SeasonDivision {
+    pub name: String,
+    pub teams: Vec<SflTeam>,
+    pub matches: Vec<SflMatch>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SeasonPlayer {
+    pub name: String,
+    pub team: SflTeam,
+    // 使用キャラクター。先頭がメイン
+    pub characters: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Season {
+    pub id: String,
+    pub name: String,
+    // SflTeamの番号順のチームコード
+    pub teams: Vec<String>,
+    pub formats: SeasonFormats,
+    // 各ディビジョンからプレイオフに進むチーム数
+    // プレイオフの試合を回すのは2ディビジョンの上位3チームの形式だけ
+    pub playoff_team_count: usize,
+    pub divisions: Vec<SeasonDivision>,
+    // SflPlayerの番号順のプレイヤー
+    pub players: Vec<SeasonPlayer>,
+}
+
+impl Season {
+    // 同梱しているシーズンをidで取得する
+    #[cfg(feature = "serialize")]
+    pub fn bundled(id: &str) -> Option<Season> {
+        match id {
+            "jp2024" => Some(Season::jp2024()),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "serialize")]
+    pub fn from_json(json: &str) -> Result<Season, SeasonError> {
+        let document: SeasonDocument =
+            serde_json::from_str(json).map_err(|e| SeasonError::Parse(e.to_string()))?;
+        Season::from_document(document)
+    }
+
+    #[cfg(feature = "serialize")]
+    pub fn jp2024() -> Season {
+        Season::from_json(JP2024_SEASON_JSON).unwrap()
+    }
+
+    #[cfg(feature = "serialize")]
+    fn from_document(document: SeasonDocument) -> Result<Season, SeasonError> {
+        if document.divisions.is_empty() {
+            return Err(SeasonError::NoDivision);
+        }
+        if document.playoff_teams == 0 {
+            return Err(SeasonError::InvalidFormat(
+                "playoff_teams must be positive".to_string(),
+            ));
+        }
+        // ディビジョンに書かれた順にチームの番号を振る
+        let mut team_codes: Vec<String> = vec![];
+        for code in document.divisions.iter().flat_map(|d| d.teams.iter()) {
+            if team_codes.contains(code) {
+                return Err(SeasonError::DuplicateTeam(code.to_owned()));
+            }
+            team_codes.push(code.to_owned());
+        }
+        let find_team = |code: &String| {
+            team_codes
+                .iter()
+                .position(|team_code| team_code == code)
+                .map(SflTeam::new)
+        };
+        // チームの番号順にプレイヤーの番号を振る
+        let mut players: Vec<SeasonPlayer> = vec![];
+        for code in document.rosters.keys() {
+            if !team_codes.contains(code) {
+                return Err(SeasonError::UnknownTeam(code.to_owned()));
+            }
+        }
+        for (index, code) in team_codes.iter().enumerate() {
+            for player in document.rosters.get(code).into_iter().flatten() {
+                if players.iter().any(|p| p.name == player.name) {
+                    return Err(SeasonError::DuplicatePlayer(player.name.to_owned()));
+                }
+                players.push(SeasonPlayer {
+                    name: player.name.to_owned(),
+                    team: SflTeam::new(index),
+                    characters: player.characters.to_owned(),
+                });
+            }
+        }
+        let mut divisions = vec![];
+        for (division_index, division) in document.divisions.into_iter().enumerate() {
+            let teams: Vec<SflTeam> = division.teams.iter().filter_map(find_team).collect();
+            let division_team = |code: &String| {
+                let team = find_team(code).ok_or(SeasonError::UnknownTeam(code.to_owned()))?;
+                if teams.contains(&team) {
+                    Ok(team)
+                } else {
+                    Err(SeasonError::TeamNotInDivision {
+                        division: division.name.to_owned(),
+                        team: code.to_owned(),
+                    })
+                }
+            };
+            let mut matches = vec![];
+            for m in division.matches.iter() {
+                // 先に書かれたチームがAWAY
+                matches.push(SflMatch {
+                    section: m.section,
+                    branch: m.branch,
+                    date_expression: m.date.to_owned(),
+                    sfl_stage: SflStage::division(division_index),
+                    team: division_team(&m.away)?,
+                    opponent_team: division_team(&m.home)?,
+                    is_home: false,
+                });
+            }
+            divisions.push(SeasonDivision {
+                name: division.name,
+                teams,
+                matches,
+            });
+        }
+        let formats = match document.formats {
+            Some(formats) => {
+                formats.validate().map_err(SeasonError::InvalidFormat)?;
+                formats
+            }
+            None => Season::jp2024().formats,
+        };
+        Ok(Season {
+            id: document.id,
+            name: document.name,
+            teams: team_codes,
+            formats,
+            playoff_team_count: document.playoff_teams,
+            divisions,
+            players,
+        })
+    }
+
+    pub fn find_team(&self, code: &str) -> Option<SflTeam> {
+        self.teams
+            .iter()
+            .position(|team_code| team_code == code)
+            .map(SflTeam::new)
+    }
+
+    pub fn get_team_code(&self, team: &SflTeam) -> &str {
+        &self.teams[team.get_index()]
+    }
+
+    pub fn find_player(&self, name: &str) -> Option<SflPlayer> {
+        self.players
+            .iter()
+            .position(|player| player.name == name)
+            .map(SflPlayer::new)
+    }
+
+    pub fn get_player(&self, player: &SflPlayer) -> &SeasonPlayer {
+        &self.players[player.get_index()]
+    }
+
+    pub fn get_roster(&self, team: &SflTeam) -> Vec<SflPlayer> {
+        self.players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.team == *team)
+            .map(|(index, _)| SflPlayer::new(index))
+            .collect()
+    }
+
+    // 予想するレコードに出場プレイヤーを割り当てる
+    // 同じレグは同じプレイヤーが戦い、1試合の中ではなるべく別のプレイヤーを選ぶ
+    // すでにプレイヤーが決まっているレグ（オーダー発表済みなど）はそのまま
+    pub fn sample_lineup(&self, records: &mut [SflRecord], rng: &mut StdRng) {
+        let Some(first) = records.first() else {
+            return;
+        };
+        let sfl_match = first.sfl_match.to_owned();
+        let mut game_types = vec![];
+        for record in records.iter() {
+            if !game_types.contains(&record.game_type) {
+                game_types.push(record.game_type);
+            }
+        }
+        for is_team in [true, false] {
+            let team = if is_team {
+                sfl_match.team
+            } else {
+                sfl_match.opponent_team
+            };
+            let mut roster = self.get_roster(&team);
+            if roster.is_empty() {
+                continue;
+            }
+            roster.shuffle(rng);
+            for (n, game_type) in game_types.iter().enumerate() {
+                let leg: Vec<&mut SflRecord> = records
+                    .iter_mut()
+                    .filter(|record| record.game_type == *game_type)
+                    .collect();
+                let side = |record: &SflRecord| {
+                    if is_team {
+                        record.player
+                    } else {
+                        record.opponent_player
+                    }
+                };
+                let player = leg
+                    .iter()
+                    .find_map(|record| side(record))
+                    .unwrap_or(roster[n % roster.len()]);
+                for record in leg {
+                    if !record.is_prediction {
+                        continue;
+                    }
+                    if is_team {
+                        record.player.get_or_insert(player);
+                    } else {
+                        record.opponent_player.get_or_insert(player);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn get_format(&self, sfl_stage: &SflStage) -> &MatchFormat {
+        match sfl_stage.kind {
+            Division | AllDivision => &self.formats.division,
+            Playoff => &self.formats.playoff,
+            GrandFinal => &self.formats.grand_final,
+        }
+    }
+
+    pub fn get_division_stages(&self) -> Vec<SflStage> {
+        (0..self.divisions.len()).map(SflStage::division).collect()
+    }
+
+    fn get_division(&self, sfl_stage: &SflStage) -> Option<&SeasonDivision> {
+        match sfl_stage.kind {
+            Division => self.divisions.get(sfl_stage.division_index),
+            _ => None,
+        }
+    }
+
+    pub fn get_teams(&self, sfl_stage: &SflStage) -> Vec<SflTeam> {
+        match sfl_stage.kind {
+            AllDivision => self
+                .divisions
+                .iter()
+                .flat_map(|division| division.teams.to_owned())
+                .collect(),
+            _ => self
+                .get_division(sfl_stage)
+                .map(|division| division.teams.to_owned())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn get_matches(&self, sfl_stage: &SflStage) -> Vec<SflMatch> {
+        match sfl_stage.kind {
+            AllDivision => {
+                // 節ごとにディビジョン順で並べる
+                let mut matches: Vec<(usize, SflMatch)> = self
+                    .divisions
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(n, division)| {
+                        division.matches.iter().map(move |m| (n, m.to_owned()))
+                    })
+                    .collect();
+                matches.sort_by_key(|(n, m)| (m.section, *n));
+                matches
+                    .into_iter()
+                    .map(|(_, mut m)| {
+                        m.sfl_stage = SflStage::all_division();
+                        m
+                    })
+                    .collect()
+            }
+            _ => self
+                .get_division(sfl_stage)
+                .map(|division| division.matches.to_owned())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn get_max_team_index(&self, sfl_stage: &SflStage) -> usize {
+        self.get_teams(sfl_stage)
+            .iter()
+            .map(|team| team.get_index())
+            .max()
+            .unwrap()
+    }
+
+    pub fn get_initial_records(&self, sfl_stage: &SflStage) -> Vec<Vec<SflRecord>> {
+        let format = self.get_format(sfl_stage);
+        self.get_matches(sfl_stage)
+            .iter()
+            .map(|sfl_match| format.to_records(sfl_match))
+            .collect()
+    }
+
+    pub fn get_playoff_records(&self, team: &SflTeam, opponent_team: &SflTeam) -> Vec<SflRecord> {
+        let sfl_stage = SflStage::playoff();
+        let sfl_match = sfl_stage.get_playoff_match(team, opponent_team);
+        self.get_format(&sfl_stage).to_records(&sfl_match)
+    }
+
+    pub fn get_grand_final_records(
+        &self,
+        team: &SflTeam,
+        opponent_team: &SflTeam,
+    ) -> Vec<SflRecord> {
+        let sfl_stage = SflStage::grand_final();
+        let sfl_match = sfl_stage.get_grand_final_match(team, opponent_team);
+        self.get_format(&sfl_stage).to_records(&sfl_match)
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl Default for Season {
+    fn default() -> Season {
+        Season::jp2024()
+    }
+}
@@ -1,18 +1,16 @@
+use crate::season::Season;
 use crate::sfl::GameType::{PlayoffExtra, EXTRA, GENERAL, MID, VAN};
-use crate::sfl::SflStage::{
-    JP2024AllDivision, JP2024DivisionF, JP2024DivisionS, JP2024GrandFinal, JP2024Playoff,
-};
-use crate::sfl::SflTeam::*;
+use crate::sfl::SflStageKind::{AllDivision, Division, GrandFinal, Playoff};
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
 use std::collections::HashMap;
-use wasm_bindgen::prelude::wasm_bindgen;
 
 const K: f64 = 16_f64;
 
 #[derive(Clone, Debug)]
-#[wasm_bindgen]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct SflRecord {
-    #[wasm_bindgen(skip)]
     pub sfl_match: SflMatch,
     pub set_number: u32,
     pub win_flag: bool,
@@ -25,10 +23,30 @@ pub struct SflRecord {
     // 予想か実際かを区別する。予想ならtrue
     // これから予想する場合にもtrue。実績ならfalse
     pub is_prediction: bool,
+    // セットを戦ったプレイヤーとキャラクター。わからない場合はNone
+    pub player: Option<SflPlayer>,
+    pub opponent_player: Option<SflPlayer>,
+    pub character: Option<String>,
+    pub opponent_character: Option<String>,
 }
 
-#[derive(Copy, Clone, Debug)]
-#[wasm_bindgen]
+impl SflRecord {
+    pub fn player(&self) -> Option<usize> {
+        self.player.map(|player| player.get_index())
+    }
+    pub fn opponent_player(&self) -> Option<usize> {
+        self.opponent_player.map(|player| player.get_index())
+    }
+    pub fn character(&self) -> Option<String> {
+        self.character.to_owned()
+    }
+    pub fn opponent_character(&self) -> Option<String> {
+        self.opponent_character.to_owned()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum GameType {
     VAN,
     MID,
@@ -38,182 +56,65 @@ pub enum GameType {
 }
 
 impl GameType {
-    fn get_point(&self) -> u32 {
-        match self {
-            VAN => 10,
-            MID => 10,
-            GENERAL => 20,
-            EXTRA => 5,
-            PlayoffExtra => 10,
-        }
-    }
     pub fn is_leader(&self) -> bool {
         match self {
             VAN | MID => false,
             GENERAL | EXTRA | PlayoffExtra => true,
         }
     }
-    fn get_games_by_stage(sfl_stage: &SflStage) -> Vec<(u32, GameType)> {
-        match sfl_stage {
-            JP2024DivisionS | JP2024DivisionF | JP2024AllDivision => vec![
-                (1, VAN),
-                (2, VAN),
-                (3, VAN),
-                (1, MID),
-                (2, MID),
-                (3, MID),
-                (1, GENERAL),
-                (2, GENERAL),
-                (3, GENERAL),
-                (4, GENERAL),
-                (5, GENERAL),
-                (1, EXTRA),
-            ],
-            JP2024Playoff => vec![
-                (1, VAN),
-                (2, VAN),
-                (3, VAN),
-                (1, MID),
-                (2, MID),
-                (3, MID),
-                (1, GENERAL),
-                (2, GENERAL),
-                (3, GENERAL),
-                (4, GENERAL),
-                (5, GENERAL),
-                (1, VAN),
-                (2, VAN),
-                (3, VAN),
-                (1, MID),
-                (2, MID),
-                (3, MID),
-                (1, GENERAL),
-                (2, GENERAL),
-                (3, GENERAL),
-                (4, GENERAL),
-                (5, GENERAL),
-                (1, VAN),
-                (2, VAN),
-                (3, VAN),
-                (1, MID),
-                (2, MID),
-                (3, MID),
-                (1, GENERAL),
-                (2, GENERAL),
-                (3, GENERAL),
-                (4, GENERAL),
-                (5, GENERAL),
-                (1, PlayoffExtra),
-                (2, PlayoffExtra),
-                (3, PlayoffExtra),
-            ],
-            JP2024GrandFinal => vec![
-                (1, VAN),
-                (2, VAN),
-                (3, VAN),
-                (1, MID),
-                (2, MID),
-                (3, MID),
-                (1, GENERAL),
-                (2, GENERAL),
-                (3, GENERAL),
-                (4, GENERAL),
-                (5, GENERAL),
-                (1, VAN),
-                (2, VAN),
-                (3, VAN),
-                (1, MID),
-                (2, MID),
-                (3, MID),
-                (1, GENERAL),
-                (2, GENERAL),
-                (3, GENERAL),
-                (4, GENERAL),
-                (5, GENERAL),
-                (1, VAN),
-                (2, VAN),
-                (3, VAN),
-                (1, MID),
-                (2, MID),
-                (3, MID),
-                (1, GENERAL),
-                (2, GENERAL),
-                (3, GENERAL),
-                (4, GENERAL),
-                (5, GENERAL),
-                (1, VAN),
-                (2, VAN),
-                (3, VAN),
-                (1, MID),
-                (2, MID),
-                (3, MID),
-                (1, GENERAL),
-                (2, GENERAL),
-                (3, GENERAL),
-                (4, GENERAL),
-                (5, GENERAL),
-                (1, PlayoffExtra),
-                (2, PlayoffExtra),
-                (3, PlayoffExtra),
-            ],
-        }
-    }
 }
 
-#[derive(Clone, Copy, Debug)]
-#[wasm_bindgen]
-pub enum SflStage {
-    JP2024DivisionS,
-    JP2024DivisionF,
-    JP2024AllDivision,
-    JP2024Playoff,
-    JP2024GrandFinal,
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum SflStageKind {
+    Division,
+    AllDivision,
+    Playoff,
+    GrandFinal,
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct SflStage {
+    pub kind: SflStageKind,
+    // シーズン内のディビジョン番号（Division以外では0）
+    pub division_index: usize,
 }
 
 impl SflStage {
-    pub fn get_teams(&self) -> Vec<SflTeam> {
-        match self {
-            JP2024DivisionS => vec![G8S, DFM, SOL, IBS, OJA, SNB],
-            JP2024DivisionF => vec![CR, CAG, IXA, RC, VAR, FAV],
-            JP2024AllDivision => vec![G8S, DFM, SOL, IBS, OJA, SNB, CR, CAG, IXA, RC, VAR, FAV],
-            _ => vec![],
+    pub fn division(division_index: usize) -> SflStage {
+        SflStage {
+            kind: Division,
+            division_index,
         }
     }
-
-    pub fn get_max_team_index(&self) -> usize {
-        self.get_teams()
-            .iter()
-            .map(|team| team.get_index())
-            .max()
-            .unwrap()
+    pub fn all_division() -> SflStage {
+        SflStage {
+            kind: AllDivision,
+            division_index: 0,
+        }
     }
-
-    pub fn get_initial_records(&self) -> Vec<Vec<SflRecord>> {
-        self.get_matches()
-            .iter()
-            .map(|sfl_match| self.match_to_records(sfl_match))
-            .collect()
+    pub fn playoff() -> SflStage {
+        SflStage {
+            kind: Playoff,
+            division_index: 0,
+        }
     }
-
-    pub fn get_playoff_records(&self, team: &SflTeam, opponent_team: &SflTeam) -> Vec<SflRecord> {
-        let sfl_match = self.get_playoff_match(team, opponent_team);
-        self.match_to_records(&sfl_match)
+    pub fn grand_final() -> SflStage {
+        SflStage {
+            kind: GrandFinal,
+            division_index: 0,
+        }
     }
+}
 
-    pub fn get_grand_final_records(
-        &self,
-        team: &SflTeam,
-        opponent_team: &SflTeam,
-    ) -> Vec<SflRecord> {
-        let sfl_match = self.get_grand_final_match(team, opponent_team);
-        self.match_to_records(&sfl_match)
-    }
+impl SflStage {
     pub fn get_playoff_match(&self, team: &SflTeam, opponent_team: &SflTeam) -> SflMatch {
         SflMatch {
             section: 0,
             branch: 0,
             date_expression: "".to_string(),
-            sfl_stage: JP2024Playoff,
+            sfl_stage: SflStage::playoff(),
             team: team.to_owned(),
             opponent_team: opponent_team.to_owned(),
             is_home: false,
@@ -225,449 +126,21 @@ impl SflStage {
             section: 0,
             branch: 0,
             date_expression: "".to_string(),
-            sfl_stage: JP2024GrandFinal,
+            sfl_stage: SflStage::grand_final(),
             team: team.to_owned(),
             opponent_team: opponent_team.to_owned(),
             is_home: false,
         }
     }
-    pub fn get_matches(&self) -> Vec<SflMatch> {
-        match self {
-            JP2024DivisionS => vec![
-                ("08/16", 1, 1, DFM, OJA),
-                ("08/16", 1, 2, G8S, SNB),
-                ("08/16", 1, 3, SOL, IBS),
-                ("08/27", 2, 1, SNB, DFM),
-                ("08/27", 2, 2, IBS, OJA),
-                ("08/27", 2, 3, SOL, G8S),
-                ("09/03", 3, 1, OJA, SOL),
-                ("09/03", 3, 2, G8S, DFM),
-                ("09/03", 3, 3, SNB, IBS),
-                ("09/10", 4, 1, G8S, OJA),
-                ("09/10", 4, 2, SNB, SOL),
-                ("09/10", 4, 3, IBS, DFM),
-                ("09/20", 5, 1, IBS, G8S),
-                ("09/20", 5, 2, DFM, SOL),
-                ("09/20", 5, 3, OJA, SNB),
-                ("10/04", 6, 1, IBS, SOL),
-                ("10/04", 6, 2, SNB, G8S),
-                ("10/04", 6, 3, OJA, DFM),
-                ("10/22", 7, 1, G8S, SOL),
-                ("10/22", 7, 2, DFM, SNB),
-                ("10/22", 7, 3, OJA, IBS),
-                ("10/29", 8, 1, IBS, SNB),
-                ("10/29", 8, 2, SOL, OJA),
-                ("10/29", 8, 3, DFM, G8S),
-                ("11/05", 9, 1, OJA, G8S),
-                ("11/05", 9, 2, DFM, IBS),
-                ("11/05", 9, 3, SOL, SNB),
-                ("11/19", 10, 1, SNB, OJA),
-                ("11/19", 10, 2, SOL, DFM),
-                ("11/19", 10, 3, G8S, IBS),
-            ],
-
-            JP2024DivisionF => vec![
-                ("08/20", 1, 1, RC, IXA),
-                ("08/20", 1, 2, CAG, VAR),
-                ("08/20", 1, 3, CR, FAV),
-                ("08/30", 2, 1, VAR, RC),
-                ("08/30", 2, 2, FAV, IXA),
-                ("08/30", 2, 3, CR, CAG),
-                ("09/06", 3, 1, IXA, CR),
-                ("09/06", 3, 2, CAG, RC),
-                ("09/06", 3, 3, VAR, FAV),
-                ("09/18", 4, 1, CAG, IXA),
-                ("09/18", 4, 2, VAR, CR),
-                ("09/18", 4, 3, FAV, RC),
-                ("10/01", 5, 1, FAV, CAG),
-                ("10/01", 5, 2, RC, CR),
-                ("10/01", 5, 3, IXA, VAR),
-                ("10/08", 6, 1, FAV, CR),
-                ("10/08", 6, 2, VAR, CAG),
-                ("10/08", 6, 3, IXA, RC),
-                ("10/25", 7, 1, CAG, CR),
-                ("10/25", 7, 2, RC, VAR),
-                ("10/25", 7, 3, IXA, FAV),
-                ("11/01", 8, 1, FAV, VAR),
-                ("11/01", 8, 2, CR, IXA),
-                ("11/01", 8, 3, RC, CAG),
-                ("11/15", 9, 1, IXA, CAG),
-                ("11/15", 9, 2, RC, FAV),
-                ("11/15", 9, 3, CR, VAR),
-                ("11/22", 10, 1, VAR, IXA),
-                ("11/22", 10, 2, CR, RC),
-                ("11/22", 10, 3, CAG, FAV),
-            ],
-
-            JP2024AllDivision => vec![
-                ("08/16", 1, 1, DFM, OJA),
-                ("08/16", 1, 2, G8S, SNB),
-                ("08/16", 1, 3, SOL, IBS),
-                ("08/20", 1, 1, RC, IXA),
-                ("08/20", 1, 2, CAG, VAR),
-                ("08/20", 1, 3, CR, FAV),
-                ("08/27", 2, 1, SNB, DFM),
-                ("08/27", 2, 2, IBS, OJA),
-                ("08/27", 2, 3, SOL, G8S),
-                ("08/30", 2, 1, VAR, RC),
-                ("08/30", 2, 2, FAV, IXA),
-                ("08/30", 2, 3, CR, CAG),
-                ("09/03", 3, 1, OJA, SOL),
-                ("09/03", 3, 2, G8S, DFM),
-                ("09/03", 3, 3, SNB, IBS),
-                ("09/06", 3, 1, IXA, CR),
-                ("09/06", 3, 2, CAG, RC),
-                ("09/06", 3, 3, VAR, FAV),
-                ("09/10", 4, 1, G8S, OJA),
-                ("09/10", 4, 2, SNB, SOL),
-                ("09/10", 4, 3, IBS, DFM),
-                ("09/18", 4, 1, CAG, IXA),
-                ("09/18", 4, 2, VAR, CR),
-                ("09/18", 4, 3, FAV, RC),
-                ("09/20", 5, 1, IBS, G8S),
-                ("09/20", 5, 2, DFM, SOL),
-                ("09/20", 5, 3, OJA, SNB),
-                ("10/01", 5, 1, FAV, CAG),
-                ("10/01", 5, 2, RC, CR),
-                ("10/01", 5, 3, IXA, VAR),
-                ("10/04", 6, 1, IBS, SOL),
-                ("10/04", 6, 2, SNB, G8S),
-                ("10/04", 6, 3, OJA, DFM),
-                ("10/08", 6, 1, FAV, CR),
-                ("10/08", 6, 2, VAR, CAG),
-                ("10/08", 6, 3, IXA, RC),
-                ("10/22", 7, 1, G8S, SOL),
-                ("10/22", 7, 2, DFM, SNB),
-                ("10/22", 7, 3, OJA, IBS),
-                ("10/25", 7, 1, CAG, CR),
-                ("10/25", 7, 2, RC, VAR),
-                ("10/25", 7, 3, IXA, FAV),
-                ("10/29", 8, 1, IBS, SNB),
-                ("10/29", 8, 2, SOL, OJA),
-                ("10/29", 8, 3, DFM, G8S),
-                ("11/01", 8, 1, FAV, VAR),
-                ("11/01", 8, 2, CR, IXA),
-                ("11/01", 8, 3, RC, CAG),
-                ("11/05", 9, 1, OJA, G8S),
-                ("11/05", 9, 2, DFM, IBS),
-                ("11/05", 9, 3, SOL, SNB),
-                ("11/15", 9, 1, IXA, CAG),
-                ("11/15", 9, 2, RC, FAV),
-                ("11/15", 9, 3, CR, VAR),
-                ("11/19", 10, 1, SNB, OJA),
-                ("11/19", 10, 2, SOL, DFM),
-                ("11/19", 10, 3, G8S, IBS),
-                ("11/22", 10, 1, VAR, IXA),
-                ("11/22", 10, 2, CR, RC),
-                ("11/22", 10, 3, CAG, FAV),
-            ],
-            _ => {
-                vec![]
-            }
-        }
-        .iter()
-        .map(|tup| {
-            let (date_expression, section, branch, team, opponent_team) = tup.to_owned();
-            SflMatch {
-                section,
-                branch,
-                date_expression: date_expression.to_string(),
-                sfl_stage: self.to_owned(),
-                team,
-                opponent_team,
-                is_home: false,
-            }
-        })
-        .collect()
-    }
-    fn match_to_records(&self, sfl_match: &SflMatch) -> Vec<SflRecord> {
-        match sfl_match.sfl_stage {
-            JP2024DivisionS | JP2024DivisionF | JP2024AllDivision | JP2024Playoff
-            | JP2024GrandFinal => {
-                GameType::get_games_by_stage(&sfl_match.sfl_stage)
-                    .iter()
-                    .map(|(set_number, game_type)| {
-                        SflRecord {
-                            sfl_match: sfl_match.to_owned(),
-                            set_number: *set_number,
-                            win_flag: false,
-                            game_type: game_type.to_owned(),
-                            // pointはcorrect_recordでセットする
-                            point: 0,
-                            is_valid: false,
-                            is_prediction: true,
-                        }
-                    })
-                    .collect()
-            }
-        }
-    }
-    // パフォーマンスの問題もあるから前後の関連だけ見て修正する
-    // is_valid = true フラグが立っているレコードについて見直して一部 is_valid = false に変える
-    // ポイントを決着セットに書き加える
-    // 決着していない場合はもちろんポイントを書かない
-    // ランダム結果と実際結果が混じることがある
-    pub fn correct_records(&self, records: &mut Vec<SflRecord>) {
-        match self {
-            JP2024DivisionS | JP2024DivisionF | JP2024AllDivision => {
-                let van1 = records.get(0).unwrap().to_owned();
-                let van2 = records.get(1).unwrap().to_owned();
-                let van3 = records.get(2).unwrap().to_owned();
-                let mid1 = records.get(3).unwrap().to_owned();
-                let mid2 = records.get(4).unwrap().to_owned();
-                let mid3 = records.get(5).unwrap().to_owned();
-                let general1 = records.get(6).unwrap().to_owned();
-                let general2 = records.get(7).unwrap().to_owned();
-                let general3 = records.get(8).unwrap().to_owned();
-                let general4 = records.get(9).unwrap().to_owned();
-                let general5 = records.get(10).unwrap().to_owned();
-
-                let mut team_point: u32 = 0;
-                let mut opponent_team_point: u32 = 0;
-
-                // 先鋒戦のポイント決定
-                let van_point = VAN.get_point();
-                if van1.is_valid && van2.is_valid {
-                    if van1.win_flag == van2.win_flag {
-                        let mut_van2 = records.get_mut(1).unwrap();
-                        mut_van2.point = van_point;
-                        let mut_van3 = records.get_mut(2).unwrap();
-                        mut_van3.is_valid = false;
-                        // ポイントのリセットはここではしない
-                        // mut_van3.point = 0;
-                        if van1.win_flag {
-                            team_point += van_point;
-                        } else {
-                            opponent_team_point += van_point;
-                        }
-                    } else if van3.is_valid {
-                        let mut_van3 = records.get_mut(2).unwrap();
-                        mut_van3.is_valid = true;
-                        mut_van3.point = van_point;
-                        // ポイントのリセットはここではしない
-                        // let mut_van2 = records.get_mut(1).unwrap();
-                        // mut_van2.point = 0;
-                        if van3.win_flag {
-                            team_point += van_point;
-                        } else {
-                            opponent_team_point += van_point;
-                        }
-                    }
-                }
-
-                // 中堅戦のポイント決定
-                let mid_point = MID.get_point();
-                if mid1.is_valid && mid2.is_valid {
-                    if mid1.win_flag == mid2.win_flag {
-                        let mut_mid2 = records.get_mut(4).unwrap();
-                        mut_mid2.point = mid_point;
-                        let mut_mid3 = records.get_mut(5).unwrap();
-                        mut_mid3.is_valid = false;
-                        // ポイントのリセットはここではしない
-                        // mut_mid3.point = 0;
-                        if mid1.win_flag {
-                            team_point += mid_point;
-                        } else {
-                            opponent_team_point += mid_point;
-                        }
-                    } else if mid3.is_valid {
-                        let mut_mid3 = records.get_mut(5).unwrap();
-                        mut_mid3.is_valid = true;
-                        mut_mid3.point = mid_point;
-                        // ポイントのリセットはここではしない
-                        // let mut_mid2 = records.get_mut(4).unwrap();
-                        // mut_mid2.point = 0;
-                        if mid3.win_flag {
-                            team_point += mid_point;
-                        } else {
-                            opponent_team_point += mid_point;
-                        }
-                    }
-                }
-
-                // 大将戦
-                let general_point = GENERAL.get_point();
-                if general1.is_valid && general2.is_valid && general3.is_valid {
-                    if general1.win_flag == general2.win_flag
-                        && general1.win_flag == general3.win_flag
-                    {
-                        let mut_general3 = records.get_mut(8).unwrap();
-                        mut_general3.point = general_point;
-                        let mut_general4 = records.get_mut(9).unwrap();
-                        mut_general4.is_valid = false;
-                        // ポイントのリセットはここではしない
-                        // mut_general4.point = 0;
-                        let mut_general5 = records.get_mut(10).unwrap();
-                        mut_general5.is_valid = false;
-                        // ポイントのリセットはここではしない
-                        // mut_general5.point = 0;
-                        if general1.win_flag {
-                            team_point += general_point;
-                        } else {
-                            opponent_team_point += general_point;
-                        }
-                    } else if general4.is_valid {
-                        let decide_flag = (general4.win_flag == general1.win_flag
-                            && general4.win_flag == general2.win_flag
-                            && general4.win_flag != general3.win_flag)
-                            || (general4.win_flag == general1.win_flag
-                                && general4.win_flag != general2.win_flag
-                                && general4.win_flag == general3.win_flag)
-                            || (general4.win_flag != general1.win_flag
-                                && general4.win_flag == general2.win_flag
-                                && general4.win_flag == general3.win_flag);
-                        if decide_flag {
-                            // ポイントのリセットはここではしない
-                            let mut_general5 = records.get_mut(10).unwrap();
-                            mut_general5.is_valid = false;
-                            let mut_general4 = records.get_mut(9).unwrap();
-                            mut_general4.is_valid = true;
-                            mut_general4.point = general_point;
-                            // mut_general5.point = 0;
-                            if mut_general4.win_flag {
-                                team_point += general_point;
-                            } else {
-                                opponent_team_point += general_point;
-                            }
-                        } else {
-                            if general5.is_valid {
-                                let mut_general5 = records.get_mut(10).unwrap();
-                                mut_general5.is_valid = true;
-                                mut_general5.point = general_point;
-                                // ポイントのリセットはここではしない
-                                // let mut_general4 = records.get_mut(9).unwrap();
-                                // mut_general4.point = 0;
-                                if mut_general5.win_flag {
-                                    team_point += general_point;
-                                } else {
-                                    opponent_team_point += general_point;
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // 延長戦
-                let mut_extra1 = records.get_mut(11).unwrap();
-                mut_extra1.is_valid = if team_point == van_point + mid_point
-                    && opponent_team_point == general_point
-                {
-                    mut_extra1.point = EXTRA.get_point();
-                    true
-                } else {
-                    false
-                };
-            }
-            JP2024Playoff => {}
-            _ => {}
-        }
-    }
-    pub fn get_win_team(&self, records: &mut Vec<SflRecord>) -> (SflTeam, u32, u32) {
-        match self {
-            JP2024Playoff => {
-                let games = [
-                    (vec![0_usize, 1, 2], 1_u32),
-                    (vec![3, 4, 5], 1),
-                    (vec![6, 7, 8, 9, 10], 2),
-                    (vec![11, 12, 13], 1),
-                    (vec![14, 15, 16], 1),
-                    (vec![17, 18, 19, 20, 21], 2),
-                    (vec![22, 23, 24], 1),
-                    (vec![25, 26, 27], 1),
-                    (vec![28, 29, 30, 31, 32], 2),
-                    (vec![33, 34, 35], 1),
-                ];
-                let mut team_point = 0_u32;
-                let mut opponent_team_point = 0_u32;
-                for (game, p) in games.iter() {
-                    let won = game
-                        .iter()
-                        .filter(|index| records[**index].win_flag)
-                        .collect::<Vec<_>>()
-                        .len() as u32;
-                    if won > *p {
-                        team_point += *p * 10;
-                        if team_point >= 70 {
-                            return (
-                                records[0].sfl_match.team.to_owned(),
-                                team_point,
-                                opponent_team_point,
-                            );
-                        }
-                    } else {
-                        opponent_team_point += *p * 10;
-                        if opponent_team_point >= 70 {
-                            return (
-                                records[0].sfl_match.opponent_team.to_owned(),
-                                team_point,
-                                opponent_team_point,
-                            );
-                        }
-                    }
-                }
-                panic!()
-            }
-            JP2024GrandFinal => {
-                let games = [
-                    (vec![0_usize, 1, 2], 1_u32),
-                    (vec![3, 4, 5], 1),
-                    (vec![6, 7, 8, 9, 10], 2),
-                    (vec![11, 12, 13], 1),
-                    (vec![14, 15, 16], 1),
-                    (vec![17, 18, 19, 20, 21], 2),
-                    (vec![22, 23, 24], 1),
-                    (vec![25, 26, 27], 1),
-                    (vec![28, 29, 30, 31, 32], 2),
-                    (vec![33, 34, 35], 1),
-                    (vec![36, 37, 38], 1),
-                    (vec![39, 40, 41, 42, 43], 2),
-                    (vec![44, 45, 46], 1),
-                ];
-                let mut team_point = 0_u32;
-                let mut opponent_team_point = 0_u32;
-                for (game, p) in games.iter() {
-                    let won = game
-                        .iter()
-                        .filter(|index| records[**index].win_flag)
-                        .collect::<Vec<_>>()
-                        .len() as u32;
-                    if won > *p {
-                        team_point += *p * 10;
-                        if team_point >= 90 {
-                            return (
-                                records[0].sfl_match.team.to_owned(),
-                                team_point,
-                                opponent_team_point,
-                            );
-                        }
-                    } else {
-                        opponent_team_point += *p * 10;
-                        if opponent_team_point >= 90 {
-                            return (
-                                records[0].sfl_match.opponent_team.to_owned(),
-                                team_point,
-                                opponent_team_point,
-                            );
-                        }
-                    }
-                }
-                panic!()
-            }
-            _ => {
-                panic!()
-            }
-        }
-    }
 }
 
 #[derive(Clone, Debug)]
-#[wasm_bindgen]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct SflMatch {
     // 節
     pub section: u32,
     // 節内の順序
     pub branch: u32,
-    #[wasm_bindgen(skip)]
     pub date_expression: String,
     pub sfl_stage: SflStage,
     pub team: SflTeam,
@@ -675,88 +148,84 @@ pub struct SflMatch {
     pub(crate) is_home: bool,
 }
 
-#[wasm_bindgen]
 impl SflMatch {
-    #[wasm_bindgen(getter)]
     pub fn date_expression(&self) -> String {
         self.date_expression.to_string()
     }
-    pub fn to_records(&self) -> Vec<SflRecord> {
-        match self.sfl_stage {
-            JP2024DivisionS | JP2024DivisionF | JP2024AllDivision | JP2024Playoff
-            | JP2024GrandFinal => {
-                GameType::get_games_by_stage(&self.sfl_stage)
-                    .iter()
-                    .map(|(set_number, game_type)| {
-                        SflRecord {
-                            sfl_match: self.to_owned(),
-                            set_number: *set_number,
-                            win_flag: false,
-                            game_type: game_type.to_owned(),
-                            // pointはcorrect_recordでセットする
-                            point: 0,
-                            is_valid: false,
-                            is_prediction: true,
-                        }
-                    })
-                    .collect()
-            }
-        }
+    // チームはシーズン内の番号で返す
+    pub fn team(&self) -> usize {
+        self.team.get_index()
+    }
+    pub fn opponent_team(&self) -> usize {
+        self.opponent_team.get_index()
     }
 }
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
-#[wasm_bindgen]
-pub enum SflTeam {
-    G8S,
-    DFM,
-    SOL,
-    IBS,
-    OJA,
-    SNB,
-    CR,
-    CAG,
-    IXA,
-    RC,
-    VAR,
-    FAV,
-}
+
+// チームはシーズン内の番号で識別する
+// コードや名前はSeasonが持つ
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct SflTeam(usize);
 
 impl SflTeam {
+    pub fn new(index: usize) -> SflTeam {
+        SflTeam(index)
+    }
     pub fn get_index(&self) -> usize {
-        match self {
-            G8S => 0,
-            DFM => 1,
-            SOL => 2,
-            IBS => 3,
-            OJA => 4,
-            SNB => 5,
-            CR => 6,
-            CAG => 7,
-            IXA => 8,
-            RC => 9,
-            VAR => 10,
-            FAV => 11,
-        }
+        self.0
+    }
+}
+
+// プレイヤーもシーズン内の番号で識別する
+// 名前や所属チームはSeasonが持つ
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct SflPlayer(usize);
+
+impl SflPlayer {
+    pub fn new(index: usize) -> SflPlayer {
+        SflPlayer(index)
+    }
+    pub fn get_index(&self) -> usize {
+        self.0
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum SflRatingSetting {
     TeamOnly,
     HomeAway,
     GameType,
     HomeAwayGameType,
+    // セットを戦ったプレイヤー単位
+    Player,
+}
+
+pub type RatingKey = (SflTeam, u8);
+pub type RatingKeyFunction = fn(&SflRecord) -> (RatingKey, RatingKey);
+// レーティングの計算方法
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum SflRatingModel {
+    // K固定のイロレーティング
+    Elo,
+    // RDとボラティリティも持つ。試合数が少ないうちは勝率が50%に寄る
+    Glicko2,
 }
 
+// レーティングを引けないレコード（プレイヤー不明など）はNone
+pub type RatingIndexFunction = fn(&SflRecord) -> Option<(usize, usize)>;
+pub type PlaceSimCount = HashMap<SflTeam, (Vec<u32>, (u32, u32, i32, i32))>;
+
+// チーム単位の設定のみ。プレイヤー単位ではキーをチームで作れないので None
 pub fn create_key_function_and_init_rating_map(
     setting: SflRatingSetting,
     teams: Vec<SflTeam>,
-) -> (
-    fn(&SflRecord) -> ((SflTeam, u8), (SflTeam, u8)),
-    HashMap<(SflTeam, u8), f64>,
-) {
+) -> Option<(RatingKeyFunction, HashMap<RatingKey, f64>)> {
     let default_rating = 1500_f64;
     let mut rating_map: HashMap<(SflTeam, u8), f64> = HashMap::new();
-    match setting {
+    let key_function: RatingKeyFunction = match setting {
         SflRatingSetting::TeamOnly => {
             for team in teams.iter() {
                 rating_map.insert((team.to_owned(), 000_u8), default_rating);
@@ -767,7 +236,7 @@ pub fn create_key_function_and_init_rating_map(
                     (record.sfl_match.opponent_team.to_owned(), 000_u8),
                 )
             }
-            (team_only_function, rating_map)
+            team_only_function
         }
         SflRatingSetting::HomeAway => {
             for team in teams.iter() {
@@ -787,7 +256,7 @@ pub fn create_key_function_and_init_rating_map(
                     )
                 }
             }
-            (home_away_function, rating_map)
+            home_away_function
         }
         SflRatingSetting::GameType => {
             for team in teams.iter() {
@@ -807,7 +276,7 @@ pub fn create_key_function_and_init_rating_map(
                     )
                 }
             }
-            (game_type_function, rating_map)
+            game_type_function
         }
         SflRatingSetting::HomeAwayGameType => {
             for team in teams.iter() {
@@ -842,57 +311,107 @@ pub fn create_key_function_and_init_rating_map(
                     }
                 }
             }
-            (home_away_game_type_function, rating_map)
+            home_away_game_type_function
         }
-    }
+        SflRatingSetting::Player => return None,
+    };
+    Some((key_function, rating_map))
+}
+
+// チームごとに4つ分の枠を取り、HOMEなら+1、大将戦以降なら+2 の位置を使う
+// 区別しない設定では使わない枠は1500のまま残る
+// プレイヤー単位の設定ではチームの枠はないので None
+pub fn get_rating_index(
+    setting: SflRatingSetting,
+    team_index: usize,
+    is_home: bool,
+    is_leader: bool,
+) -> Option<usize> {
+    let (use_home, use_leader) = match setting {
+        SflRatingSetting::TeamOnly => (false, false),
+        SflRatingSetting::HomeAway => (true, false),
+        SflRatingSetting::GameType => (false, true),
+        SflRatingSetting::HomeAwayGameType => (true, true),
+        SflRatingSetting::Player => return None,
+    };
+    Some(
+        team_index * 4
+            + if use_home && is_home { 1 } else { 0 }
+            + if use_leader && is_leader { 2 } else { 0 },
+    )
+}
+
+fn get_team_rating_indexes(
+    setting: SflRatingSetting,
+    record: &SflRecord,
+) -> Option<(usize, usize)> {
+    let sfl_match = &record.sfl_match;
+    let is_leader = record.game_type.is_leader();
+    Some((
+        get_rating_index(
+            setting,
+            sfl_match.team.get_index(),
+            sfl_match.is_home,
+            is_leader,
+        )?,
+        get_rating_index(
+            setting,
+            sfl_match.opponent_team.get_index(),
+            !sfl_match.is_home,
+            is_leader,
+        )?,
+    ))
 }
 
 pub fn create_key_function_and_init_ratings(
     setting: SflRatingSetting,
-    teams: Vec<SflTeam>,
-) -> (fn(&SflRecord) -> (usize, usize), Vec<f64>) {
-    let max_team_index = teams.iter().map(|team| team.get_index()).max().unwrap();
+    season: &Season,
+    sfl_stage: &SflStage,
+) -> (RatingIndexFunction, Vec<f64>) {
+    let max_team_index = season.get_max_team_index(sfl_stage);
     let ratings: Vec<f64> = vec![1500_f64; (max_team_index + 1) * 4];
     match setting {
         SflRatingSetting::TeamOnly => {
-            todo!()
+            fn team_only_function(record: &SflRecord) -> Option<(usize, usize)> {
+                get_team_rating_indexes(SflRatingSetting::TeamOnly, record)
+            }
+            (team_only_function, ratings)
         }
         SflRatingSetting::HomeAway => {
-            todo!()
+            fn home_away_function(record: &SflRecord) -> Option<(usize, usize)> {
+                get_team_rating_indexes(SflRatingSetting::HomeAway, record)
+            }
+            (home_away_function, ratings)
         }
         SflRatingSetting::GameType => {
-            todo!()
+            fn game_type_function(record: &SflRecord) -> Option<(usize, usize)> {
+                get_team_rating_indexes(SflRatingSetting::GameType, record)
+            }
+            (game_type_function, ratings)
         }
         SflRatingSetting::HomeAwayGameType => {
-            fn home_away_game_type_function(record: &SflRecord) -> (usize, usize) {
-                let team_index = record.sfl_match.team.get_index() * 4;
-                let opponent_team_index = record.sfl_match.opponent_team.get_index() * 4;
-                let mut team_mod_index = 0_usize;
-                let mut opponent_team_mod_index = 0_usize;
-                if record.sfl_match.is_home {
-                    team_mod_index += 1;
-                } else {
-                    opponent_team_mod_index += 1;
-                }
-                if record.game_type.is_leader() {
-                    team_mod_index += 2;
-                    opponent_team_mod_index += 2;
-                }
-                (
-                    team_index + team_mod_index,
-                    opponent_team_index + opponent_team_mod_index,
-                )
+            fn home_away_game_type_function(record: &SflRecord) -> Option<(usize, usize)> {
+                get_team_rating_indexes(SflRatingSetting::HomeAwayGameType, record)
             }
             (home_away_game_type_function, ratings)
         }
+        SflRatingSetting::Player => {
+            fn player_function(record: &SflRecord) -> Option<(usize, usize)> {
+                match (record.player, record.opponent_player) {
+                    (Some(player), Some(opponent_player)) => {
+                        Some((player.get_index(), opponent_player.get_index()))
+                    }
+                    _ => None,
+                }
+            }
+            (player_function, vec![1500_f64; season.players.len()])
+        }
     }
 }
 
-pub fn get_place_sim_count(
-    sfl_stage: SflStage,
-) -> HashMap<SflTeam, (Vec<u32>, (u32, u32, i32, i32))> {
-    let mut count: HashMap<SflTeam, (Vec<u32>, (u32, u32, i32, i32))> = HashMap::new();
-    for team in sfl_stage.get_teams().into_iter() {
+pub fn get_place_sim_count(season: &Season, sfl_stage: SflStage) -> PlaceSimCount {
+    let mut count: PlaceSimCount = HashMap::new();
+    for team in season.get_teams(&sfl_stage).into_iter() {
         count.insert(team, (vec![0; 6], (0, 0, 0, 0)));
     }
     count
@@ -0,0 +1,367 @@
+// シミュレーション結果を保存したもの
+// ソースに結果を貼り付ける代わりに、JSONで保存して読み込む
+use crate::format::MatchFormat;
+use crate::prior::RatingPrior;
+use crate::season::Season;
+use crate::sfl::SflRecord;
+use crate::{SflRating, SflSimulationResult};
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+#[cfg(feature = "serialize")]
+use std::path::Path;
+
+// 形式を変えたら上げる。違うバージョンは読み込まない
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+// 同梱しているシミュレーション結果
+#[cfg(feature = "serialize")]
+const JP2024_SNAPSHOT_JSON: &str = include_str!("../seasons/jp2024.snapshot.json");
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Parse(String),
+    Io(String),
+    Version { expected: u32, found: u32 },
+    Season { expected: String, found: String },
+    Shape(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Parse(message) => write!(f, "failed to parse snapshot: {}", message),
+            SnapshotError::Io(message) => write!(f, "failed to read snapshot: {}", message),
+            SnapshotError::Version { expected, found } => write!(
+                f,
+                "unsupported snapshot version: {} (expected {})",
+                found, expected
+            ),
+            SnapshotError::Season { expected, found } => write!(
+                f,
+                "snapshot is for season {} (expected {})",
+                found, expected
+            ),
+            SnapshotError::Shape(field) => {
+                write!(f, "snapshot does not match the season: {}", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+// SflSimulationResult を serde の形のまま包み、どのシーズンのどの入力から出した結果かを付ける
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct SimulationSnapshot {
+    pub version: u32,
+    pub season_id: String,
+    // get_input_hash の値。JSでも扱えるよう16進の文字列で持つ
+    pub input_hash: String,
+    // result の回数とシード。中身を読まずに確かめられるよう外にも持つ
+    pub count: usize,
+    pub seed: u64,
+    pub result: SflSimulationResult,
+}
+
+impl SimulationSnapshot {
+    pub fn new(
+        season: &Season,
+        input_hash: &str,
+        result: &SflSimulationResult,
+    ) -> SimulationSnapshot {
+        SimulationSnapshot {
+            version: SNAPSHOT_VERSION,
+            season_id: season.id.to_owned(),
+            input_hash: input_hash.to_string(),
+            count: result.count,
+            seed: result.seed,
+            result: result.to_owned(),
+        }
+    }
+
+    #[cfg(feature = "serialize")]
+    pub fn jp2024() -> SimulationSnapshot {
+        SimulationSnapshot::from_json(JP2024_SNAPSHOT_JSON).unwrap()
+    }
+
+    // バージョンが違うものはここでエラーにする
+    #[cfg(feature = "serialize")]
+    pub fn from_json(json: &str) -> Result<SimulationSnapshot, SnapshotError> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| SnapshotError::Parse(e.to_string()))?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .ok_or_else(|| SnapshotError::Parse("missing version".to_string()))?;
+        if version != SNAPSHOT_VERSION as u64 {
+            return Err(SnapshotError::Version {
+                expected: SNAPSHOT_VERSION,
+                found: version as u32,
+            });
+        }
+        serde_json::from_value(value).map_err(|e| SnapshotError::Parse(e.to_string()))
+    }
+
+    // JSの ArrayBuffer などで受け取ったUTF-8のJSON
+    #[cfg(feature = "serialize")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<SimulationSnapshot, SnapshotError> {
+        let json = std::str::from_utf8(bytes).map_err(|e| SnapshotError::Parse(e.to_string()))?;
+        SimulationSnapshot::from_json(json)
+    }
+
+    #[cfg(feature = "serialize")]
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<SimulationSnapshot, SnapshotError> {
+        let bytes = std::fs::read(path).map_err(|e| SnapshotError::Io(e.to_string()))?;
+        SimulationSnapshot::from_bytes(&bytes)
+    }
+
+    #[cfg(feature = "serialize")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    // シーズンと形が合わない場合はエラー。並べ替えた順位は summarize で作り直す
+    pub fn to_result(&self, season: &Season) -> Result<SflSimulationResult, SnapshotError> {
+        if self.season_id != season.id {
+            return Err(SnapshotError::Season {
+                expected: season.id.to_owned(),
+                found: self.season_id.to_owned(),
+            });
+        }
+        let zero = SflSimulationResult::zero(season);
+        let mut result = self.result.to_owned();
+        // 古いスナップショットにないフィールドは回数0とする
+        fill_missing(&mut result.division_place_count, &zero.division_place_count);
+        fill_missing(
+            &mut result.division_points_battles,
+            &zero.division_points_battles,
+        );
+        fill_missing(&mut result.playoff_place_count, &zero.playoff_place_count);
+        fill_missing(&mut result.match_points, &zero.match_points);
+        fill_missing(
+            &mut result.knockout_match_points,
+            &zero.knockout_match_points,
+        );
+        fill_missing(&mut result.place_to_points, &zero.place_to_points);
+        fill_missing(
+            &mut result.division_place_detail,
+            &zero.division_place_detail,
+        );
+        let team_count = season.teams.len();
+        let place_count = zero.division_place_count[0].len();
+        if result.count != self.count {
+            return Err(SnapshotError::Shape("count".to_string()));
+        }
+        if result.seed != self.seed {
+            return Err(SnapshotError::Shape("seed".to_string()));
+        }
+        check_shape(
+            "division_place_count",
+            &result.division_place_count,
+            team_count,
+            place_count,
+        )?;
+        check_shape(
+            "division_points_battles",
+            &result.division_points_battles,
+            team_count,
+            2,
+        )?;
+        check_shape(
+            "playoff_place_count",
+            &result.playoff_place_count,
+            team_count,
+            4,
+        )?;
+        check_shape(
+            "match_points",
+            &result.match_points,
+            zero.match_points.len(),
+            4,
+        )?;
+        check_shape(
+            "knockout_match_points",
+            &result.knockout_match_points,
+            zero.knockout_match_points.len(),
+            4,
+        )?;
+        check_shape(
+            "place_to_points",
+            &result.place_to_points,
+            team_count,
+            place_count,
+        )?;
+        check_shape(
+            "division_place_detail",
+            &result.division_place_detail,
+            team_count,
+            team_count,
+        )?;
+        for detail in result.division_place_detail.iter() {
+            check_shape("division_place_detail", detail, team_count, team_count)?;
+        }
+        result.summarize(season);
+        Ok(result)
+    }
+}
+
+fn fill_missing<T: Clone>(values: &mut Vec<T>, zero: &[T]) {
+    if values.is_empty() {
+        *values = zero.to_vec();
+    }
+}
+
+fn check_shape<T>(
+    field: &str,
+    values: &[Vec<T>],
+    len: usize,
+    inner_len: usize,
+) -> Result<(), SnapshotError> {
+    if values.len() == len && values.iter().all(|value| value.len() == inner_len) {
+        Ok(())
+    } else {
+        Err(SnapshotError::Shape(field.to_string()))
+    }
+}
+
+// 結果に効く入力のハッシュ
+// シーズンのチーム、日程、試合形式、ロースターと、入力した結果、プレイヤー、
+// レーティングの設定と事前のレーティング、差し替えた勝率のモデルの鍵（WinProbabilityModel::get_model_key）を含める
+// Rustのバージョンで変わらないよう FNV-1a で計算する
+pub fn get_input_hash(
+    season: &Season,
+    sfl_records: &[Vec<SflRecord>],
+    enable_rate: bool,
+    sfl_rating: &SflRating,
+    model_key: Option<&str>,
+) -> String {
+    let mut hasher = InputHasher::new();
+    hasher.write_season(season);
+    hasher.write(&[enable_rate as u8]);
+    hasher.write_str(&format!(
+        "{:?}/{:?}",
+        sfl_rating.get_setting(),
+        sfl_rating.get_model()
+    ));
+    hasher.write_prior(sfl_rating.get_prior());
+    hasher.write(&[model_key.is_some() as u8]);
+    hasher.write_str(model_key.unwrap_or_default());
+    hasher.write_len(sfl_records.len());
+    for records in sfl_records {
+        hasher.write_len(records.len());
+        for record in records {
+            hasher.write(&[
+                record.is_prediction as u8,
+                record.is_valid as u8,
+                record.win_flag as u8,
+            ]);
+            for player in [&record.player, &record.opponent_player] {
+                let index = player
+                    .as_ref()
+                    .map_or(u32::MAX, |player| player.get_index() as u32);
+                hasher.write(&index.to_le_bytes());
+            }
+        }
+    }
+    format!("{:016x}", hasher.0)
+}
+
+struct InputHasher(u64);
+
+impl InputHasher {
+    fn new() -> InputHasher {
+        InputHasher(0xcbf2_9ce4_8422_2325)
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    // 長さを先に書いて、続く値との区切りをはっきりさせる
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u32).to_le_bytes());
+    }
+    fn write_str(&mut self, value: &str) {
+        self.write_len(value.len());
+        self.write(value.as_bytes());
+    }
+    fn write_format(&mut self, format: &MatchFormat) {
+        self.write(
+            &format
+                .win_threshold
+                .map_or(u32::MAX, |threshold| threshold)
+                .to_le_bytes(),
+        );
+        self.write_len(format.legs.len());
+        for leg in format.legs.iter() {
+            self.write_str(&format!("{:?}/{:?}", leg.game_type, leg.condition));
+            self.write(&leg.sets.to_le_bytes());
+            self.write(&leg.point.to_le_bytes());
+        }
+    }
+    fn write_f64(&mut self, value: f64) {
+        self.write(&value.to_bits().to_le_bytes());
+    }
+    fn write_prior(&mut self, prior: Option<&RatingPrior>) {
+        let Some(prior) = prior else {
+            self.write(&[0]);
+            return;
+        };
+        self.write(&[1]);
+        self.write_f64(prior.regression);
+        let snapshot = &prior.snapshot;
+        self.write_str(&snapshot.season_id);
+        self.write_str(&format!("{:?}/{:?}", snapshot.setting, snapshot.model));
+        self.write_len(snapshot.teams.len());
+        for team in snapshot.teams.iter() {
+            self.write_str(&team.code);
+            for values in [&team.ratings, &team.deviations] {
+                self.write_len(values.len());
+                values.iter().for_each(|value| self.write_f64(*value));
+            }
+        }
+        self.write_len(snapshot.players.len());
+        for player in snapshot.players.iter() {
+            self.write_str(&player.name);
+            self.write_f64(player.rating);
+            self.write_f64(player.deviation.unwrap_or(f64::NAN));
+        }
+    }
+    fn write_season(&mut self, season: &Season) {
+        self.write_str(&season.id);
+        self.write_len(season.teams.len());
+        for team in season.teams.iter() {
+            self.write_str(team);
+        }
+        self.write_len(season.playoff_team_count);
+        for format in [
+            &season.formats.division,
+            &season.formats.playoff,
+            &season.formats.grand_final,
+        ] {
+            self.write_format(format);
+        }
+        self.write_len(season.divisions.len());
+        for division in season.divisions.iter() {
+            self.write_len(division.teams.len());
+            for team in division.teams.iter() {
+                self.write_len(team.get_index());
+            }
+            self.write_len(division.matches.len());
+            for sfl_match in division.matches.iter() {
+                self.write(&sfl_match.section.to_le_bytes());
+                self.write(&sfl_match.branch.to_le_bytes());
+                self.write_len(sfl_match.team.get_index());
+                self.write_len(sfl_match.opponent_team.get_index());
+            }
+        }
+        self.write_len(season.players.len());
+        for player in season.players.iter() {
+            self.write_str(&player.name);
+            self.write_len(player.team.get_index());
+        }
+    }
+}
@@ -0,0 +1,892 @@
+// JSから使うためのアダプター。wasm_bindgen の型と JsValue、console への出力はここだけで扱う
+// 中の型をそのまま包み、JS側の名前はRustの型名に合わせる
+use crate::bradley_terry::BradleyTerry;
+use crate::clinch::ClinchStatus;
+use crate::leverage::{MatchLeverage, TeamLeverage};
+#[cfg(not(feature = "parallel"))]
+use crate::model::WinProbabilityModel;
+use crate::results::ResultsError;
+use crate::scenario::{SflScenario, SflScenarioResult};
+#[cfg(not(feature = "parallel"))]
+use crate::season::Season;
+use crate::sfl::{
+    GameType, SflMatch, SflRatingModel, SflRatingSetting, SflRecord, SflStage, SflStageKind,
+};
+use crate::{
+    DivisionPlaceDetail, MatchScoreProbability, PlaceToPointDetail, SflSimulation, SflStats,
+    SimulationOption,
+};
+#[cfg(not(feature = "parallel"))]
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::console_log;
+
+#[wasm_bindgen(js_name = SflSimulation)]
+pub struct JsSflSimulation {
+    simulation: SflSimulation,
+}
+
+#[wasm_bindgen(js_class = SflSimulation)]
+impl JsSflSimulation {
+    // simulated なら同梱しているシミュレーション結果を持たせる
+    #[wasm_bindgen(constructor)]
+    pub fn new(simulated: bool) -> JsSflSimulation {
+        JsSflSimulation {
+            simulation: SflSimulation::new(simulated),
+        }
+    }
+    pub fn from_season_json(json: &str) -> Result<JsSflSimulation, String> {
+        let simulation = SflSimulation::from_season_json(json).map_err(|e| e.to_string())?;
+        Ok(JsSflSimulation { simulation })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn count(&self) -> usize {
+        self.simulation.count
+    }
+    #[wasm_bindgen(setter)]
+    pub fn set_count(&mut self, count: usize) {
+        self.simulation.count = count;
+    }
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> u64 {
+        self.simulation.seed
+    }
+    #[wasm_bindgen(setter)]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.simulation.seed = seed;
+    }
+    #[wasm_bindgen(getter)]
+    pub fn option(&self) -> JsSimulationOption {
+        self.simulation.option.into()
+    }
+    #[wasm_bindgen(setter)]
+    pub fn set_option(&mut self, option: JsSimulationOption) {
+        self.simulation.option = option.into();
+    }
+    #[wasm_bindgen(getter)]
+    pub fn sfl_stage(&self) -> JsSflStage {
+        JsSflStage(self.simulation.sfl_stage)
+    }
+    #[wasm_bindgen(getter)]
+    pub fn max_team_index(&self) -> usize {
+        self.simulation.max_team_index
+    }
+    #[wasm_bindgen(getter)]
+    pub fn sfl_stats(&self) -> JsSflStats {
+        JsSflStats(self.simulation.sfl_stats())
+    }
+
+    pub fn get_team_names(&self, stage: &JsSflStage) -> Vec<String> {
+        self.simulation.get_team_names(&stage.0)
+    }
+    pub fn enable_rate(&mut self, flag: bool) {
+        self.simulation.enable_rate(flag);
+    }
+    pub fn use_random_seed(&mut self, flag: bool) {
+        self.simulation.use_random_seed(flag);
+    }
+    pub fn get_used_seed(&self) -> u64 {
+        self.simulation.get_used_seed()
+    }
+    pub fn get_used_count(&self) -> usize {
+        self.simulation.get_used_count()
+    }
+    pub fn get_matches(&self) -> Vec<JsSflMatch> {
+        self.simulation
+            .get_matches()
+            .into_iter()
+            .map(JsSflMatch)
+            .collect()
+    }
+    pub fn get_leg_wins_needed(&self) -> Vec<u32> {
+        self.simulation.get_leg_wins_needed()
+    }
+    pub fn get_match_records(&self, match_index: usize) -> Vec<JsSflRecord> {
+        self.simulation
+            .get_match_records(match_index)
+            .into_iter()
+            .map(JsSflRecord)
+            .collect()
+    }
+    pub fn get_knockout_match_points(&self, knockout_index: usize) -> Option<Vec<u32>> {
+        self.simulation.get_knockout_match_points(knockout_index)
+    }
+    pub fn get_match_points(&self, match_index: usize) -> Vec<u32> {
+        self.simulation.get_match_points(match_index)
+    }
+
+    pub fn fit_bradley_terry(
+        &self,
+        use_home: bool,
+        use_leader: bool,
+        prior_variance: Option<f64>,
+    ) -> JsBradleyTerry {
+        JsBradleyTerry(
+            self.simulation
+                .fit_bradley_terry(use_home, use_leader, prior_variance),
+        )
+    }
+    pub fn set_bradley_terry_model(&mut self, model: JsBradleyTerry) {
+        self.simulation.set_bradley_terry_model(model.0);
+    }
+    // JS側の関数で勝率を決める
+    #[cfg(not(feature = "parallel"))]
+    pub fn set_js_win_probability_model(&mut self, model: JsWinProbabilityModel) {
+        self.simulation.set_win_probability_model(Box::new(model));
+    }
+    pub fn clear_win_probability_model(&mut self) {
+        self.simulation.clear_win_probability_model();
+    }
+
+    pub fn get_rating(&self, team_index: usize, is_home: bool, is_reader: bool) -> f64 {
+        self.simulation.get_rating(team_index, is_home, is_reader)
+    }
+    pub fn get_rating_setting(&self) -> JsSflRatingSetting {
+        self.simulation.get_rating_setting().into()
+    }
+    pub fn set_rating_setting(&mut self, setting: JsSflRatingSetting) {
+        self.simulation.set_rating_setting(setting.into());
+    }
+    pub fn get_rating_model(&self) -> JsSflRatingModel {
+        self.simulation.get_rating_model().into()
+    }
+    pub fn set_rating_model(&mut self, model: JsSflRatingModel) {
+        self.simulation.set_rating_model(model.into());
+    }
+    pub fn get_rating_snapshot(&self) -> String {
+        self.simulation.get_rating_snapshot()
+    }
+    pub fn set_rating_prior(&mut self, snapshot_json: &str, regression: f64) -> Result<(), String> {
+        self.simulation
+            .set_rating_prior(snapshot_json, regression)
+            .map_err(|e| e.to_string())
+    }
+    pub fn clear_rating_prior(&mut self) {
+        self.simulation.clear_rating_prior();
+    }
+    pub fn get_rating_deviation(
+        &self,
+        team_index: usize,
+        is_home: bool,
+        is_reader: bool,
+    ) -> Option<f64> {
+        self.simulation
+            .get_rating_deviation(team_index, is_home, is_reader)
+    }
+    pub fn get_player_names(&self, team_index: usize) -> Vec<String> {
+        self.simulation.get_player_names(team_index)
+    }
+    pub fn get_roster(&self, team_index: usize) -> Vec<usize> {
+        self.simulation.get_roster(team_index)
+    }
+    pub fn get_player_rating(&self, player_index: usize) -> Option<f64> {
+        self.simulation.get_player_rating(player_index)
+    }
+    pub fn set_record_player(
+        &mut self,
+        match_index: usize,
+        set_index: usize,
+        player: Option<usize>,
+        opponent_player: Option<usize>,
+        character: Option<String>,
+        opponent_character: Option<String>,
+    ) {
+        self.simulation.set_record_player(
+            match_index,
+            set_index,
+            player,
+            opponent_player,
+            character,
+            opponent_character,
+        );
+    }
+
+    pub fn get_input_hash(&self) -> String {
+        self.simulation.get_input_hash()
+    }
+    pub fn is_result_stale(&self) -> bool {
+        self.simulation.is_result_stale()
+    }
+    pub fn get_simulation_snapshot(&self) -> String {
+        self.simulation.get_simulation_snapshot()
+    }
+    pub fn load_simulation_snapshot(&mut self, json: &str) -> Result<(), String> {
+        self.simulation
+            .load_simulation_snapshot(json)
+            .map_err(|e| e.to_string())
+    }
+    // JSの ArrayBuffer は Uint8Array にして渡す
+    pub fn load_simulation_snapshot_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.simulation
+            .load_simulation_snapshot_bytes(bytes)
+            .map_err(|e| e.to_string())
+    }
+
+    // 行われなかったセットは null。試合形式に合わない場合は何も変えずにエラーを投げる
+    pub fn set_match_result(
+        &mut self,
+        match_index: usize,
+        results: Vec<JsValue>,
+    ) -> Result<(), String> {
+        let results = results
+            .iter()
+            .enumerate()
+            .map(|(index, result)| match result.as_bool() {
+                Some(result) => Ok(Some(result)),
+                None if result.is_null() || result.is_undefined() => Ok(None),
+                None => Err(ResultsError::NotBoolean {
+                    match_index,
+                    set: index + 1,
+                }),
+            })
+            .collect::<Result<Vec<Option<bool>>, ResultsError>>()
+            .map_err(|e| e.to_string())?;
+        self.simulation
+            .set_match_result(match_index, &results)
+            .map_err(|e| e.to_string())
+    }
+    pub fn import_results_json(&mut self, json: &str) -> Result<(), String> {
+        self.simulation
+            .import_results_json(json)
+            .map_err(|e| e.to_string())
+    }
+    pub fn import_results_csv(&mut self, csv: &str) -> Result<(), String> {
+        self.simulation
+            .import_results_csv(csv)
+            .map_err(|e| e.to_string())
+    }
+    pub fn find_match_index(&self, team_code: &str, opponent_team_code: &str) -> Option<usize> {
+        self.simulation
+            .find_match_index(team_code, opponent_team_code)
+    }
+
+    // output_flag なら順位の回数だけ、そうでなければ seasons/jp2024.snapshot.json に保存する形で出力する
+    pub fn simulate(&mut self, output_flag: bool) {
+        self.simulation.simulate();
+        self.log_result(output_flag);
+    }
+    pub fn simulate_until(
+        &mut self,
+        target_width: f64,
+        batch_count: usize,
+        max_count: usize,
+        output_flag: bool,
+    ) {
+        self.simulation
+            .simulate_until(target_width, batch_count, max_count);
+        self.log_result(output_flag);
+    }
+    pub fn simulate_stream(&mut self, stream: usize) {
+        self.simulation.simulate_stream(stream);
+    }
+    // シーズンや形が違う結果は何も変えずにエラーを投げる
+    pub fn merge_simulation(&mut self, other: &JsSflSimulation) -> Result<(), String> {
+        self.simulation
+            .merge_simulation(&other.simulation)
+            .map_err(|e| e.to_string())
+    }
+    pub fn simulate_scenario(
+        &mut self,
+        scenario: &JsSflScenario,
+    ) -> Result<JsSflScenarioResult, String> {
+        self.simulation
+            .simulate_scenario(&scenario.0)
+            .map(JsSflScenarioResult)
+            .map_err(|e| e.to_string())
+    }
+    pub fn get_match_leverages(&mut self) -> Result<Vec<JsMatchLeverage>, String> {
+        self.simulation
+            .get_match_leverages()
+            .map(|leverages| leverages.into_iter().map(JsMatchLeverage).collect())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn get_current_places(&self, stage: &JsSflStage) -> Option<Vec<usize>> {
+        self.simulation.get_current_places(&stage.0)
+    }
+    pub fn get_division_places_detail(&mut self, team_index: usize) -> Vec<JsDivisionPlaceDetail> {
+        self.simulation
+            .get_division_places_detail(team_index)
+            .iter()
+            .map(JsDivisionPlaceDetail::from)
+            .collect()
+    }
+    pub fn get_place_to_point_detail(&mut self, team_index: usize) -> Vec<JsPlaceToPointDetail> {
+        self.simulation
+            .get_place_to_point_detail(team_index)
+            .iter()
+            .map(JsPlaceToPointDetail::from)
+            .collect()
+    }
+    pub fn get_expect_point(&self, team_index: usize) -> i32 {
+        self.simulation.get_expect_point(team_index)
+    }
+    pub fn get_expect_battle(&self, team_index: usize) -> i32 {
+        self.simulation.get_expect_battle(team_index)
+    }
+    pub fn get_place_count(&self, sfl_stage: &JsSflStage, team_index: usize) -> Vec<u32> {
+        self.simulation.get_place_count(&sfl_stage.0, team_index)
+    }
+    pub fn get_probability_interval(&self, success_count: u32) -> Vec<f64> {
+        self.simulation.get_probability_interval(success_count)
+    }
+    pub fn get_place_interval(
+        &self,
+        sfl_stage: &JsSflStage,
+        team_index: usize,
+        place: usize,
+    ) -> Vec<f64> {
+        self.simulation
+            .get_place_interval(&sfl_stage.0, team_index, place)
+    }
+    pub fn get_playoff_interval(&self, team_index: usize) -> Vec<f64> {
+        self.simulation.get_playoff_interval(team_index)
+    }
+    pub fn get_max_playoff_interval_width(&self) -> f64 {
+        self.simulation.get_max_playoff_interval_width()
+    }
+    pub fn get_clinch_statuses(&self, sfl_stage: &JsSflStage) -> Vec<JsClinchStatus> {
+        self.simulation
+            .get_clinch_statuses(&sfl_stage.0)
+            .iter()
+            .map(JsClinchStatus::from)
+            .collect()
+    }
+    pub fn get_match_score_distribution(&self, match_index: usize) -> Vec<JsMatchScoreProbability> {
+        self.simulation
+            .get_match_score_distribution(match_index)
+            .iter()
+            .map(JsMatchScoreProbability::from)
+            .collect()
+    }
+    pub fn is_exact(&self) -> bool {
+        self.simulation.is_exact()
+    }
+    pub fn get_exact_state_count(&self) -> usize {
+        self.simulation.get_exact_state_count()
+    }
+    pub fn get_exact_place_probability(
+        &self,
+        sfl_stage: &JsSflStage,
+        team_index: usize,
+    ) -> Vec<f64> {
+        self.simulation
+            .get_exact_place_probability(&sfl_stage.0, team_index)
+    }
+}
+
+impl JsSflSimulation {
+    fn log_result(&self, output_flag: bool) {
+        if output_flag {
+            console_log!("{:?}", self.simulation.get_result().division_place_count);
+        } else {
+            console_log!("{}", self.simulation.get_simulation_snapshot());
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = SimulationOption)]
+#[derive(Copy, Clone)]
+pub struct JsSimulationOption {
+    pub enable_rate: bool,
+    pub random_seed: bool,
+    pub exact_state_limit: usize,
+    pub sample_match_outcomes: bool,
+}
+
+impl From<SimulationOption> for JsSimulationOption {
+    fn from(option: SimulationOption) -> JsSimulationOption {
+        JsSimulationOption {
+            enable_rate: option.enable_rate,
+            random_seed: option.random_seed,
+            exact_state_limit: option.exact_state_limit,
+            sample_match_outcomes: option.sample_match_outcomes,
+        }
+    }
+}
+
+impl From<JsSimulationOption> for SimulationOption {
+    fn from(option: JsSimulationOption) -> SimulationOption {
+        SimulationOption {
+            enable_rate: option.enable_rate,
+            random_seed: option.random_seed,
+            exact_state_limit: option.exact_state_limit,
+            sample_match_outcomes: option.sample_match_outcomes,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = SflStats)]
+pub struct JsSflStats(SflStats);
+
+#[wasm_bindgen(js_class = SflStats)]
+impl JsSflStats {
+    pub fn get_points(&self) -> Vec<u32> {
+        self.0.get_points()
+    }
+    pub fn get_battles(&self) -> Vec<i32> {
+        self.0.get_battles()
+    }
+}
+
+#[wasm_bindgen(js_name = SflStage)]
+#[derive(Copy, Clone)]
+pub struct JsSflStage(SflStage);
+
+#[wasm_bindgen(js_class = SflStage)]
+impl JsSflStage {
+    pub fn division(division_index: usize) -> JsSflStage {
+        JsSflStage(SflStage::division(division_index))
+    }
+    pub fn all_division() -> JsSflStage {
+        JsSflStage(SflStage::all_division())
+    }
+    pub fn playoff() -> JsSflStage {
+        JsSflStage(SflStage::playoff())
+    }
+    pub fn grand_final() -> JsSflStage {
+        JsSflStage(SflStage::grand_final())
+    }
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> JsSflStageKind {
+        self.0.kind.into()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn division_index(&self) -> usize {
+        self.0.division_index
+    }
+}
+
+#[wasm_bindgen(js_name = SflStageKind)]
+pub enum JsSflStageKind {
+    Division,
+    AllDivision,
+    Playoff,
+    GrandFinal,
+}
+
+impl From<SflStageKind> for JsSflStageKind {
+    fn from(kind: SflStageKind) -> JsSflStageKind {
+        match kind {
+            SflStageKind::Division => JsSflStageKind::Division,
+            SflStageKind::AllDivision => JsSflStageKind::AllDivision,
+            SflStageKind::Playoff => JsSflStageKind::Playoff,
+            SflStageKind::GrandFinal => JsSflStageKind::GrandFinal,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = GameType)]
+pub enum JsGameType {
+    VAN,
+    MID,
+    GENERAL,
+    EXTRA,
+    PlayoffExtra,
+}
+
+impl From<GameType> for JsGameType {
+    fn from(game_type: GameType) -> JsGameType {
+        match game_type {
+            GameType::VAN => JsGameType::VAN,
+            GameType::MID => JsGameType::MID,
+            GameType::GENERAL => JsGameType::GENERAL,
+            GameType::EXTRA => JsGameType::EXTRA,
+            GameType::PlayoffExtra => JsGameType::PlayoffExtra,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = SflRatingSetting)]
+pub enum JsSflRatingSetting {
+    TeamOnly,
+    HomeAway,
+    GameType,
+    HomeAwayGameType,
+    Player,
+}
+
+impl From<SflRatingSetting> for JsSflRatingSetting {
+    fn from(setting: SflRatingSetting) -> JsSflRatingSetting {
+        match setting {
+            SflRatingSetting::TeamOnly => JsSflRatingSetting::TeamOnly,
+            SflRatingSetting::HomeAway => JsSflRatingSetting::HomeAway,
+            SflRatingSetting::GameType => JsSflRatingSetting::GameType,
+            SflRatingSetting::HomeAwayGameType => JsSflRatingSetting::HomeAwayGameType,
+            SflRatingSetting::Player => JsSflRatingSetting::Player,
+        }
+    }
+}
+
+impl From<JsSflRatingSetting> for SflRatingSetting {
+    fn from(setting: JsSflRatingSetting) -> SflRatingSetting {
+        match setting {
+            JsSflRatingSetting::TeamOnly => SflRatingSetting::TeamOnly,
+            JsSflRatingSetting::HomeAway => SflRatingSetting::HomeAway,
+            JsSflRatingSetting::GameType => SflRatingSetting::GameType,
+            JsSflRatingSetting::HomeAwayGameType => SflRatingSetting::HomeAwayGameType,
+            JsSflRatingSetting::Player => SflRatingSetting::Player,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = SflRatingModel)]
+pub enum JsSflRatingModel {
+    Elo,
+    Glicko2,
+}
+
+impl From<SflRatingModel> for JsSflRatingModel {
+    fn from(model: SflRatingModel) -> JsSflRatingModel {
+        match model {
+            SflRatingModel::Elo => JsSflRatingModel::Elo,
+            SflRatingModel::Glicko2 => JsSflRatingModel::Glicko2,
+        }
+    }
+}
+
+impl From<JsSflRatingModel> for SflRatingModel {
+    fn from(model: JsSflRatingModel) -> SflRatingModel {
+        match model {
+            JsSflRatingModel::Elo => SflRatingModel::Elo,
+            JsSflRatingModel::Glicko2 => SflRatingModel::Glicko2,
+        }
+    }
+}
+
+// チームとプレイヤーはシーズン内の番号で渡す
+#[wasm_bindgen(js_name = SflMatch)]
+pub struct JsSflMatch(SflMatch);
+
+#[wasm_bindgen(js_class = SflMatch)]
+impl JsSflMatch {
+    #[wasm_bindgen(getter)]
+    pub fn section(&self) -> u32 {
+        self.0.section
+    }
+    #[wasm_bindgen(getter)]
+    pub fn branch(&self) -> u32 {
+        self.0.branch
+    }
+    #[wasm_bindgen(getter)]
+    pub fn date_expression(&self) -> String {
+        self.0.date_expression()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn sfl_stage(&self) -> JsSflStage {
+        JsSflStage(self.0.sfl_stage)
+    }
+    #[wasm_bindgen(getter)]
+    pub fn team(&self) -> usize {
+        self.0.team()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn opponent_team(&self) -> usize {
+        self.0.opponent_team()
+    }
+}
+
+#[wasm_bindgen(js_name = SflRecord)]
+pub struct JsSflRecord(SflRecord);
+
+#[wasm_bindgen(js_class = SflRecord)]
+impl JsSflRecord {
+    #[wasm_bindgen(getter)]
+    pub fn set_number(&self) -> u32 {
+        self.0.set_number
+    }
+    #[wasm_bindgen(getter)]
+    pub fn win_flag(&self) -> bool {
+        self.0.win_flag
+    }
+    #[wasm_bindgen(getter)]
+    pub fn point(&self) -> u32 {
+        self.0.point
+    }
+    #[wasm_bindgen(getter)]
+    pub fn game_type(&self) -> JsGameType {
+        self.0.game_type.into()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn is_valid(&self) -> bool {
+        self.0.is_valid
+    }
+    #[wasm_bindgen(getter)]
+    pub fn is_prediction(&self) -> bool {
+        self.0.is_prediction
+    }
+    #[wasm_bindgen(getter)]
+    pub fn player(&self) -> Option<usize> {
+        self.0.player()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn opponent_player(&self) -> Option<usize> {
+        self.0.opponent_player()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn character(&self) -> Option<String> {
+        self.0.character()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn opponent_character(&self) -> Option<String> {
+        self.0.opponent_character()
+    }
+}
+
+#[wasm_bindgen(js_name = DivisionPlaceDetail)]
+pub struct JsDivisionPlaceDetail {
+    pub first: usize,
+    pub second: usize,
+    pub third: usize,
+    pub time: usize,
+}
+
+impl From<&DivisionPlaceDetail> for JsDivisionPlaceDetail {
+    fn from(detail: &DivisionPlaceDetail) -> JsDivisionPlaceDetail {
+        JsDivisionPlaceDetail {
+            first: detail.first,
+            second: detail.second,
+            third: detail.third,
+            time: detail.time,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = PlaceToPointDetail)]
+pub struct JsPlaceToPointDetail {
+    pub time: usize,
+    pub sum_point: usize,
+    pub highest_pont: usize,
+    pub lowest_point: usize,
+}
+
+impl From<&PlaceToPointDetail> for JsPlaceToPointDetail {
+    fn from(detail: &PlaceToPointDetail) -> JsPlaceToPointDetail {
+        JsPlaceToPointDetail {
+            time: detail.time,
+            sum_point: detail.sum_point,
+            highest_pont: detail.highest_pont,
+            lowest_point: detail.lowest_point,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = MatchScoreProbability)]
+pub struct JsMatchScoreProbability {
+    pub team_point: u32,
+    pub opponent_team_point: u32,
+    pub probability: f64,
+}
+
+impl From<&MatchScoreProbability> for JsMatchScoreProbability {
+    fn from(score: &MatchScoreProbability) -> JsMatchScoreProbability {
+        JsMatchScoreProbability {
+            team_point: score.team_point,
+            opponent_team_point: score.opponent_team_point,
+            probability: score.probability,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = ClinchStatus)]
+pub struct JsClinchStatus {
+    pub team_index: usize,
+    pub guaranteed_point: u32,
+    pub max_point: u32,
+    pub playoff_clinched: bool,
+    pub first_clinched: bool,
+    pub playoff_eliminated: bool,
+    pub first_eliminated: bool,
+    pub playoff_magic_number: u32,
+    pub first_magic_number: u32,
+    pub is_exact: bool,
+}
+
+impl From<&ClinchStatus> for JsClinchStatus {
+    fn from(status: &ClinchStatus) -> JsClinchStatus {
+        JsClinchStatus {
+            team_index: status.team_index,
+            guaranteed_point: status.guaranteed_point,
+            max_point: status.max_point,
+            playoff_clinched: status.playoff_clinched,
+            first_clinched: status.first_clinched,
+            playoff_eliminated: status.playoff_eliminated,
+            first_eliminated: status.first_eliminated,
+            playoff_magic_number: status.playoff_magic_number,
+            first_magic_number: status.first_magic_number,
+            is_exact: status.is_exact,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = TeamLeverage)]
+pub struct JsTeamLeverage {
+    pub team_index: usize,
+    pub playoff_probability: f64,
+    pub playoff_swing: f64,
+    pub first_probability: f64,
+    pub first_swing: f64,
+}
+
+impl From<&TeamLeverage> for JsTeamLeverage {
+    fn from(team: &TeamLeverage) -> JsTeamLeverage {
+        JsTeamLeverage {
+            team_index: team.team_index,
+            playoff_probability: team.playoff_probability,
+            playoff_swing: team.playoff_swing,
+            first_probability: team.first_probability,
+            first_swing: team.first_swing,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = MatchLeverage)]
+pub struct JsMatchLeverage(MatchLeverage);
+
+#[wasm_bindgen(js_class = MatchLeverage)]
+impl JsMatchLeverage {
+    #[wasm_bindgen(getter)]
+    pub fn match_index(&self) -> usize {
+        self.0.match_index
+    }
+    #[wasm_bindgen(getter)]
+    pub fn leverage(&self) -> f64 {
+        self.0.leverage
+    }
+    pub fn get_teams(&self) -> Vec<JsTeamLeverage> {
+        self.0
+            .get_teams()
+            .iter()
+            .map(JsTeamLeverage::from)
+            .collect()
+    }
+}
+
+#[wasm_bindgen(js_name = SflScenario)]
+pub struct JsSflScenario(SflScenario);
+
+#[wasm_bindgen(js_class = SflScenario)]
+impl JsSflScenario {
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str) -> JsSflScenario {
+        JsSflScenario(SflScenario::new(name))
+    }
+    pub fn get_name(&self) -> String {
+        self.0.get_name()
+    }
+    pub fn force_set(&mut self, match_index: usize, set_index: usize, win_flag: bool) {
+        self.0.force_set(match_index, set_index, win_flag);
+    }
+    // JSからは真偽値の配列を数値の配列で渡す。0 以外ならチーム側の勝ち
+    pub fn force_match_sets(&mut self, match_index: usize, results: Vec<u8>) {
+        let results: Vec<bool> = results.iter().map(|result| *result != 0).collect();
+        self.0.force_match_sets(match_index, &results);
+    }
+    pub fn force_match_score(
+        &mut self,
+        match_index: usize,
+        team_point: u32,
+        opponent_team_point: u32,
+    ) {
+        self.0
+            .force_match_score(match_index, team_point, opponent_team_point);
+    }
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[wasm_bindgen(js_name = SflScenarioResult)]
+pub struct JsSflScenarioResult(SflScenarioResult);
+
+#[wasm_bindgen(js_class = SflScenarioResult)]
+impl JsSflScenarioResult {
+    pub fn get_name(&self) -> String {
+        self.0.get_name()
+    }
+    pub fn is_exact(&self) -> bool {
+        self.0.is_exact()
+    }
+    pub fn get_used_count(&self) -> usize {
+        self.0.get_used_count()
+    }
+    pub fn get_place_count(&self, sfl_stage: &JsSflStage, team_index: usize) -> Vec<u32> {
+        self.0.get_place_count(&sfl_stage.0, team_index)
+    }
+    pub fn get_place_probability(&self, sfl_stage: &JsSflStage, team_index: usize) -> Vec<f64> {
+        self.0.get_place_probability(&sfl_stage.0, team_index)
+    }
+    pub fn get_playoff_probability(&self, team_index: usize) -> f64 {
+        self.0.get_playoff_probability(team_index)
+    }
+}
+
+#[wasm_bindgen(js_name = BradleyTerry)]
+pub struct JsBradleyTerry(BradleyTerry);
+
+#[wasm_bindgen(js_class = BradleyTerry)]
+impl JsBradleyTerry {
+    #[wasm_bindgen(constructor)]
+    pub fn new(use_home: bool, use_leader: bool, prior_variance: Option<f64>) -> JsBradleyTerry {
+        JsBradleyTerry(BradleyTerry::new(use_home, use_leader, prior_variance))
+    }
+    pub fn get_strength(&self, team_index: usize) -> f64 {
+        self.0.get_strength(team_index)
+    }
+    pub fn get_strength_error(&self, team_index: usize) -> f64 {
+        self.0.get_strength_error(team_index)
+    }
+    pub fn get_leader_offset(&self, team_index: usize) -> f64 {
+        self.0.get_leader_offset(team_index)
+    }
+    pub fn get_leader_offset_error(&self, team_index: usize) -> f64 {
+        self.0.get_leader_offset_error(team_index)
+    }
+    pub fn get_home_advantage(&self) -> f64 {
+        self.0.get_home_advantage()
+    }
+    pub fn get_home_advantage_error(&self) -> f64 {
+        self.0.get_home_advantage_error()
+    }
+    pub fn get_set_count(&self) -> usize {
+        self.0.get_set_count()
+    }
+}
+
+// JS側の関数で勝率を決めるモデル
+// 関数は (record: SflRecord, match: SflMatch) を受け取り、チーム側の勝率を数値で返す
+// 実績はJS側で持っている前提なので fit では何もしない
+// JSの関数はスレッドをまたげないので parallel フィーチャーでは使えない
+#[cfg(not(feature = "parallel"))]
+#[wasm_bindgen]
+pub struct JsWinProbabilityModel {
+    callback: Function,
+}
+
+#[cfg(not(feature = "parallel"))]
+#[wasm_bindgen]
+impl JsWinProbabilityModel {
+    #[wasm_bindgen(constructor)]
+    pub fn new(callback: Function) -> JsWinProbabilityModel {
+        JsWinProbabilityModel { callback }
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl WinProbabilityModel for JsWinProbabilityModel {
+    fn fit(&mut self, _: &Season, _: &SflStage, _: &[Vec<SflRecord>]) {}
+    fn get_team_win_percentage(&self, record: &SflRecord) -> Option<f64> {
+        let result = self
+            .callback
+            .call2(
+                &JsValue::NULL,
+                &JsValue::from(JsSflRecord(record.to_owned())),
+                &JsValue::from(JsSflMatch(record.sfl_match.to_owned())),
+            )
+            .ok()?;
+        result
+            .as_f64()
+            .filter(|percentage| (0_f64..=1_f64).contains(percentage))
+    }
+}
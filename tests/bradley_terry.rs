@@ -0,0 +1,77 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::bradley_terry::BradleyTerry;
+use sfl_lib::model::WinProbabilityModel;
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{SflRecord, SflStage};
+
+const THREE_TEAMS_JSON: &str = include_str!("fixtures/three_teams.json");
+
+// 各試合の最初の10セットのうち、AWAYが wins セット取った実績
+fn played_records(season: &Season, wins: &[usize]) -> Vec<Vec<SflRecord>> {
+    let mut sfl_records = season.get_initial_records(&SflStage::all_division());
+    for (records, wins) in sfl_records.iter_mut().zip(wins) {
+        for (n, record) in records.iter_mut().take(10).enumerate() {
+            record.win_flag = n < *wins;
+            record.is_valid = true;
+            record.is_prediction = false;
+        }
+    }
+    sfl_records
+}
+
+#[test]
+fn bradley_terry_orders_teams() {
+    let season = Season::from_json(THREE_TEAMS_JSON).unwrap();
+    let all_division = SflStage::all_division();
+    // AAAはBBBに8-2、BBBはCCCに8-2、AAAはCCCに9-1
+    let sfl_records = played_records(&season, &[8, 8, 1]);
+    let mut model = BradleyTerry::new(false, false, None);
+    model.fit(&season, &all_division, &sfl_records);
+    assert_eq!(model.get_set_count(), 30);
+    let strength = |code: &str| model.get_strength(season.find_team(code).unwrap().get_index());
+    let error = |code: &str| model.get_strength_error(season.find_team(code).unwrap().get_index());
+    // 最尤推定では最初のチームを0に固定する
+    assert_eq!(strength("AAA"), 0_f64);
+    assert_eq!(error("AAA"), 0_f64);
+    assert!(strength("BBB") < 0_f64 && strength("CCC") < strength("BBB"));
+    assert!(error("BBB") > 0_f64 && error("BBB").is_finite());
+
+    // 順番を入れ替えても同じ推定になる
+    let mut reversed = sfl_records.to_owned();
+    reversed.reverse();
+    let mut reversed_model = BradleyTerry::new(false, false, None);
+    reversed_model.fit(&season, &all_division, &reversed);
+    let team = season.find_team("CCC").unwrap().get_index();
+    assert!((reversed_model.get_strength(team) - model.get_strength(team)).abs() < 1e-9);
+
+    let percentage = model.get_team_win_percentage(&sfl_records[0][11]).unwrap();
+    assert!(percentage > 0.5);
+}
+
+#[test]
+fn bradley_terry_prior_and_covariates() {
+    let season = Season::from_json(THREE_TEAMS_JSON).unwrap();
+    let all_division = SflStage::all_division();
+    let sfl_records = played_records(&season, &[8, 8, 1]);
+    let mut flat = BradleyTerry::new(false, false, Some(100_f64));
+    flat.fit(&season, &all_division, &sfl_records);
+    let mut tight = BradleyTerry::new(false, false, Some(0.1_f64));
+    tight.fit(&season, &all_division, &sfl_records);
+    // 事前分布が強いほど強さは0に寄る
+    let team = season.find_team("AAA").unwrap().get_index();
+    assert!(flat.get_strength(team) > tight.get_strength(team));
+    assert!(tight.get_strength(team) > 0_f64);
+
+    // AWAYが全部勝っていればHOME補正はマイナス
+    let sfl_records = played_records(&season, &[10, 10, 10]);
+    let mut home = BradleyTerry::new(true, true, Some(1_f64));
+    home.fit(&season, &all_division, &sfl_records);
+    assert!(home.get_home_advantage() < 0_f64);
+    assert!(home.get_home_advantage_error() > 0_f64);
+    assert!(home.get_leader_offset_error(team).is_finite());
+
+    // 実績がなければ勝率はわからない
+    let empty = BradleyTerry::new(true, false, Some(1_f64));
+    assert_eq!(empty.get_team_win_percentage(&sfl_records[0][0]), None);
+}
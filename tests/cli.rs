@@ -0,0 +1,72 @@
+#![cfg(feature = "serialize")]
+
+use std::process::{Command, Output};
+
+const RESULTS_JSON: &str = r#"[
+    {"section": 1, "branch": 1, "away": "DFM", "home": "OJA", "sets": ["DFM", "DFM", "DFM", "DFM", "DFM", "DFM", "DFM"]}
+]"#;
+
+fn write_results(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("sfl_cli_{}_{}.json", name, std::process::id()));
+    std::fs::write(&path, RESULTS_JSON).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+fn sfl(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_sfl"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn simulate_json_is_reproducible() {
+    let results = write_results("simulate");
+    let args = [
+        "simulate",
+        "--results",
+        &results,
+        "--count",
+        "200",
+        "--seed",
+        "7",
+        "--json",
+    ];
+    let first = sfl(&args);
+    let second = sfl(&args);
+    assert!(first.status.success());
+    assert_eq!(first.stdout, second.stdout);
+    let value: serde_json::Value = serde_json::from_slice(&first.stdout).unwrap();
+    assert_eq!(value["count"], 200);
+    assert_eq!(value["seed"], 7);
+    let teams = value["teams"].as_array().unwrap();
+    assert_eq!(teams.len(), 12);
+    let place_probability = teams[0]["place_probability"].as_array().unwrap();
+    let total: f64 = place_probability.iter().map(|p| p.as_f64().unwrap()).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn standings_and_predict_tables() {
+    let results = write_results("tables");
+    let standings = sfl(&["standings", "--results", &results]);
+    let standings = String::from_utf8(standings.stdout).unwrap();
+    let lines: Vec<&str> = standings.lines().collect();
+    assert_eq!(lines.len(), 13);
+    assert!(lines[0].starts_with("division"));
+    // 40-0 で勝ったDFMがディビジョンの先頭。決着後のセットはバトル得失に入らない
+    let dfm: Vec<&str> = lines[1].split_whitespace().collect();
+    assert_eq!(dfm, vec!["S", "DFM", "1", "40", "7"]);
+
+    // 終わった試合は予想に出ない
+    let predict = sfl(&["predict", "--results", &results]);
+    let predict = String::from_utf8(predict.stdout).unwrap();
+    assert_eq!(predict.lines().count(), 60);
+    assert!(!predict.contains("DFM   OJA"));
+
+    let unknown = sfl(&["unknown"]);
+    assert_eq!(unknown.status.code(), Some(2));
+    assert!(String::from_utf8(unknown.stderr)
+        .unwrap()
+        .contains("unknown command"));
+}
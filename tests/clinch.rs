@@ -0,0 +1,111 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::clinch::{get_clinch_statuses, ClinchStatus};
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{SflRecord, SflStage};
+
+const FOUR_TEAMS_JSON: &str = include_str!("fixtures/four_teams.json");
+
+// 最初の played 試合はAWAYが全セット取って 40-0
+fn played_records(season: &Season, played: usize) -> Vec<Vec<SflRecord>> {
+    played_records_with(season, &vec![true; played])
+}
+
+// 先頭から試合ごとに、AWAYかHOMEの一方が全セット取る
+fn played_records_with(season: &Season, away_wins: &[bool]) -> Vec<Vec<SflRecord>> {
+    let all_division = SflStage::all_division();
+    let format = season.get_format(&all_division);
+    let mut sfl_records = season.get_initial_records(&all_division);
+    for (records, away_win) in sfl_records.iter_mut().zip(away_wins) {
+        for record in records.iter_mut() {
+            record.win_flag = *away_win;
+            record.is_valid = true;
+            record.is_prediction = false;
+        }
+        format.correct_records(records);
+    }
+    sfl_records
+}
+
+fn find_status(season: &Season, statuses: &[ClinchStatus], code: &str) -> ClinchStatus {
+    let team_index = season.find_team(code).unwrap().get_index();
+    statuses
+        .iter()
+        .find(|status| status.team_index == team_index)
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn clinch_and_elimination() {
+    let season = Season::from_json(FOUR_TEAMS_JSON).unwrap();
+    let sfl_records = played_records(&season, 5);
+    let statuses = get_clinch_statuses(&season, &SflStage::division(0), &sfl_records);
+    let status = |code: &str| find_status(&season, &statuses, code);
+    // AAAは3勝で120、他は最大でも80
+    let aaa = status("AAA");
+    assert_eq!((aaa.guaranteed_point, aaa.max_point), (120, 120));
+    assert!(aaa.first_clinched && aaa.playoff_clinched);
+    // BBBは最後の試合によらず3位以内だが、1位はない
+    let bbb = status("BBB");
+    assert_eq!((bbb.guaranteed_point, bbb.max_point), (40, 80));
+    assert!(bbb.playoff_clinched && bbb.first_eliminated);
+    assert!(!bbb.first_clinched);
+    // DDDは全敗で脱落
+    let ddd = status("DDD");
+    assert!(ddd.playoff_eliminated && !ddd.playoff_clinched);
+    assert!(ddd.playoff_magic_number > ddd.max_point);
+}
+
+#[test]
+fn magic_number_before_season() {
+    let season = Season::from_json(FOUR_TEAMS_JSON).unwrap();
+    let sfl_records = played_records(&season, 0);
+    let statuses = get_clinch_statuses(&season, &SflStage::all_division(), &sfl_records);
+    assert_eq!(statuses.len(), 4);
+    for status in statuses.iter() {
+        assert_eq!(status.guaranteed_point, 0);
+        assert_eq!(status.max_point, 120);
+        assert!(!status.playoff_clinched && !status.playoff_eliminated);
+        // 他の3チームが全勝した120ではなく、直接対決を考えて抜かれないポイントを上回る必要がある
+        assert!(status.is_exact);
+        assert_eq!(status.first_magic_number, 101);
+        assert_eq!(status.playoff_magic_number, 66);
+    }
+}
+
+#[test]
+fn head_to_head_is_considered() {
+    // 上位2チームがプレイオフに進むシーズン
+    let json = FOUR_TEAMS_JSON.replacen(
+        r#""name": "four team season","#,
+        r#""name": "four team season", "playoff_teams": 2,"#,
+        1,
+    );
+    let season = Season::from_json(&json).unwrap();
+    assert_eq!(season.playoff_team_count, 2);
+    assert!(
+        Season::from_json(&json.replace("\"playoff_teams\": 2", "\"playoff_teams\": 0")).is_err()
+    );
+    // AAA 40, BBB 40, CCC 40, DDD 80 で、残りは BBB-CCC だけ
+    let sfl_records = played_records_with(&season, &[false, false, false, false, true]);
+    let statuses = get_clinch_statuses(&season, &SflStage::division(0), &sfl_records);
+    // BBBとCCCはどちらも80に届きうるが、直接対決なので両方は届かない
+    let ddd = find_status(&season, &statuses, "DDD");
+    assert_eq!((ddd.guaranteed_point, ddd.max_point), (80, 80));
+    assert!(ddd.is_exact && ddd.playoff_clinched);
+    assert_eq!(ddd.playoff_magic_number, 0);
+    // 1位はBBBかCCCが40-0で勝つと並ぶので決まらない
+    assert!(!ddd.first_clinched && !ddd.first_eliminated);
+    let aaa = find_status(&season, &statuses, "AAA");
+    assert!(aaa.playoff_eliminated && aaa.first_eliminated);
+
+    // 組み合わせが多すぎるとポイントの上下限だけから求める
+    let season = Season::jp2024();
+    let sfl_records = season.get_initial_records(&SflStage::all_division());
+    let statuses = get_clinch_statuses(&season, &SflStage::all_division(), &sfl_records);
+    assert_eq!(statuses.len(), season.teams.len());
+    assert!(statuses
+        .iter()
+        .all(|status| !status.is_exact && !status.playoff_clinched && !status.playoff_eliminated));
+}
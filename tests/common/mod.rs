@@ -0,0 +1,15 @@
+// 先鋒、中堅、大将をAWAYがストレートで取った40-0。決着後のセットは None
+pub const SWEEP: [Option<bool>; 12] = [
+    Some(true),
+    Some(true),
+    None,
+    Some(true),
+    Some(true),
+    None,
+    Some(true),
+    Some(true),
+    Some(true),
+    None,
+    None,
+    None,
+];
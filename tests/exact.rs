@@ -0,0 +1,91 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::exact::enumerate_season;
+use sfl_lib::scenario::SflScenario;
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{SflRecord, SflStage};
+use sfl_lib::SflSimulation;
+
+const THREE_TEAMS_JSON: &str = include_str!("fixtures/three_teams.json");
+
+#[test]
+fn exact_probabilities_sum_to_one() {
+    let season = Season::from_json(THREE_TEAMS_JSON).unwrap();
+    let sfl_records = season.get_initial_records(&SflStage::all_division());
+    let exact =
+        enumerate_season(&season, &sfl_records, &|_: &SflRecord| 0.5_f64, 1_000_000).unwrap();
+    assert!(exact.state_count > 1);
+    for probabilities in exact.division_place_probability.iter() {
+        let total: f64 = probabilities.iter().sum();
+        assert!((total - 1_f64).abs() < 1e-9);
+    }
+    for place in 0..3 {
+        let total: f64 = exact
+            .division_place_probability
+            .iter()
+            .map(|probabilities| probabilities[place])
+            .sum();
+        assert!((total - 1_f64).abs() < 1e-9);
+    }
+    // ディビジョンが2つないのでプレイオフはない
+    assert!(exact
+        .playoff_place_probability
+        .iter()
+        .flatten()
+        .all(|p| *p == 0_f64));
+
+    // 組み合わせが多すぎれば列挙しない
+    assert!(enumerate_season(&season, &sfl_records, &|_: &SflRecord| 0.5_f64, 10).is_none());
+}
+
+#[test]
+fn exact_result_converts_to_counts() {
+    let season = Season::from_json(THREE_TEAMS_JSON).unwrap();
+    let sfl_records = season.get_initial_records(&SflStage::all_division());
+    // AWAYが必ず勝つなら全チーム1勝1敗で、ポイントも同じなのでチーム番号の大きい順
+    let exact = enumerate_season(&season, &sfl_records, &|_: &SflRecord| 1_f64, 1_000_000).unwrap();
+    assert_eq!(exact.state_count, 1);
+    let team = |code: &str| season.find_team(code).unwrap().get_index();
+    assert_eq!(exact.division_place_probability[team("CCC")][0], 1_f64);
+    assert_eq!(exact.division_place_probability[team("AAA")][2], 1_f64);
+
+    let result = exact.to_simulation_result(&season, 10000);
+    assert_eq!(result.count, 10000);
+    assert_eq!(result.division_place_count[team("CCC")][0], 10000);
+    assert_eq!(result.place_to_points[team("CCC")][0].time, 10000);
+    assert_eq!(result.place_to_points[team("CCC")][1].highest_point, None);
+}
+
+#[test]
+fn exact_probabilities_are_not_rounded() {
+    // 回数が少なくても確率は丸めない
+    let mut simulation = SflSimulation::from_season_json(THREE_TEAMS_JSON).unwrap();
+    simulation.count = 7;
+    simulation.simulate();
+    assert!(simulation.is_exact());
+    let division = SflStage::division(0);
+    for team_index in 0..3 {
+        let probabilities = simulation.get_exact_place_probability(&division, team_index);
+        for (place, probability) in probabilities.iter().enumerate() {
+            let interval = simulation.get_place_interval(&division, team_index, place);
+            assert_eq!(
+                interval,
+                vec![*probability, 0.0, *probability, *probability]
+            );
+        }
+    }
+    let rounded = simulation.get_place_count(&division, 0)[0] as f64 / 7.0;
+    assert_ne!(
+        simulation.get_exact_place_probability(&division, 0)[0],
+        rounded
+    );
+
+    let mut scenario = SflScenario::new("first");
+    scenario.force_match_score(0, 40, 0);
+    let result = simulation.simulate_scenario(&scenario).unwrap();
+    assert!(result.is_exact());
+    let probability: f64 = result.get_place_probability(&division, 0).iter().sum();
+    assert!((probability - 1.0).abs() < 1e-9);
+    let rounded = result.get_place_count(&division, 0)[0] as f64 / 7.0;
+    assert_ne!(result.get_place_probability(&division, 0)[0], rounded);
+}
@@ -0,0 +1,267 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::format::{MatchFormat, MatchScore, SetError, SetSlot};
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{GameType, SflRecord, SflStage};
+use sfl_lib::SflSimulation;
+
+fn set_results(records: &mut [SflRecord], results: &[bool]) {
+    for (record, win_flag) in records.iter_mut().zip(results) {
+        record.win_flag = *win_flag;
+        record.is_valid = true;
+        record.is_prediction = false;
+    }
+}
+
+fn points(records: &[SflRecord]) -> Vec<u32> {
+    records
+        .iter()
+        .map(|record| if record.is_valid { record.point } else { 0 })
+        .collect()
+}
+
+#[test]
+fn division_format_corrects_records() {
+    let season = Season::jp2024();
+    let format = season.get_format(&SflStage::all_division());
+    let mut records = season.get_initial_records(&SflStage::all_division())[0].to_owned();
+    // 先鋒2-0、中堅1-2、大将3-1、延長戦なし
+    set_results(
+        &mut records,
+        &[
+            true, true, true, false, true, false, true, false, true, true, true, true,
+        ],
+    );
+    format.correct_records(&mut records);
+    assert_eq!(
+        points(&records),
+        vec![0, 10, 0, 0, 0, 10, 0, 0, 0, 20, 0, 0]
+    );
+    assert!(!records[2].is_valid);
+    assert!(!records[10].is_valid);
+    assert!(!records[11].is_valid);
+    assert_eq!(
+        format.evaluate(&records),
+        MatchScore {
+            team_point: 30,
+            opponent_team_point: 10,
+            win_flag: None,
+        }
+    );
+}
+
+#[test]
+fn division_format_extra_only_when_tied() {
+    let season = Season::jp2024();
+    let format = season.get_format(&SflStage::all_division());
+    let mut records = season.get_initial_records(&SflStage::all_division())[0].to_owned();
+    // 先鋒、中堅を取って大将を0-3で落とすと20-20で延長戦
+    set_results(
+        &mut records,
+        &[
+            true, true, true, true, true, true, false, false, false, true, true, false,
+        ],
+    );
+    format.correct_records(&mut records);
+    assert!(records[11].is_valid);
+    assert_eq!(records[11].point, 5);
+    assert_eq!(format.evaluate(&records).opponent_team_point, 25);
+
+    // 大将戦が決着していなければ延長戦は判定しない
+    let mut records = season.get_initial_records(&SflStage::all_division())[0].to_owned();
+    set_results(&mut records, &[true, true, true, true, true, true, false]);
+    records[11].is_valid = true;
+    format.correct_records(&mut records);
+    assert!(!records[11].is_valid);
+}
+
+#[test]
+fn playoff_format_win_threshold() {
+    let season = Season::jp2024();
+    let format: &MatchFormat = season.get_format(&SflStage::playoff());
+    let teams = season.get_teams(&SflStage::all_division());
+    let mut records = season.get_playoff_records(&teams[0], &teams[1]);
+    assert_eq!(records.len(), 36);
+    // 1周目はチーム側が40-0、2周目は相手側が0-40、3周目は先鋒だけチーム側
+    let mut results = vec![true; 11];
+    results.extend(vec![false; 11]);
+    results.extend(vec![
+        true, true, true, false, false, false, true, true, true, false, false,
+    ]);
+    results.extend(vec![false; 3]);
+    set_results(&mut records, &results);
+    let (win_team, team_point, opponent_team_point) = format.get_win_team(&records).unwrap();
+    assert_eq!(win_team, teams[0]);
+    assert_eq!((team_point, opponent_team_point), (70, 50));
+
+    // 60-60なら延長戦で決める
+    let mut results = vec![true; 11];
+    results.extend(vec![false; 11]);
+    results.extend(vec![
+        true, true, true, true, true, true, false, false, false, true, true,
+    ]);
+    results.extend(vec![false, true, false]);
+    set_results(&mut records, &results);
+    let (win_team, team_point, opponent_team_point) = format.get_win_team(&records).unwrap();
+    assert_eq!(win_team, teams[1]);
+    assert_eq!((team_point, opponent_team_point), (60, 70));
+}
+
+#[test]
+fn undecided_knockout_has_no_winner() {
+    let season = Season::jp2024();
+    let format = season.get_format(&SflStage::playoff());
+    let teams = season.get_teams(&SflStage::all_division());
+    // 1周目だけの40-0では70に届かない
+    let mut records = season.get_playoff_records(&teams[0], &teams[1]);
+    set_results(&mut records, &[true; 7]);
+    format.correct_records(&mut records);
+    assert_eq!(format.get_win_team(&records), None);
+
+    // 延長戦を外すと同点で決まらない試合がある。その試行のプレイオフ以降は数えない
+    let mut season = Season::jp2024();
+    season.formats.playoff.legs.pop();
+    season.formats.grand_final.legs.pop();
+    let mut simulation = SflSimulation::from_season(season);
+    simulation.count = 200;
+    simulation.option.exact_state_limit = 0;
+    simulation.simulate();
+    let champion_count: u32 = simulation
+        .get_result()
+        .playoff_place_count
+        .iter()
+        .map(|count| count[0])
+        .sum();
+    assert!(champion_count > 0 && champion_count < 200);
+}
+
+#[test]
+fn playoff_format_corrects_after_threshold() {
+    let season = Season::jp2024();
+    let format = season.get_format(&SflStage::playoff());
+    let teams = season.get_teams(&SflStage::all_division());
+    let mut records = season.get_playoff_records(&teams[0], &teams[1]);
+    // 1周目、2周目ともチーム側が40-0なら2周目の大将戦で70を超える
+    set_results(&mut records, &[true; 36]);
+    format.correct_records(&mut records);
+    let valid_count = records.iter().filter(|record| record.is_valid).count();
+    assert_eq!(valid_count, 7 * 2);
+    assert!(records[19].is_valid);
+    assert_eq!(records[19].point, 20);
+    assert!(records[20..].iter().all(|record| !record.is_valid));
+    assert_eq!(points(&records).iter().sum::<u32>(), 80);
+    assert_eq!(
+        format.evaluate(&records),
+        MatchScore {
+            team_point: 80,
+            opponent_team_point: 0,
+            win_flag: Some(true),
+        }
+    );
+}
+
+#[test]
+fn division_format_outcomes() {
+    let season = Season::jp2024();
+    let format = season.get_format(&SflStage::all_division());
+    let mut records = season.get_initial_records(&SflStage::all_division())[0].to_owned();
+    let outcomes = format.get_outcomes(&records, &|_: &SflRecord| 0.5_f64);
+    let total: f64 = outcomes.iter().map(|(_, p)| p).sum();
+    assert!((total - 1_f64).abs() < 1e-12);
+    // 五分なら裏返した結果も同じ確率
+    for (outcome, p) in outcomes.iter() {
+        let (_, q) = outcomes
+            .iter()
+            .find(|(o, _)| {
+                let [a, b, c, d] = outcome.match_points;
+                o.match_points == [b, a, d, c] && o.battle == -outcome.battle
+            })
+            .unwrap();
+        assert!((p - q).abs() < 1e-12);
+    }
+
+    // 先鋒を2-0で取っていれば先鋒のポイントは必ずチーム側
+    set_results(&mut records[..2], &[true, true]);
+    let outcomes = format.get_outcomes(&records, &|_: &SflRecord| 0.3_f64);
+    assert!(outcomes.iter().all(|(o, _)| o.match_points[0] >= 10));
+    let total: f64 = outcomes.iter().map(|(_, p)| p).sum();
+    assert!((total - 1_f64).abs() < 1e-12);
+
+    // すべて決まっていれば evaluate と同じ1通り
+    set_results(
+        &mut records,
+        &[
+            true, true, true, false, true, false, true, false, true, true, true, true,
+        ],
+    );
+    let outcomes = format.get_outcomes(&records, &|_: &SflRecord| 0.5_f64);
+    assert_eq!(outcomes.len(), 1);
+    let score = format.evaluate(&records);
+    assert_eq!(outcomes[0].0.get_team_point(), score.team_point);
+    assert_eq!(
+        outcomes[0].0.get_opponent_team_point(),
+        score.opponent_team_point
+    );
+    assert_eq!(outcomes[0].0.battle, 3);
+}
+
+#[test]
+fn check_results_by_position() {
+    let season = Season::jp2024();
+    let format = season.get_format(&SflStage::all_division());
+    let (t, f) = (Some(true), Some(false));
+    // 先鋒2-0、中堅2-0、大将3-0。決着後は None
+    let sweep = vec![t, t, None, t, t, None, t, t, t, None, None, None];
+    let slots = format.check_results(&sweep).unwrap();
+    assert_eq!(slots[1], SetSlot::Played(1));
+    assert_eq!(slots[2], SetSlot::Skipped);
+    assert_eq!(slots[11], SetSlot::Skipped);
+    // 途中までなら残りは Open
+    let slots = format.check_results(&[t, None]).unwrap();
+    assert_eq!(slots[1], SetSlot::Open);
+    assert_eq!(slots[2], SetSlot::Open);
+    // 決着した試合は途中で切っても残りは Skipped
+    let slots = format.check_results(&sweep[..9]).unwrap();
+    assert_eq!(slots[8], SetSlot::Played(8));
+    assert!(slots[9..].iter().all(|slot| *slot == SetSlot::Skipped));
+    let slots = format.check_results(&[t, t]).unwrap();
+    assert_eq!(slots[2], SetSlot::Skipped);
+    assert_eq!(slots[3], SetSlot::Open);
+
+    assert_eq!(
+        format.check_results(&[None; 13]),
+        Err(SetError::TooManySets { set: 13 })
+    );
+    assert_eq!(
+        format.check_results(&[t, t, f]),
+        Err(SetError::LegDecided {
+            set: 3,
+            game_type: GameType::VAN
+        })
+    );
+    let mut extra = sweep.to_owned();
+    extra[11] = t;
+    assert_eq!(
+        format.check_results(&extra),
+        Err(SetError::NotTied {
+            set: 12,
+            game_type: GameType::EXTRA,
+            team_point: 40,
+            opponent_team_point: 0
+        })
+    );
+    assert_eq!(
+        format.check_results(&[t, None, t]),
+        Err(SetError::MissingSet { set: 2 })
+    );
+
+    // プレイオフは70ポイントで決着した後のセットを受け付けない
+    let playoff = season.get_format(&SflStage::playoff());
+    let mut results = [&sweep[..11], &sweep[..11]].concat();
+    assert!(playoff.check_results(&results).is_ok());
+    results.push(f);
+    assert_eq!(
+        playoff.check_results(&results),
+        Err(SetError::MatchDecided { set: 23 })
+    );
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::glicko::{get_glicko2_win_percentage, update_glicko2, Glicko2Rating};
+use sfl_lib::season::Season;
+use sfl_lib::sfl::SflRatingModel::Glicko2;
+use sfl_lib::sfl::SflRatingSetting::TeamOnly;
+use sfl_lib::sfl::SflStage;
+use sfl_lib::SflRating;
+
+fn rating(rating: f64, deviation: f64) -> Glicko2Rating {
+    Glicko2Rating {
+        rating,
+        deviation,
+        volatility: 0.06_f64,
+    }
+}
+
+#[test]
+fn glicko2_paper_example() {
+    // Glickman の論文の計算例
+    let player = rating(1500_f64, 200_f64);
+    let results = vec![
+        (rating(1400_f64, 30_f64), 1_f64),
+        (rating(1550_f64, 100_f64), 0_f64),
+        (rating(1700_f64, 300_f64), 0_f64),
+    ];
+    let updated = update_glicko2(&player, &results);
+    assert!((updated.rating - 1464.06_f64).abs() < 0.01);
+    assert!((updated.deviation - 151.52_f64).abs() < 0.01);
+    assert!((updated.volatility - 0.05999_f64).abs() < 0.00001);
+
+    // 結果がなければRDだけ広がる
+    let idle = update_glicko2(&player, &[]);
+    assert_eq!(idle.rating, 1500_f64);
+    assert!(idle.deviation > 200_f64);
+}
+
+#[test]
+fn glicko2_uncertainty_widens_win_percentage() {
+    let (certain, _) =
+        get_glicko2_win_percentage(&rating(1600_f64, 50_f64), &rating(1500_f64, 50_f64));
+    let (uncertain, _) =
+        get_glicko2_win_percentage(&rating(1600_f64, 300_f64), &rating(1500_f64, 50_f64));
+    assert!(certain > uncertain && uncertain > 0.5);
+}
+
+#[test]
+fn glicko2_sfl_rating() {
+    let season = Season::jp2024();
+    let all_division = SflStage::all_division();
+    let mut records = season.get_initial_records(&all_division);
+    // 第1節第1試合の先鋒戦をAWAYが2-0
+    for record in records[0].iter_mut().take(2) {
+        record.win_flag = true;
+        record.is_valid = true;
+        record.is_prediction = false;
+    }
+    let away = records[0][0].sfl_match.team.get_index();
+    let home = records[0][0].sfl_match.opponent_team.get_index();
+    let mut sfl_rating = SflRating::with_model(TeamOnly, Glicko2);
+    sfl_rating.calc_ratings(&season, &all_division, &records);
+    assert!(sfl_rating.get_rating(away, false, false).unwrap() > 1500_f64);
+    assert!(sfl_rating.get_rating(home, false, false).unwrap() < 1500_f64);
+    // 試合をしたチームだけRDが縮む
+    assert!(sfl_rating.get_rating_deviation(away, false, false).unwrap() < 350_f64);
+    let idle = season.get_teams(&all_division)[0].get_index();
+    assert_eq!(
+        sfl_rating.get_rating_deviation(idle, false, false),
+        Some(350_f64)
+    );
+    let win_percentage = sfl_rating.get_team_win_percentage(&records[0][0]).unwrap();
+    assert!(win_percentage > 0.5);
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::interval::{get_standard_error, get_wilson_interval, Z_95};
+use sfl_lib::sfl::SflStage;
+use sfl_lib::SflSimulation;
+
+#[test]
+fn wilson_interval() {
+    let (low, high) = get_wilson_interval(5, 10, Z_95);
+    assert!((low - 0.2366).abs() < 0.0001);
+    assert!((high - 0.7634).abs() < 0.0001);
+    // 一度も起きていなくても幅は0にならない
+    let (low, high) = get_wilson_interval(0, 100, Z_95);
+    assert_eq!(low, 0_f64);
+    assert!(high > 0.03 && high < 0.04);
+    assert_eq!(get_wilson_interval(0, 0, Z_95), (0_f64, 1_f64));
+
+    assert!((get_standard_error(5, 100) - 0.0218).abs() < 0.0001);
+    assert!(get_standard_error(0, 0).is_nan());
+}
+
+#[test]
+fn simulated_result_intervals() {
+    let simulation = SflSimulation::new(true);
+    // 同梱の結果は10000回分
+    let interval = simulation.get_place_interval(&SflStage::division(0), 0, 0);
+    assert_eq!(interval[0], 0.4448);
+    assert!(interval[1] > 0.0049 && interval[1] < 0.0050);
+    assert!(interval[2] < 0.4448 && 0.4448 < interval[3]);
+    let playoff = simulation.get_playoff_interval(0);
+    assert!((playoff[0] - 0.9438).abs() < 1e-9);
+    let width = simulation.get_max_playoff_interval_width();
+    assert!(width > 0_f64 && width < 0.02);
+}
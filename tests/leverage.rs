@@ -0,0 +1,49 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::leverage::get_swing;
+use sfl_lib::SflSimulation;
+
+const FOUR_TEAMS_JSON: &str = include_str!("fixtures/four_teams.json");
+
+#[test]
+fn swing_of_conditional_probabilities() {
+    // 平均0.5から 0.25 * 0.3 + 0.75 * 0.1
+    let (probability, swing) = get_swing(vec![(0.25, 0.2), (0.75, 0.6)].into_iter());
+    assert!((probability - 0.5).abs() < 1e-12);
+    assert!((swing - 0.15).abs() < 1e-12);
+    assert_eq!(get_swing(std::iter::empty::<(f64, f64)>()), (0.0, 0.0));
+
+    // ほとんど起きないスコアで大きく動く試合より、五分の試合で動く方が大きい
+    let (_, unlikely) = get_swing(vec![(0.001, 0.0), (0.999, 1.0)].into_iter());
+    let (_, close) = get_swing(vec![(0.5, 0.4), (0.5, 0.6)].into_iter());
+    assert!(unlikely < close, "{} {}", unlikely, close);
+}
+
+#[test]
+fn rank_remaining_matches() {
+    let mut simulation = SflSimulation::from_season_json(FOUR_TEAMS_JSON).unwrap();
+    simulation.count = 300;
+    simulation.option.exact_state_limit = 0;
+    let leverages = simulation.get_match_leverages().unwrap();
+    assert_eq!(leverages.len(), 6);
+    assert!(leverages
+        .windows(2)
+        .all(|pair| pair[0].leverage >= pair[1].leverage));
+    for leverage in leverages.iter() {
+        let teams = leverage.get_teams();
+        assert_eq!(teams.len(), 4);
+        let records = simulation.get_match_records(leverage.match_index);
+        let sfl_match = &records[0].sfl_match;
+        // 試合をする2チームの確率はスコアで動く
+        for team_index in [sfl_match.team(), sfl_match.opponent_team()] {
+            let team = teams
+                .iter()
+                .find(|team| team.team_index == team_index)
+                .unwrap();
+            assert!(team.playoff_swing > 0.0);
+            assert!((0.0..=1.0).contains(&team.playoff_probability));
+        }
+    }
+    // 元のシミュレーション結果はそのまま
+    assert_eq!(simulation.get_used_count(), 0);
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::model::{get_match_score_distribution, WinProbabilityModel};
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{SflRecord, SflStage};
+use sfl_lib::{SflRating, SflSimulation};
+
+// 大将戦だけAWAYが必ず勝つモデル
+struct LeaderModel {
+    fitted_records: usize,
+}
+
+impl WinProbabilityModel for LeaderModel {
+    fn fit(&mut self, _: &Season, _: &SflStage, sfl_records: &[Vec<SflRecord>]) {
+        self.fitted_records = sfl_records.iter().flatten().count();
+    }
+    fn get_team_win_percentage(&self, record: &SflRecord) -> Option<f64> {
+        if self.fitted_records == 0 {
+            return None;
+        }
+        if record.game_type.is_leader() {
+            Some(1_f64)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn simulation_uses_win_probability_model() {
+    let mut simulation = SflSimulation::new(false);
+    let records = simulation.get_match_records(0);
+    // 実績がなければレーティングは全員1500
+    assert_eq!(simulation.get_team_win_percentage(&records[0]), Some(0.5));
+
+    simulation.set_win_probability_model(Box::new(LeaderModel { fitted_records: 0 }));
+    assert_eq!(simulation.get_team_win_percentage(&records[0]), None);
+    assert_eq!(simulation.get_team_win_percentage(&records[6]), Some(1_f64));
+
+    simulation.clear_win_probability_model();
+    assert_eq!(simulation.get_team_win_percentage(&records[6]), Some(0.5));
+}
+
+#[test]
+fn sfl_rating_as_model() {
+    let season = Season::jp2024();
+    let all_division = SflStage::all_division();
+    let mut records = season.get_initial_records(&all_division);
+    for record in records[0].iter_mut().take(2) {
+        record.win_flag = true;
+        record.is_valid = true;
+        record.is_prediction = false;
+    }
+    let mut model: Box<dyn WinProbabilityModel> = Box::new(SflRating::new());
+    assert_eq!(model.get_team_win_percentage(&records[0][0]), None);
+    model.fit(&season, &all_division, &records);
+    assert!(model.get_team_win_percentage(&records[0][0]).unwrap() > 0.5);
+}
+
+#[test]
+fn match_score_distribution_from_model() {
+    let season = Season::jp2024();
+    let sfl_match = season.get_matches(&SflStage::all_division())[0].to_owned();
+    let mut model = LeaderModel { fitted_records: 0 };
+    // 勝率がわからなければ五分で、裏返したスコアも同じ確率
+    let distribution = get_match_score_distribution(&season, &sfl_match, &model);
+    let total: f64 = distribution.iter().map(|(_, p)| p).sum();
+    assert!((total - 1_f64).abs() < 1e-12);
+    assert_eq!(distribution[0].0.team_point, 40);
+    assert!((distribution[0].1 - distribution.last().unwrap().1).abs() < 1e-12);
+
+    // 大将戦と延長戦を必ず取るなら 40-0, 30-10, 25-20 だけ
+    let all_division = SflStage::all_division();
+    model.fit(
+        &season,
+        &all_division,
+        &season.get_initial_records(&all_division),
+    );
+    let distribution: Vec<(u32, u32, f64)> =
+        get_match_score_distribution(&season, &sfl_match, &model)
+            .iter()
+            .map(|(score, p)| (score.team_point, score.opponent_team_point, *p))
+            .collect();
+    assert_eq!(
+        distribution,
+        vec![(40, 0, 0.25), (30, 10, 0.5), (25, 20, 0.25)]
+    );
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "serialize")]
+
+use rand::prelude::*;
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{SflPlayer, SflRatingSetting, SflStage};
+use sfl_lib::SflRating;
+
+const ROSTER_SEASON_JSON: &str = r#"{
+    "id": "roster",
+    "name": "roster season",
+    "divisions": [
+        {"name": "East", "teams": ["AAA", "BBB"], "matches": [
+            {"date": "01/01", "section": 1, "branch": 1, "away": "AAA", "home": "BBB"},
+            {"date": "01/08", "section": 2, "branch": 1, "away": "BBB", "home": "AAA"}
+        ]}
+    ],
+    "rosters": {
+        "BBB": [
+            {"name": "b1", "characters": ["Ryu"]},
+            {"name": "b2"},
+            {"name": "b3"},
+            {"name": "b4"}
+        ],
+        "AAA": [
+            {"name": "a1", "characters": ["Ken", "Luke"]},
+            {"name": "a2"},
+            {"name": "a3"}
+        ]
+    }
+}"#;
+
+#[test]
+fn season_rosters() {
+    let season = Season::from_json(ROSTER_SEASON_JSON).unwrap();
+    // チームの番号順にプレイヤーの番号を振る
+    assert_eq!(season.players.len(), 7);
+    let aaa = season.find_team("AAA").unwrap();
+    let bbb = season.find_team("BBB").unwrap();
+    assert_eq!(
+        season.get_roster(&aaa),
+        (0..3).map(SflPlayer::new).collect::<Vec<SflPlayer>>()
+    );
+    assert_eq!(season.get_roster(&bbb).len(), 4);
+    let a1 = season.find_player("a1").unwrap();
+    assert_eq!(season.get_player(&a1).team, aaa);
+    assert_eq!(season.get_player(&a1).characters, vec!["Ken", "Luke"]);
+    assert!(season.get_roster(&aaa).contains(&a1));
+    assert!(Season::jp2024().players.is_empty());
+
+    let unknown = ROSTER_SEASON_JSON.replace("\"BBB\": [", "\"XXX\": [");
+    assert!(Season::from_json(&unknown).is_err());
+    let duplicate = ROSTER_SEASON_JSON.replace("\"b2\"", "\"a2\"");
+    assert!(Season::from_json(&duplicate).is_err());
+}
+
+#[test]
+fn sample_lineup_by_leg() {
+    let season = Season::from_json(ROSTER_SEASON_JSON).unwrap();
+    let mut rng: StdRng = SeedableRng::seed_from_u64(1);
+    let mut records = season.get_initial_records(&SflStage::all_division())[0].to_owned();
+    // 先鋒戦はオーダー発表済み
+    let b4 = season.find_player("b4").unwrap();
+    records[1].opponent_player = Some(b4);
+    season.sample_lineup(&mut records, &mut rng);
+    assert!(records
+        .iter()
+        .all(|r| r.player.is_some() && r.opponent_player.is_some()));
+    assert!(records[0..3].iter().all(|r| r.opponent_player == Some(b4)));
+    // 先鋒、中堅、大将は別のプレイヤー
+    let players: Vec<SflPlayer> = [0, 3, 6]
+        .iter()
+        .map(|&i| records[i].player.unwrap())
+        .collect();
+    assert!(players[0] != players[1] && players[1] != players[2] && players[0] != players[2]);
+    assert!(records[6..11].iter().all(|r| r.player == Some(players[2])));
+}
+
+#[test]
+fn player_ratings() {
+    let season = Season::from_json(ROSTER_SEASON_JSON).unwrap();
+    let a1 = season.find_player("a1").unwrap();
+    let b1 = season.find_player("b1").unwrap();
+    let mut records = season.get_initial_records(&SflStage::all_division());
+    for record in records[0].iter_mut().take(2) {
+        record.win_flag = true;
+        record.is_valid = true;
+        record.is_prediction = false;
+        record.player = Some(a1);
+        record.opponent_player = Some(b1);
+    }
+    // プレイヤーがわからないセットは無視する
+    records[0][3].is_valid = true;
+    records[0][3].is_prediction = false;
+
+    let mut sfl_rating = SflRating::with_setting(SflRatingSetting::Player);
+    sfl_rating.calc_ratings(&season, &SflStage::all_division(), &records);
+    assert!(sfl_rating.get_player_rating(&a1).unwrap() > 1500_f64);
+    assert!(sfl_rating.get_player_rating(&b1).unwrap() < 1500_f64);
+    let a2 = season.find_player("a2").unwrap();
+    assert_eq!(sfl_rating.get_player_rating(&a2), Some(1500_f64));
+    assert!(sfl_rating.get_team_win_percentage(&records[0][0]).unwrap() > 0.5);
+    assert_eq!(sfl_rating.get_team_win_percentage(&records[0][3]), None);
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::prior::{RatingPrior, RatingSnapshot};
+use sfl_lib::season::Season;
+use sfl_lib::sfl::SflRatingModel::Glicko2;
+use sfl_lib::sfl::SflRatingSetting::{HomeAwayGameType, TeamOnly};
+use sfl_lib::sfl::{SflRatingSetting, SflRecord, SflStage};
+use sfl_lib::SflRating;
+
+const NEXT_SEASON_JSON: &str = r#"{
+    "id": "next",
+    "name": "next season",
+    "divisions": [
+        {"name": "East", "teams": ["NEW", "DFM"], "matches": [
+            {"date": "01/01", "section": 1, "branch": 1, "away": "NEW", "home": "DFM"}
+        ]}
+    ]
+}"#;
+
+// 第1節第1試合（DFM対OJA）をDFMが全部取った実績
+fn last_season_rating(setting: SflRatingSetting) -> (Season, SflRating) {
+    let season = Season::jp2024();
+    let all_division = SflStage::all_division();
+    let mut records: Vec<Vec<SflRecord>> = season.get_initial_records(&all_division);
+    for record in records[0].iter_mut() {
+        record.win_flag = true;
+        record.is_valid = true;
+        record.is_prediction = false;
+    }
+    let mut sfl_rating = SflRating::with_setting(setting);
+    sfl_rating.calc_ratings(&season, &all_division, &records);
+    (season, sfl_rating)
+}
+
+#[test]
+fn rating_snapshot_round_trip() {
+    let (season, sfl_rating) = last_season_rating(HomeAwayGameType);
+    let snapshot = sfl_rating.get_snapshot(&season);
+    assert_eq!(snapshot.season_id, "jp2024");
+    assert_eq!(snapshot.teams.len(), 12);
+    let restored = RatingSnapshot::from_json(&snapshot.to_json()).unwrap();
+    assert_eq!(restored.setting, HomeAwayGameType);
+    let dfm = restored.teams.iter().find(|t| t.code == "DFM").unwrap();
+    assert!(dfm.ratings[0] > 1500_f64 && dfm.ratings[2] > 1500_f64);
+    assert_eq!(dfm.ratings[1], 1500_f64);
+    assert!(RatingSnapshot::from_json("{}").is_err());
+}
+
+#[test]
+fn prior_seeds_next_season() {
+    let (last_season, sfl_rating) = last_season_rating(HomeAwayGameType);
+    let snapshot = sfl_rating.get_snapshot(&last_season);
+    let last_dfm = sfl_rating
+        .get_rating(
+            last_season.find_team("DFM").unwrap().get_index(),
+            false,
+            false,
+        )
+        .unwrap();
+
+    let season = Season::from_json(NEXT_SEASON_JSON).unwrap();
+    let all_division = SflStage::all_division();
+    let records = season.get_initial_records(&all_division);
+    let dfm = season.find_team("DFM").unwrap().get_index();
+    let new = season.find_team("NEW").unwrap().get_index();
+    let rating = |regression: f64| {
+        let mut sfl_rating = SflRating::with_setting(HomeAwayGameType);
+        sfl_rating.set_prior(Some(RatingPrior::new(snapshot.to_owned(), regression)));
+        sfl_rating.calc_ratings(&season, &all_division, &records);
+        sfl_rating
+    };
+    assert_eq!(rating(0_f64).get_rating(dfm, false, false), Some(last_dfm));
+    let half = rating(0.5_f64).get_rating(dfm, false, false).unwrap();
+    assert!((half - (1500_f64 + (last_dfm - 1500_f64) / 2_f64)).abs() < 1e-9);
+    assert_eq!(rating(1_f64).get_rating(dfm, false, false), Some(1500_f64));
+    // 前シーズンにいないチームは1500から
+    assert_eq!(rating(0_f64).get_rating(new, false, false), Some(1500_f64));
+
+    // 設定が違ってもAWAYの先鋒中堅の枠を引き継ぐ
+    let mut team_only = SflRating::with_model(TeamOnly, Glicko2);
+    team_only.set_prior(Some(RatingPrior::new(snapshot, 0_f64)));
+    team_only.calc_ratings(&season, &all_division, &records);
+    assert_eq!(team_only.get_rating(dfm, true, true), Some(last_dfm));
+    assert_eq!(
+        team_only.get_rating_deviation(dfm, false, false),
+        Some(350_f64)
+    );
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::season::Season;
+use sfl_lib::sfl::SflRatingSetting::{GameType, HomeAway, HomeAwayGameType, TeamOnly};
+use sfl_lib::sfl::{
+    create_key_function_and_init_rating_map, get_rating_index, SflRatingSetting, SflRecord,
+    SflStage,
+};
+use sfl_lib::{SflRating, SflSimulation};
+
+// 第1節第1試合の先鋒戦をAWAYが2-0で取った状態
+fn van_records(season: &Season) -> Vec<Vec<SflRecord>> {
+    let mut records = season.get_initial_records(&SflStage::all_division());
+    for record in records[0].iter_mut().take(2) {
+        record.win_flag = true;
+        record.is_valid = true;
+        record.is_prediction = false;
+    }
+    records
+}
+
+fn calc(season: &Season, setting: SflRatingSetting) -> SflRating {
+    let mut sfl_rating = SflRating::with_setting(setting);
+    sfl_rating.calc_ratings(season, &SflStage::all_division(), &van_records(season));
+    sfl_rating
+}
+
+#[test]
+fn rating_settings_split_ratings() {
+    let season = Season::jp2024();
+    let sfl_match = &season.get_matches(&SflStage::all_division())[0];
+    let away = sfl_match.team.get_index();
+    let rating = |sfl_rating: &SflRating, is_home: bool, is_leader: bool| {
+        sfl_rating.get_rating(away, is_home, is_leader).unwrap()
+    };
+
+    let team_only = calc(&season, TeamOnly);
+    assert!(rating(&team_only, false, false) > 1500_f64);
+    assert_eq!(
+        rating(&team_only, true, true),
+        rating(&team_only, false, false)
+    );
+
+    let home_away = calc(&season, HomeAway);
+    assert!(rating(&home_away, false, false) > 1500_f64);
+    assert_eq!(
+        rating(&home_away, false, true),
+        rating(&home_away, false, false)
+    );
+    assert_eq!(rating(&home_away, true, false), 1500_f64);
+
+    let game_type = calc(&season, GameType);
+    assert!(rating(&game_type, true, false) > 1500_f64);
+    assert_eq!(rating(&game_type, false, true), 1500_f64);
+
+    let home_away_game_type = calc(&season, HomeAwayGameType);
+    assert!(rating(&home_away_game_type, false, false) > 1500_f64);
+    assert_eq!(rating(&home_away_game_type, true, false), 1500_f64);
+    assert_eq!(rating(&home_away_game_type, false, true), 1500_f64);
+
+    // 2セット分の変動はどの設定でも同じ
+    assert_eq!(
+        rating(&team_only, false, false),
+        rating(&game_type, false, false)
+    );
+    let record = &van_records(&season)[0][0];
+    assert!(team_only.get_team_win_percentage(record).unwrap() > 0.5);
+}
+
+#[test]
+fn simulation_rating_setting() {
+    let mut simulation = SflSimulation::new(false);
+    assert_eq!(simulation.get_rating_setting(), HomeAwayGameType);
+    simulation.set_rating_setting(TeamOnly);
+    assert_eq!(simulation.get_rating_setting(), TeamOnly);
+    assert_eq!(simulation.get_rating(0, true, true), 1500_f64);
+    // ロースターがなければプレイヤー単位でも1500
+    simulation.set_rating_setting(SflRatingSetting::Player);
+    assert_eq!(simulation.get_rating(0, false, false), 1500_f64);
+}
+
+#[test]
+fn player_setting_has_no_team_slot() {
+    let season = Season::jp2024();
+    let teams = season.get_teams(&SflStage::all_division());
+    assert_eq!(get_rating_index(HomeAwayGameType, 2, true, true), Some(11));
+    assert_eq!(
+        get_rating_index(SflRatingSetting::Player, 2, true, true),
+        None
+    );
+    let (key_function, rating_map) =
+        create_key_function_and_init_rating_map(TeamOnly, teams.to_owned()).unwrap();
+    assert_eq!(rating_map.len(), 12);
+    let record = &van_records(&season)[0][0];
+    assert!(rating_map.contains_key(&key_function(record).0));
+    assert!(create_key_function_and_init_rating_map(SflRatingSetting::Player, teams).is_none());
+
+    // プレイヤー単位でもチームのレーティングを聞いてよい
+    let player = calc(&season, SflRatingSetting::Player);
+    assert_eq!(player.get_rating(0, false, false), None);
+    assert_eq!(player.get_rating_deviation(0, false, false), None);
+}
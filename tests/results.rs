@@ -0,0 +1,260 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::format::SetError;
+use sfl_lib::results::{import_results, parse_csv, parse_json, ResultsError};
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{GameType, SflStage};
+use sfl_lib::SflSimulation;
+
+const RESULTS_CSV: &str = "
+section,branch,away,home,game,winner
+# 先鋒、中堅をAWAY、大将をHOMEが取って20-20。延長戦はAWAY
+1,1,DFM,OJA,VAN,DFM
+1,1,DFM,OJA,VAN,OJA
+1,1,DFM,OJA,VAN,DFM
+1,1,DFM,OJA,MID,DFM
+1,1,DFM,OJA,MID,DFM
+1,1,DFM,OJA,GENERAL,OJA
+1,1,DFM,OJA,GENERAL,OJA
+1,1,DFM,OJA,GENERAL,DFM
+1,1,DFM,OJA,GENERAL,OJA
+1,1,DFM,OJA,EXTRA,DFM
+# 途中まで
+1,2,G8S,SNB,,SNB
+";
+
+const RESULTS_JSON: &str = r#"[
+    {"section": 1, "branch": 1, "away": "DFM", "home": "OJA", "sets": [
+        "DFM", "OJA", "DFM", "DFM", "DFM", "OJA", "OJA", "DFM", "OJA", {"winner": "DFM", "game": "EXTRA"}
+    ]},
+    {"section": 1, "away": "G8S", "home": "SNB", "sets": ["SNB"]}
+]"#;
+
+#[test]
+fn import_csv_and_json() {
+    let season = Season::jp2024();
+    let stage = SflStage::all_division();
+    let from_csv = import_results(&season, &stage, &parse_csv(RESULTS_CSV).unwrap()).unwrap();
+    let from_json = import_results(&season, &stage, &parse_json(RESULTS_JSON).unwrap()).unwrap();
+    let format = season.get_format(&stage);
+    for records in [&from_csv, &from_json] {
+        let score = format.evaluate(&records[0]);
+        assert_eq!((score.team_point, score.opponent_team_point), (25, 20));
+        assert!(records[0].iter().all(|record| !record.is_prediction));
+        // 2-0で決着した中堅の3セット目は行われていない
+        assert!(!records[0][5].is_valid);
+        assert!(records[0][11].is_valid && records[0][11].win_flag);
+        // 途中の試合は残りが予想
+        assert!(!records[1][0].is_prediction && !records[1][0].win_flag);
+        assert!(records[1][1..].iter().all(|record| record.is_prediction));
+        assert!(records[2].iter().all(|record| record.is_prediction));
+    }
+
+    // 位置で指定する set_match_result と同じ状態になる
+    let mut simulation = SflSimulation::new(false);
+    simulation.import_results_csv(RESULTS_CSV).unwrap();
+    let mut positional = SflSimulation::new(false);
+    positional
+        .set_match_result(
+            0,
+            &[
+                Some(true),
+                Some(false),
+                Some(true),
+                Some(true),
+                Some(true),
+                None,
+                Some(false),
+                Some(false),
+                Some(true),
+                Some(false),
+                None,
+                Some(true),
+            ],
+        )
+        .unwrap();
+    positional.set_match_result(1, &[Some(false)]).unwrap();
+    assert_eq!(
+        simulation.sfl_stats().get_points(),
+        positional.sfl_stats().get_points()
+    );
+    assert_eq!(
+        simulation.sfl_stats().get_battles(),
+        positional.sfl_stats().get_battles()
+    );
+}
+
+#[test]
+fn import_players() {
+    let season = Season::from_json(
+        r#"{
+        "id": "players",
+        "name": "players",
+        "divisions": [
+            {"name": "East", "teams": ["AAA", "BBB"], "matches": [
+                {"date": "01/01", "section": 1, "branch": 1, "away": "AAA", "home": "BBB"}
+            ]}
+        ],
+        "rosters": {"AAA": [{"name": "a1"}], "BBB": [{"name": "b1"}]}
+    }"#,
+    )
+    .unwrap();
+    let stage = SflStage::all_division();
+    let csv = "section,away,home,winner,away_player,home_player,away_character,home_character
+1,AAA,BBB,BBB,a1,b1,Ken,Ryu";
+    let records = import_results(&season, &stage, &parse_csv(csv).unwrap()).unwrap();
+    assert_eq!(records[0][0].player, season.find_player("a1"));
+    assert_eq!(records[0][0].opponent_player, season.find_player("b1"));
+    assert_eq!(records[0][0].opponent_character, Some("Ryu".to_string()));
+
+    let wrong_team = csv.replace("a1,b1", "b1,a1");
+    assert!(matches!(
+        import_results(&season, &stage, &parse_csv(&wrong_team).unwrap()),
+        Err(ResultsError::UnknownPlayer { set: 1, .. })
+    ));
+}
+
+#[test]
+fn invalid_results_are_rejected() {
+    let season = Season::jp2024();
+    let stage = SflStage::all_division();
+    let import = |json: &str| import_results(&season, &stage, &parse_json(json).unwrap());
+    let set_error = |json: &str| match import(json) {
+        Err(ResultsError::InvalidSet { error, .. }) => error,
+        other => panic!("{:?}", other),
+    };
+
+    // 40-0 の後の延長戦
+    let sweep = r#"[{"section": 1, "away": "DFM", "home": "OJA", "sets": ["DFM", "DFM", "DFM", "DFM", "DFM", "DFM", "DFM", "DFM"]}]"#;
+    assert_eq!(
+        set_error(sweep),
+        SetError::NotTied {
+            set: 8,
+            game_type: GameType::EXTRA,
+            team_point: 40,
+            opponent_team_point: 0
+        }
+    );
+    // 2-0 で決着した先鋒戦の3セット目
+    let third = r#"[{"section": 1, "away": "DFM", "home": "OJA", "sets": ["DFM", "DFM", {"winner": "OJA", "game": "VAN"}]}]"#;
+    assert_eq!(
+        set_error(third),
+        SetError::LegDecided {
+            set: 3,
+            game_type: GameType::VAN
+        }
+    );
+    let skipped = r#"[{"section": 1, "away": "DFM", "home": "OJA", "sets": ["DFM", {"winner": "DFM", "game": "GENERAL"}]}]"#;
+    assert_eq!(
+        set_error(skipped),
+        SetError::UnexpectedGame {
+            set: 2,
+            expected: GameType::VAN,
+            found: GameType::GENERAL
+        }
+    );
+
+    assert!(matches!(
+        import(r#"[{"section": 1, "branch": 2, "away": "DFM", "home": "OJA", "sets": []}]"#),
+        Err(ResultsError::UnknownMatch { .. })
+    ));
+    assert!(matches!(
+        import(r#"[{"section": 1, "away": "OJA", "home": "DFM", "sets": []}]"#),
+        Err(ResultsError::UnknownMatch { .. })
+    ));
+    assert!(matches!(
+        import(r#"[{"section": 1, "away": "XXX", "home": "DFM", "sets": []}]"#),
+        Err(ResultsError::UnknownTeam(_))
+    ));
+    assert!(matches!(
+        import(r#"[{"section": 1, "away": "DFM", "home": "OJA", "sets": ["G8S"]}]"#),
+        Err(ResultsError::UnknownWinner { set: 1, .. })
+    ));
+    assert!(matches!(
+        import(
+            r#"[{"section": 1, "away": "DFM", "home": "OJA", "sets": []},
+                {"section": 1, "away": "DFM", "home": "OJA", "sets": []}]"#
+        ),
+        Err(ResultsError::DuplicateMatch { .. })
+    ));
+    assert!(matches!(
+        parse_csv("section,away,home\n1,DFM,OJA"),
+        Err(ResultsError::Parse { line: 1, .. })
+    ));
+    assert!(matches!(
+        parse_csv("section,away,home,winner\nx,DFM,OJA,DFM"),
+        Err(ResultsError::Parse { line: 2, .. })
+    ));
+    // " で囲んだ値には , や " を書ける
+    let quoted = parse_csv(
+        "section,away,home,winner,away_character\n1,DFM,\"OJA\",DFM,\"Ken, \"\"Shoto\"\"\"",
+    )
+    .unwrap();
+    assert_eq!(quoted[0].home, "OJA");
+    assert_eq!(
+        quoted[0].sets[0].away_character.as_deref(),
+        Some("Ken, \"Shoto\"")
+    );
+    assert!(matches!(
+        parse_csv("section,away,home,winner\n1,DFM,\"OJA,DFM"),
+        Err(ResultsError::Parse { line: 2, .. })
+    ));
+    assert!(matches!(
+        parse_csv("section,away,home,winner\n1,DFM,O\"JA,DFM"),
+        Err(ResultsError::Parse { line: 2, .. })
+    ));
+
+    // エラーの場合は結果を変えない
+    let mut simulation = SflSimulation::new(false);
+    simulation.import_results_csv(RESULTS_CSV).unwrap();
+    let error = simulation.import_results_json(sweep).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "section 1 DFM vs OJA: set 8: EXTRA is played only on a tie, but the score is 40-0"
+    );
+    assert!(!simulation.get_match_records(0)[11].is_prediction);
+}
+
+#[test]
+fn set_match_result_is_validated() {
+    let mut simulation = SflSimulation::new(false);
+    let (t, f) = (Some(true), Some(false));
+    simulation.set_match_result(0, &[t, f, t, f, f]).unwrap();
+    let records = simulation.get_match_records(0);
+
+    // 2-0で決着した中堅の3セット目
+    let error = simulation
+        .set_match_result(0, &[t, f, t, f, f, t])
+        .unwrap_err();
+    assert_eq!(
+        error,
+        ResultsError::InvalidSet {
+            section: 1,
+            away: "DFM".to_string(),
+            home: "OJA".to_string(),
+            error: SetError::LegDecided {
+                set: 6,
+                game_type: GameType::MID
+            }
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "section 1 DFM vs OJA: set 6: MID is already decided"
+    );
+    assert_eq!(
+        simulation.set_match_result(60, &[]),
+        Err(ResultsError::UnknownMatchIndex(60))
+    );
+    // エラーの場合は前の結果のまま
+    let unchanged = simulation.get_match_records(0);
+    assert!(records.iter().zip(unchanged.iter()).all(|(a, b)| (
+        a.win_flag,
+        a.is_valid,
+        a.is_prediction
+    ) == (
+        b.win_flag,
+        b.is_valid,
+        b.is_prediction
+    )));
+}
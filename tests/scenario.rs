@@ -0,0 +1,128 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::format::SetError;
+use sfl_lib::scenario::{ScenarioError, SflScenario};
+use sfl_lib::sfl::{GameType, SflStage};
+use sfl_lib::SflSimulation;
+
+const FOUR_TEAMS_JSON: &str = include_str!("fixtures/four_teams.json");
+
+// 最初の5試合はAWAYが先鋒、中堅、大将をストレートで取り、最後の試合はスコアだけ決める
+fn last_match_scenario(name: &str, team_point: u32, opponent_team_point: u32) -> SflScenario {
+    let mut scenario = SflScenario::new(name);
+    for match_index in 0..5 {
+        scenario.force_match_sets(match_index, &[true; 7]);
+    }
+    scenario.force_match_score(5, team_point, opponent_team_point);
+    scenario
+}
+
+#[test]
+fn scenarios_share_baseline() {
+    let mut simulation = SflSimulation::from_season_json(FOUR_TEAMS_JSON).unwrap();
+    let division = SflStage::division(0);
+    let team_index = |code: &str| {
+        simulation
+            .get_team_names(&division)
+            .iter()
+            .position(|name| name == code)
+            .unwrap()
+    };
+    let (bbb, ccc) = (team_index("BBB"), team_index("CCC"));
+    assert_eq!(simulation.find_match_index("CCC", "BBB"), Some(5));
+
+    let bbb_wins = simulation
+        .simulate_scenario(&last_match_scenario("bbb wins", 40, 0))
+        .unwrap();
+    let ccc_wins = simulation
+        .simulate_scenario(&last_match_scenario("ccc wins", 0, 40))
+        .unwrap();
+    // 残りは最後の試合の中身だけなので全列挙になり、順位はスコアで決まる
+    assert!(bbb_wins.is_exact() && ccc_wins.is_exact());
+    assert_eq!(bbb_wins.get_name(), "bbb wins");
+    assert_eq!(
+        bbb_wins.get_place_probability(&division, bbb),
+        vec![0.0, 1.0, 0.0, 0.0]
+    );
+    assert_eq!(
+        ccc_wins.get_place_probability(&division, bbb),
+        vec![0.0, 0.0, 1.0, 0.0]
+    );
+    assert_eq!(ccc_wins.get_playoff_probability(ccc), 1.0);
+
+    // 実際の結果とシミュレーション結果は書き換えない
+    assert!(simulation
+        .get_match_records(0)
+        .iter()
+        .all(|record| record.is_prediction));
+    assert_eq!(simulation.get_used_count(), 0);
+    assert_eq!(simulation.find_match_index("AAA", "BBB"), Some(0));
+}
+
+#[test]
+fn sampled_scenario_is_reproducible() {
+    let mut simulation = SflSimulation::from_season_json(FOUR_TEAMS_JSON).unwrap();
+    simulation.count = 2000;
+    simulation.option.exact_state_limit = 0;
+    let all_division = SflStage::all_division();
+    let mut win = SflScenario::new("AAA 40-0");
+    win.force_match_score(0, 40, 0);
+    let mut lose = SflScenario::new("AAA 0-40");
+    lose.force_match_score(0, 0, 40);
+
+    let first = simulation.simulate_scenario(&win).unwrap();
+    let second = simulation.simulate_scenario(&win).unwrap();
+    let lost = simulation.simulate_scenario(&lose).unwrap();
+    assert!(!first.is_exact());
+    assert_eq!(first.get_used_count(), 2000);
+    assert_eq!(
+        first.get_place_count(&all_division, 0),
+        second.get_place_count(&all_division, 0)
+    );
+    assert!(first.get_playoff_probability(0) > lost.get_playoff_probability(0));
+}
+
+#[test]
+fn invalid_scenario_is_error() {
+    let mut simulation = SflSimulation::from_season_json(FOUR_TEAMS_JSON).unwrap();
+    // ディビジョンの試合は最大40点
+    let mut scenario = SflScenario::new("impossible score");
+    scenario.force_match_score(0, 45, 0);
+    assert!(simulation.simulate_scenario(&scenario).is_err());
+    // 全セットを取った試合を 0-40 にはできない
+    let mut scenario = last_match_scenario("decided", 40, 0);
+    scenario.force_match_score(0, 0, 40);
+    assert!(simulation.simulate_scenario(&scenario).is_err());
+
+    let mut scenario = SflScenario::new("out of range");
+    scenario.force_set(6, 0, true);
+    assert_eq!(
+        simulation.simulate_scenario(&scenario).unwrap_err(),
+        ScenarioError::UnknownMatchIndex(6)
+    );
+    scenario.clear();
+    scenario.force_set(0, 12, true);
+    assert!(simulation.simulate_scenario(&scenario).is_err());
+
+    // 試合形式に合わないセットは set_match_result と同じエラー
+    let mut scenario = SflScenario::new("after sweep");
+    scenario.force_match_sets(0, &[true; 8]);
+    let error = simulation.simulate_scenario(&scenario).unwrap_err();
+    assert!(error.to_string().starts_with("match 0: set 8"), "{}", error);
+    scenario.clear();
+    scenario.force_match_sets(0, &[true; 7]);
+    scenario.force_set(0, 2, false);
+    assert_eq!(
+        simulation.simulate_scenario(&scenario).unwrap_err(),
+        ScenarioError::InvalidSet {
+            match_index: 0,
+            error: SetError::LegDecided {
+                set: 3,
+                game_type: GameType::VAN,
+            },
+        }
+    );
+    scenario.clear();
+    scenario.force_set(0, 1, true);
+    assert!(simulation.simulate_scenario(&scenario).is_err());
+}
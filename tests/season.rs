@@ -0,0 +1,141 @@
+#![cfg(feature = "serialize")]
+
+use sfl_lib::season::{Season, SeasonError};
+use sfl_lib::sfl::SflStage;
+
+const TEST_SEASON_JSON: &str = r#"{
+    "id": "test",
+    "name": "test season",
+    "divisions": [
+        {"name": "East", "teams": ["AAA", "BBB", "CCC"], "matches": [
+            {"date": "01/01", "section": 1, "branch": 1, "away": "AAA", "home": "BBB"},
+            {"date": "01/08", "section": 2, "branch": 1, "away": "CCC", "home": "AAA"}
+        ]},
+        {"name": "Central", "teams": ["DDD", "EEE"], "matches": [
+            {"date": "01/02", "section": 1, "branch": 1, "away": "EEE", "home": "DDD"}
+        ]},
+        {"name": "West", "teams": ["FFF", "GGG"], "matches": [
+            {"date": "01/03", "section": 1, "branch": 1, "away": "FFF", "home": "GGG"}
+        ]}
+    ]
+}"#;
+
+#[test]
+fn bundled_jp2024_season() {
+    let season = Season::bundled("jp2024").unwrap();
+    let all_division = SflStage::all_division();
+    assert_eq!(season.get_teams(&SflStage::division(0)).len(), 6);
+    assert_eq!(season.get_teams(&SflStage::division(1)).len(), 6);
+    assert_eq!(season.get_teams(&all_division).len(), 12);
+    assert_eq!(season.get_max_team_index(&all_division), 11);
+
+    let team = |code: &str| season.find_team(code).unwrap();
+    let matches = season.get_matches(&all_division);
+    assert_eq!(matches.len(), 60);
+    // 節ごとにディビジョンS、ディビジョンFの順で並ぶ
+    assert_eq!(
+        (matches[0].team, matches[0].opponent_team),
+        (team("DFM"), team("OJA"))
+    );
+    assert_eq!(
+        (matches[3].team, matches[3].opponent_team),
+        (team("RC"), team("IXA"))
+    );
+    assert_eq!(matches[3].date_expression(), "08/20");
+    assert_eq!((matches[59].section, matches[59].branch), (10, 3));
+    assert_eq!(season.get_initial_records(&all_division)[0].len(), 12);
+    assert!(Season::bundled("jp2099").is_none());
+}
+
+#[test]
+fn season_from_json() {
+    let season = Season::from_json(TEST_SEASON_JSON).unwrap();
+    assert_eq!(season.teams.len(), 7);
+    assert_eq!(season.get_division_stages().len(), 3);
+    let teams = season.get_teams(&SflStage::division(2));
+    assert_eq!(season.get_team_code(&teams[0]), "FFF");
+
+    let matches = season.get_matches(&SflStage::all_division());
+    let codes: Vec<&str> = matches
+        .iter()
+        .map(|m| season.get_team_code(&m.team))
+        .collect();
+    assert_eq!(codes, vec!["AAA", "EEE", "FFF", "CCC"]);
+
+    let unknown = TEST_SEASON_JSON.replace("\"home\": \"BBB\"", "\"home\": \"XXX\"");
+    assert!(Season::from_json(&unknown).is_err());
+    let outside_division = TEST_SEASON_JSON.replace("\"away\": \"EEE\"", "\"away\": \"AAA\"");
+    assert!(Season::from_json(&outside_division).is_err());
+    let duplicate = TEST_SEASON_JSON.replace("\"DDD\", \"EEE\"", "\"DDD\", \"AAA\"");
+    assert!(Season::from_json(&duplicate).is_err());
+}
+
+#[test]
+fn seasons_side_by_side() {
+    let jp2024 = Season::jp2024();
+    let test_season = Season::from_json(TEST_SEASON_JSON).unwrap();
+    // 同じ番号でもシーズンごとに別のチーム
+    let jp_team = jp2024.get_teams(&SflStage::all_division())[0];
+    let test_team = test_season.get_teams(&SflStage::all_division())[0];
+    assert_eq!(jp_team, test_team);
+    assert_eq!(jp2024.get_team_code(&jp_team), "G8S");
+    assert_eq!(test_season.get_team_code(&test_team), "AAA");
+}
+
+// 1周で先に30ポイントを取ったら勝ち。20-20なら延長戦
+const KNOCKOUT_FORMAT_JSON: &str = r#"{"win_threshold": 30, "legs": [
+    {"game_type": "VAN", "sets": 3, "point": 10},
+    {"game_type": "MID", "sets": 3, "point": 10},
+    {"game_type": "GENERAL", "sets": 5, "point": 20},
+    {"game_type": "PlayoffExtra", "sets": 3, "point": 10, "condition": "Tied"}
+]}"#;
+
+fn season_with_playoff_format(playoff: &str) -> String {
+    let formats = format!(
+        r#""formats": {{"division": {}, "playoff": {}, "grand_final": {}}},"#,
+        KNOCKOUT_FORMAT_JSON.replace("\"win_threshold\": 30, ", ""),
+        playoff,
+        KNOCKOUT_FORMAT_JSON
+    );
+    TEST_SEASON_JSON.replacen(
+        "\"divisions\"",
+        &format!("{}\n    \"divisions\"", formats),
+        1,
+    )
+}
+
+#[test]
+fn invalid_formats_are_rejected() {
+    let season = Season::from_json(&season_with_playoff_format(KNOCKOUT_FORMAT_JSON)).unwrap();
+    assert_eq!(season.formats.playoff.win_threshold, Some(30));
+    assert_eq!(season.formats.division.win_threshold, None);
+
+    let invalid_formats = [
+        // レグがない
+        r#"{"win_threshold": 30, "legs": []}"#.to_string(),
+        // セット数が偶数、0
+        KNOCKOUT_FORMAT_JSON.replace("\"sets\": 5", "\"sets\": 4"),
+        KNOCKOUT_FORMAT_JSON.replace("\"sets\": 5", "\"sets\": 0"),
+        // 20-20で終わってしまう
+        KNOCKOUT_FORMAT_JSON
+            .replace(", \"condition\": \"Tied\"", "")
+            .replace(
+                ",\n    {\"game_type\": \"PlayoffExtra\", \"sets\": 3, \"point\": 10}",
+                "",
+            ),
+        // 勝者を決める試合形式に win_threshold がない
+        KNOCKOUT_FORMAT_JSON.replace("\"win_threshold\": 30, ", ""),
+        // 到達できない
+        KNOCKOUT_FORMAT_JSON.replace("\"win_threshold\": 30", "\"win_threshold\": 60"),
+        KNOCKOUT_FORMAT_JSON.replace("\"win_threshold\": 30", "\"win_threshold\": 0"),
+    ];
+    for playoff in invalid_formats {
+        let error = Season::from_json(&season_with_playoff_format(&playoff)).unwrap_err();
+        assert!(
+            matches!(error, SeasonError::InvalidFormat(_)),
+            "{}: {}",
+            playoff,
+            error
+        );
+    }
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "serialize")]
+
+mod common;
+
+use common::SWEEP;
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{SflRecord, SflStage};
+use sfl_lib::{SflSimulation, SflSimulationResult};
+
+#[test]
+fn record_schema() {
+    let season = Season::jp2024();
+    let record = &season.get_initial_records(&SflStage::all_division())[0][0];
+    let value = serde_json::to_value(record).unwrap();
+    assert_eq!(value["sfl_match"]["team"], 1);
+    assert_eq!(value["sfl_match"]["sfl_stage"]["kind"], "AllDivision");
+    assert_eq!(value["game_type"], "VAN");
+    assert_eq!(value["player"], serde_json::Value::Null);
+    assert_eq!(value["is_prediction"], true);
+
+    let loaded: SflRecord = serde_json::from_value(value).unwrap();
+    assert_eq!(loaded.sfl_match.team, record.sfl_match.team);
+    assert_eq!(loaded.game_type, record.game_type);
+}
+
+#[test]
+fn simulation_result_round_trip() {
+    let mut simulation = SflSimulation::new(false);
+    simulation.count = 100;
+    simulation.set_match_result(0, &SWEEP).unwrap();
+    simulation.simulate();
+    let json = serde_json::to_string_pretty(simulation.get_result()).unwrap();
+    let loaded: SflSimulationResult = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.count, 100);
+    assert_eq!(loaded.seed, simulation.get_used_seed());
+    assert_eq!(serde_json::to_string_pretty(&loaded).unwrap(), json);
+
+    // 足りないフィールドは既定値になる
+    let mut value = serde_json::to_value(&loaded).unwrap();
+    value
+        .as_object_mut()
+        .unwrap()
+        .remove("knockout_match_points");
+    let partial: SflSimulationResult = serde_json::from_value(value).unwrap();
+    assert_eq!(partial.count, 100);
+    assert!(partial.knockout_match_points.is_empty());
+    assert_eq!(partial.match_points, loaded.match_points);
+}
@@ -0,0 +1,216 @@
+#![cfg(feature = "serialize")]
+
+mod common;
+
+use common::SWEEP;
+use rand::prelude::*;
+use sfl_lib::accumulator::{MergeError, PointRange};
+use sfl_lib::season::Season;
+use sfl_lib::sfl::SflStage;
+use sfl_lib::{create_rng, SflSimulation, SflSimulationResult, DEFAULT_SEED, STREAM_COUNT};
+
+#[test]
+fn default_seed_matches_fixed_seed() {
+    // 以前の固定シード [5; 32] と同じ乱数列になる
+    let mut fixed: StdRng = SeedableRng::from_seed([5; 32]);
+    let mut rng = create_rng(DEFAULT_SEED);
+    for _ in 0..100 {
+        assert_eq!(fixed.random::<u64>(), rng.random::<u64>());
+    }
+    let mut other = create_rng(1);
+    assert_ne!(
+        create_rng(DEFAULT_SEED).random::<u64>(),
+        other.random::<u64>()
+    );
+
+    let simulation = SflSimulation::new(true);
+    assert_eq!(simulation.seed, DEFAULT_SEED);
+    assert_eq!(simulation.get_used_seed(), DEFAULT_SEED);
+    assert_eq!(simulation.get_used_count(), 10000);
+}
+
+#[test]
+fn merge_simulation_results() {
+    let current = SflSimulationResult::current_simulated_result();
+    let mut merged = SflSimulationResult::current_simulated_result();
+    merged.merge(&current).unwrap();
+    assert_eq!(merged.count, 20000);
+    assert_eq!(
+        merged.division_place_count[0][0],
+        current.division_place_count[0][0] * 2
+    );
+    assert_eq!(
+        merged.division_place_detail[0][5][2],
+        current.division_place_detail[0][5][2] * 2
+    );
+    // 回数とポイントの合計は足し、最高・最低はそのまま
+    let range = merged.place_to_points[0][0];
+    let current_range = current.place_to_points[0][0];
+    assert_eq!(range.time, current_range.time * 2);
+    assert_eq!(range.sum_point, current_range.sum_point * 2);
+    assert_eq!(range.highest_point, current_range.highest_point);
+    assert_eq!(range.lowest_point, current_range.lowest_point);
+    // 一度もならなかった順位は最高・最低を持たない
+    assert_eq!(merged.place_to_points[1][0], PointRange::default());
+    assert_eq!(
+        merged.division_place_detail_flatten,
+        current.division_place_detail_flatten
+    );
+
+    let mut scaled = SflSimulationResult::current_simulated_result();
+    scaled.scale(2);
+    assert_eq!(scaled.count, merged.count);
+    assert_eq!(scaled.division_place_count, merged.division_place_count);
+    assert_eq!(scaled.place_to_points, merged.place_to_points);
+    merged.summarize(&Season::jp2024());
+    assert_eq!(
+        scaled.division_place_detail_flatten,
+        merged.division_place_detail_flatten
+    );
+    assert_eq!(merged.division_places, current.division_places);
+}
+
+#[test]
+fn merge_rejects_mismatched_results() {
+    let current = SflSimulationResult::current_simulated_result();
+    let mut other = SflSimulationResult::current_simulated_result();
+    other.match_points.pop();
+    let mut merged = SflSimulationResult::current_simulated_result();
+    assert_eq!(
+        merged.merge(&other),
+        Err(MergeError::Shape("match_points".to_string()))
+    );
+    // 途中まで足されていない
+    assert_eq!(merged.count, current.count);
+    assert_eq!(merged.division_place_count, current.division_place_count);
+
+    let mut other = SflSimulationResult::current_simulated_result();
+    other.division_place_detail[0][0].pop();
+    assert_eq!(
+        merged.merge(&other),
+        Err(MergeError::Shape("division_place_detail".to_string()))
+    );
+    assert_eq!(merged.division_place_count, current.division_place_count);
+
+    let mut season = Season::jp2024();
+    season.id = "other".to_string();
+    let mut simulation = SflSimulation::new(true);
+    assert_eq!(
+        simulation.merge_simulation(&SflSimulation::from_season(season)),
+        Err(MergeError::Season {
+            expected: Season::jp2024().id,
+            found: "other".to_string(),
+        })
+    );
+    assert_eq!(simulation.get_result().count, current.count);
+}
+
+#[test]
+fn zero_result_is_identity() {
+    let season = Season::jp2024();
+    let current = SflSimulationResult::current_simulated_result();
+    let mut merged = SflSimulationResult::zero(&season);
+    merged.merge(&current).unwrap();
+    assert_eq!(merged.count, current.count);
+    assert_eq!(merged.place_to_points, current.place_to_points);
+    assert_eq!(merged.playoff_place_count, current.playoff_place_count);
+
+    let mut range = PointRange::default();
+    range.merge(&PointRange::default());
+    assert_eq!(range.highest_point, None);
+    range.add(150);
+    range.add(90);
+    assert_eq!(
+        (range.highest_point, range.lowest_point),
+        (Some(150), Some(90))
+    );
+    range.scale(0);
+    assert_eq!(range, PointRange::default());
+}
+
+#[test]
+fn native_simulate_returns_result() {
+    let mut simulation = SflSimulation::new(false);
+    simulation.count = 300;
+    // 先鋒、中堅は3セット、大将は5セット、延長戦は1セット
+    assert_eq!(simulation.get_leg_wins_needed(), vec![2, 2, 3, 1]);
+    simulation.set_match_result(0, &SWEEP).unwrap();
+    assert!(simulation
+        .get_match_records(0)
+        .iter()
+        .all(|record| !record.is_prediction));
+    simulation.simulate();
+    let result = simulation.get_result();
+    assert_eq!(result.count, 300);
+    for count in result.division_place_count.iter() {
+        assert_eq!(count.iter().sum::<u32>(), 300);
+    }
+    // 範囲外は空ではなく None
+    assert_eq!(
+        simulation
+            .get_current_places(&SflStage::division(0))
+            .map(|places| places.len()),
+        Some(6)
+    );
+    assert_eq!(simulation.get_current_places(&SflStage::division(2)), None);
+    assert_eq!(simulation.get_current_places(&SflStage::playoff()), None);
+    assert_eq!(
+        simulation
+            .get_knockout_match_points(4)
+            .map(|points| points.len()),
+        Some(4)
+    );
+    assert_eq!(simulation.get_knockout_match_points(5), None);
+    let division_place_count = result.division_place_count.to_owned();
+    simulation.simulate();
+    assert_eq!(
+        simulation.get_result().division_place_count,
+        division_place_count
+    );
+    assert!(simulation
+        .get_simulation_snapshot()
+        .starts_with("{\"version\":2,"));
+
+    // 決着した試合は最後のセットまでで切っても終わった試合になる
+    simulation.set_match_result(0, &SWEEP[..9]).unwrap();
+    assert!(simulation
+        .get_match_records(0)
+        .iter()
+        .all(|record| !record.is_prediction));
+    assert_ne!(simulation.find_match_index("DFM", "OJA"), Some(0));
+
+    // 入力が足りないセットは予想に戻る
+    simulation.set_match_result(0, &[]).unwrap();
+    assert!(simulation
+        .get_match_records(0)
+        .iter()
+        .all(|record| record.is_prediction));
+}
+
+#[test]
+fn streams_merge_to_simulate_result() {
+    // 系列ごとの回数が揃わない回数にする
+    let create = || {
+        let mut simulation = SflSimulation::new(false);
+        simulation.count = 150;
+        simulation.option.exact_state_limit = 0;
+        simulation.set_match_result(0, &SWEEP).unwrap();
+        simulation
+    };
+    let mut simulation = create();
+    simulation.simulate();
+
+    let mut merged = create();
+    merged.simulate_stream(0);
+    for stream in 1..STREAM_COUNT {
+        let mut other = create();
+        other.simulate_stream(stream);
+        merged.merge_simulation(&other).unwrap();
+    }
+    let (result, merged) = (simulation.get_result(), merged.get_result());
+    assert_eq!(merged.count, 150);
+    assert_eq!(merged.division_place_count, result.division_place_count);
+    assert_eq!(merged.playoff_place_count, result.playoff_place_count);
+    assert_eq!(merged.match_points, result.match_points);
+    assert_eq!(merged.place_to_points, result.place_to_points);
+}
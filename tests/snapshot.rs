@@ -0,0 +1,219 @@
+#![cfg(feature = "serialize")]
+
+mod common;
+
+use common::SWEEP;
+use sfl_lib::model::WinProbabilityModel;
+use sfl_lib::season::Season;
+use sfl_lib::sfl::{SflRatingSetting, SflRecord, SflStage};
+use sfl_lib::snapshot::{get_input_hash, SimulationSnapshot, SnapshotError, SNAPSHOT_VERSION};
+use sfl_lib::{SflRating, SflSimulation};
+
+// 中身がわからないモデル
+struct EvenModel;
+
+impl WinProbabilityModel for EvenModel {
+    fn fit(&mut self, _: &Season, _: &SflStage, _: &[Vec<SflRecord>]) {}
+    fn get_team_win_percentage(&self, _: &SflRecord) -> Option<f64> {
+        Some(0.5_f64)
+    }
+}
+
+fn simulate(count: usize) -> SflSimulation {
+    let mut simulation = SflSimulation::new(false);
+    simulation.count = count;
+    simulation.option.exact_state_limit = 0;
+    simulation.set_match_result(0, &SWEEP).unwrap();
+    simulation.simulate();
+    simulation
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let simulation = simulate(200);
+    let json = simulation.get_simulation_snapshot();
+
+    let mut loaded = SflSimulation::new(false);
+    loaded.load_simulation_snapshot(&json).unwrap();
+    assert_eq!(loaded.get_used_count(), 200);
+    assert_eq!(
+        loaded.get_result().division_place_count,
+        simulation.get_result().division_place_count
+    );
+    assert_eq!(
+        loaded.get_result().playoff_places,
+        simulation.get_result().playoff_places
+    );
+
+    // ArrayBuffer やファイルから読んでも同じ
+    let mut from_bytes = SflSimulation::new(false);
+    from_bytes
+        .load_simulation_snapshot_bytes(json.as_bytes())
+        .unwrap();
+    assert_eq!(
+        from_bytes.get_result().place_to_points,
+        simulation.get_result().place_to_points
+    );
+    let path = std::env::temp_dir().join(format!("sfl_snapshot_{}.json", std::process::id()));
+    std::fs::write(&path, &json).unwrap();
+    let mut from_file = SflSimulation::new(false);
+    from_file.load_simulation_snapshot_file(&path).unwrap();
+    assert_eq!(
+        from_file.get_result().match_points,
+        simulation.get_result().match_points
+    );
+}
+
+#[test]
+fn stale_snapshot_is_detected() {
+    let mut simulation = simulate(100);
+    assert!(!simulation.is_result_stale());
+    let json = simulation.get_simulation_snapshot();
+
+    // 同じ結果を入力したシミュレーションでは新しいまま
+    let mut loaded = SflSimulation::new(false);
+    assert!(loaded.is_result_stale());
+    loaded.set_match_result(0, &SWEEP).unwrap();
+    loaded.load_simulation_snapshot(&json).unwrap();
+    assert!(!loaded.is_result_stale());
+
+    // 結果やレーティングの設定が変わると古くなる
+    let home_sweep: Vec<Option<bool>> = SWEEP
+        .iter()
+        .map(|result| result.map(|win_flag| !win_flag))
+        .collect();
+    loaded.set_match_result(1, &home_sweep).unwrap();
+    assert!(loaded.is_result_stale());
+    loaded.set_match_result(1, &[]).unwrap();
+    assert!(!loaded.is_result_stale());
+    loaded.enable_rate(false);
+    assert!(loaded.is_result_stale());
+
+    simulation.set_match_result(0, &[]).unwrap();
+    assert!(simulation.is_result_stale());
+    simulation.simulate();
+    assert!(!simulation.is_result_stale());
+
+    // 同梱の結果は何も入力していない状態のものではない
+    assert!(SflSimulation::new(true).is_result_stale());
+}
+
+#[test]
+fn input_hash_covers_season_and_skipped_sets() {
+    let json = include_str!("../seasons/jp2024.json");
+    let hash = |json: &str| {
+        SflSimulation::from_season_json(json)
+            .unwrap()
+            .get_input_hash()
+    };
+    let base = hash(json);
+    assert_eq!(hash(json), base);
+    // 日程や試合形式が違えば、結果を入力していなくても違う入力
+    let swapped = json.replacen("\"section\": 1,", "\"section\": 11,", 1);
+    assert_ne!(hash(&swapped), base);
+    let playoff_teams = json.replacen("\"divisions\":", "\"playoff_teams\": 2, \"divisions\":", 1);
+    assert_ne!(hash(&playoff_teams), base);
+
+    // 行われなかったセットか、負けたセットか
+    let simulation = simulate(10);
+    let mut sfl_records: Vec<Vec<SflRecord>> = (0..simulation.get_matches().len())
+        .map(|index| simulation.get_match_records(index))
+        .collect();
+    let season = simulation.get_season().to_owned();
+    let hash = |sfl_records: &[Vec<SflRecord>]| {
+        get_input_hash(
+            &season,
+            sfl_records,
+            true,
+            &SflRating::with_setting(SflRatingSetting::TeamOnly),
+            None,
+        )
+    };
+    let played = hash(&sfl_records);
+    assert!(!sfl_records[0][2].is_valid);
+    sfl_records[0][2].is_valid = true;
+    assert_ne!(hash(&sfl_records), played);
+}
+
+#[test]
+fn input_hash_covers_prior_and_model() {
+    let mut simulation = simulate(10);
+    let base = simulation.get_input_hash();
+    // 事前のレーティングと回帰の割合
+    let snapshot = simulation.get_rating_snapshot();
+    simulation.set_rating_prior(&snapshot, 0.5).unwrap();
+    let prior = simulation.get_input_hash();
+    assert_ne!(prior, base);
+    simulation.set_rating_prior(&snapshot, 0.25).unwrap();
+    assert_ne!(simulation.get_input_hash(), prior);
+    simulation.clear_rating_prior();
+    assert_eq!(simulation.get_input_hash(), base);
+
+    // 設定で表せるモデルは同じ設定なら同じ
+    simulation.set_bradley_terry_model(simulation.fit_bradley_terry(true, false, None));
+    let bradley_terry = simulation.get_input_hash();
+    assert_ne!(bradley_terry, base);
+    simulation.set_bradley_terry_model(simulation.fit_bradley_terry(true, false, None));
+    assert_eq!(simulation.get_input_hash(), bradley_terry);
+    simulation.set_bradley_terry_model(simulation.fit_bradley_terry(true, true, None));
+    assert_ne!(simulation.get_input_hash(), bradley_terry);
+
+    // 中身がわからないモデルは差し替えるたびに古い結果になる
+    simulation.set_win_probability_model(Box::new(EvenModel));
+    let even = simulation.get_input_hash();
+    simulation.set_win_probability_model(Box::new(EvenModel));
+    assert_ne!(simulation.get_input_hash(), even);
+    simulation.clear_win_probability_model();
+    assert_eq!(simulation.get_input_hash(), base);
+    assert!(!simulation.is_result_stale());
+}
+
+#[test]
+fn invalid_snapshot_is_error() {
+    let simulation = simulate(10);
+    let mut snapshot = simulation.to_simulation_snapshot();
+    snapshot.version = SNAPSHOT_VERSION + 1;
+    assert!(matches!(
+        SimulationSnapshot::from_json(&snapshot.to_json()),
+        Err(SnapshotError::Version { .. })
+    ));
+
+    let mut snapshot = simulation.to_simulation_snapshot();
+    snapshot.season_id = "other".to_string();
+    let mut loaded = SflSimulation::new(false);
+    assert!(matches!(
+        loaded.apply_simulation_snapshot(snapshot),
+        Err(SnapshotError::Season { .. })
+    ));
+
+    let mut snapshot = simulation.to_simulation_snapshot();
+    snapshot.result.match_points.pop();
+    assert!(matches!(
+        loaded.apply_simulation_snapshot(snapshot),
+        Err(SnapshotError::Shape(_))
+    ));
+    let mut snapshot = simulation.to_simulation_snapshot();
+    snapshot.count += 1;
+    assert!(matches!(
+        loaded.apply_simulation_snapshot(snapshot),
+        Err(SnapshotError::Shape(_))
+    ));
+    // 古いスナップショットにないフィールドは回数0で読み込む
+    let mut value: serde_json::Value =
+        serde_json::from_str(&simulation.get_simulation_snapshot()).unwrap();
+    value["result"]
+        .as_object_mut()
+        .unwrap()
+        .remove("knockout_match_points");
+    let mut partial = SflSimulation::new(false);
+    partial
+        .load_simulation_snapshot(&value.to_string())
+        .unwrap();
+    assert_eq!(partial.get_used_count(), 10);
+    assert_eq!(partial.get_knockout_match_points(0), Some(vec![0; 4]));
+
+    assert!(loaded.load_simulation_snapshot("{}").is_err());
+    assert!(loaded.load_simulation_snapshot_bytes(&[0xff]).is_err());
+    // 読み込めなかった場合は元の結果のまま
+    assert_eq!(loaded.get_used_count(), 0);
+}